@@ -0,0 +1,81 @@
+use anyhow::{Context, Result};
+use std::io::{Read, Write};
+use std::net::{IpAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+const WHOIS_SERVER: &str = "whois.cymru.com:43";
+
+/// The network each address is announced from and the country it's registered in, like
+/// "AS13335 CLOUDFLARENET (US)", looked up with Team Cymru's whois service. Addresses that aren't
+/// announced, such as private ones, have `None`.
+pub fn lookup(ips: &[IpAddr]) -> Result<Vec<Option<String>>> {
+    let server = WHOIS_SERVER
+        .to_socket_addrs()
+        .context("Error resolving whois.cymru.com")?
+        .next()
+        .context("Error resolving whois.cymru.com")?;
+    let mut stream = TcpStream::connect_timeout(&server, Duration::from_secs(5))
+        .context("Error connecting to whois.cymru.com")?;
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    // Bulk mode answers every address at once
+    let mut query = "begin\nverbose\n".to_string();
+    for ip in ips {
+        query.push_str(&format!("{ip}\n"));
+    }
+    query.push_str("end\n");
+    stream.write_all(query.as_bytes())?;
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .context("Error reading from whois.cymru.com")?;
+    let networks = parse_response(&response);
+    Ok(ips
+        .iter()
+        .map(|ip| {
+            networks
+                .iter()
+                .find(|(network_ip, _)| network_ip == ip)
+                .map(|(_, network)| network.clone())
+        })
+        .collect())
+}
+
+/// The address and network of each line of a verbose bulk response, which are like
+/// "13335 | 1.1.1.1 | 1.1.1.0/24 | US | arin | 2010-07-14 | CLOUDFLARENET, US".
+fn parse_response(response: &str) -> Vec<(IpAddr, String)> {
+    response
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split('|').map(str::trim).collect::<Vec<_>>();
+            let [asn, ip, _, country, _, _, name] = fields[..] else {
+                return None;
+            };
+            let ip = ip.parse().ok()?;
+            if asn.parse::<u32>().is_err() {
+                return None;
+            }
+            // The name ends with the country of the organisation, which is usually the same
+            let name = name.rsplit_once(", ").map_or(name, |(name, _)| name);
+            Some((ip, format!("AS{asn} {name} ({country})")))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        let response = "Bulk mode; whois.cymru.com [2024-01-01 00:00:00 +0000]
+13335   | 1.1.1.1          | 1.1.1.0/24          | US | arin     | 2010-07-14 | CLOUDFLARENET, US
+NA      | 10.0.0.1         | NA                  |    | other    |            | NA";
+        assert_eq!(
+            parse_response(response),
+            [(
+                "1.1.1.1".parse().unwrap(),
+                "AS13335 CLOUDFLARENET (US)".to_string()
+            )]
+        );
+    }
+}
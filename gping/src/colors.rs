@@ -1,11 +1,95 @@
-use std::{iter::Iterator, ops::RangeFrom, str::FromStr};
+use std::{iter::Iterator, ops::RangeFrom, slice, str::FromStr};
 
 use anyhow::{anyhow, Result};
-use tui::style::Color;
+use clap::ValueEnum;
+use tui::style::{Color, Modifier};
+
+/// A set of line colors, and the colors and styles used for the rest of the graph.
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+pub enum Theme {
+    /// The terminal's indexed colors.
+    #[default]
+    Default,
+    /// The Okabe-Ito palette, which can be told apart with any color vision deficiency.
+    Colorblind,
+    /// Bright colors and bold headers.
+    HighContrast,
+    /// The accent colors of Solarized.
+    Solarized,
+}
+
+impl Theme {
+    /// The colors given to hosts in turn. Once they run out, the terminal's indexed colors are used.
+    fn palette(self) -> &'static [Color] {
+        match self {
+            Theme::Default => &[],
+            Theme::Colorblind => &[
+                Color::Rgb(0xe6, 0x9f, 0x00),
+                Color::Rgb(0x56, 0xb4, 0xe9),
+                Color::Rgb(0x00, 0x9e, 0x73),
+                Color::Rgb(0xf0, 0xe4, 0x42),
+                Color::Rgb(0x00, 0x72, 0xb2),
+                Color::Rgb(0xd5, 0x5e, 0x00),
+                Color::Rgb(0xcc, 0x79, 0xa7),
+            ],
+            Theme::HighContrast => &[
+                Color::LightYellow,
+                Color::LightCyan,
+                Color::LightMagenta,
+                Color::LightGreen,
+                Color::LightRed,
+                Color::LightBlue,
+                Color::White,
+            ],
+            Theme::Solarized => &[
+                Color::Rgb(0xb5, 0x89, 0x00),
+                Color::Rgb(0x26, 0x8b, 0xd2),
+                Color::Rgb(0x85, 0x99, 0x00),
+                Color::Rgb(0xd3, 0x36, 0x82),
+                Color::Rgb(0x2a, 0xa1, 0x98),
+                Color::Rgb(0xcb, 0x4b, 0x16),
+                Color::Rgb(0x6c, 0x71, 0xc4),
+                Color::Rgb(0xdc, 0x32, 0x2f),
+            ],
+        }
+    }
+
+    pub fn axis(self) -> Color {
+        match self {
+            Theme::Default | Theme::Colorblind => Color::Gray,
+            Theme::HighContrast => Color::White,
+            Theme::Solarized => Color::Rgb(0x93, 0xa1, 0xa1),
+        }
+    }
+
+    /// For text and highlights that stay in the background, like the status bar.
+    pub fn muted(self) -> Color {
+        match self {
+            Theme::Default | Theme::Colorblind => Color::DarkGray,
+            Theme::HighContrast => Color::Gray,
+            Theme::Solarized => Color::Rgb(0x58, 0x6e, 0x75),
+        }
+    }
+
+    /// Added to the style of the stats for each host.
+    pub fn header(self) -> Modifier {
+        match self {
+            Theme::HighContrast => Modifier::BOLD,
+            _ => Modifier::empty(),
+        }
+    }
+}
+
+/// Parse a color name or a `#RRGGBB` hex code.
+pub fn parse_color(name: &str) -> Result<Color> {
+    Color::from_str(name)
+        .map_err(|err| anyhow!(err).context(format!("Invalid color code: `{}`", name)))
+}
 
 pub struct Colors<T> {
     already_used: Vec<Color>,
     color_names: T,
+    palette: slice::Iter<'static, Color>,
     indices: RangeFrom<u8>,
 }
 
@@ -14,11 +98,20 @@ impl<T> From<T> for Colors<T> {
         Self {
             already_used: Vec::new(),
             color_names,
+            palette: [].iter(),
             indices: 2..,
         }
     }
 }
 
+impl<T> Colors<T> {
+    /// Use the theme's palette for hosts without a color of their own.
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.palette = theme.palette().iter();
+        self
+    }
+}
+
 impl<'a, T> Iterator for Colors<T>
 where
     T: Iterator<Item = &'a String>,
@@ -27,18 +120,19 @@ where
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.color_names.next() {
-            Some(name) => match Color::from_str(name) {
-                Ok(color) => {
+            Some(name) => Some(parse_color(name).inspect(|color| {
+                if !self.already_used.contains(color) {
+                    self.already_used.push(*color);
+                }
+            })),
+            None => loop {
+                if let Some(&color) = self.palette.next() {
                     if !self.already_used.contains(&color) {
                         self.already_used.push(color);
+                        break Some(Ok(color));
                     }
-                    Some(Ok(color))
+                    continue;
                 }
-                error => Some(error.map_err(|err| {
-                    anyhow!(err).context(format!("Invalid color code: `{}`", name))
-                })),
-            },
-            None => loop {
                 let index = unsafe { self.indices.next().unwrap_unchecked() };
                 let color = Color::Indexed(index);
                 if !self.already_used.contains(&color) {
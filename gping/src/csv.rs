@@ -0,0 +1,167 @@
+use crate::plot_data::{Entry, HostNames, Marker, Sample};
+use crate::replay::{rtt_from_ms, Record};
+use anyhow::{bail, Context, Result};
+use chrono::prelude::*;
+use std::convert::TryInto;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, BufWriter, LineWriter, Write};
+use std::path::Path;
+use std::sync::mpsc;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::thread::JoinHandle;
+
+const HEADER: &[u8] = b"timestamp,target,rtt_ms\n";
+
+/// Quote a field if it contains anything that would break the row, such as a comma in a command.
+fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Split a row into its fields, undoing the quoting done by `field`.
+fn split_row(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+/// A marker is written in place of a sample, with its label as the target and "marker" as the
+/// round trip time.
+fn marker_row(marker: &Marker) -> String {
+    format!(
+        "{},{},marker\n",
+        marker.at.to_rfc3339_opts(SecondsFormat::Millis, false),
+        field(&marker.label)
+    )
+}
+
+fn row(sample: &Sample, hosts: &[String]) -> String {
+    let rtt = match sample.rtt {
+        Some(rtt) => format!("{:.3}", rtt.as_secs_f64() * 1000.0),
+        None => "timeout".to_string(),
+    };
+    format!(
+        "{},{},{}\n",
+        sample.at.to_rfc3339_opts(SecondsFormat::Millis, false),
+        field(&hosts[sample.host_id]),
+        rtt
+    )
+}
+
+/// Append a row to the CSV file at `path` for every sample and marker sent to the returned
+/// channel, from a background thread. The header is written if the file is new. The thread exits
+/// once the sender is dropped.
+pub fn start_writer(
+    path: &Path,
+    hosts: HostNames,
+) -> Result<(Sender<Entry>, JoinHandle<Result<()>>)> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Error opening CSV file {}", path.display()))?;
+    let is_empty = file.metadata()?.len() == 0;
+    // Rows are flushed as they are written, so the file can be read while gping runs.
+    let mut writer = LineWriter::new(file);
+    if is_empty {
+        writer.write_all(HEADER)?;
+    }
+
+    let (tx, rx) = mpsc::channel::<Entry>();
+    let thread = thread::spawn(move || -> Result<()> {
+        for entry in rx {
+            let row = match entry {
+                Entry::Sample(sample) => row(&sample, &hosts.read().unwrap()),
+                Entry::Marker(marker) => marker_row(&marker),
+            };
+            writer.write_all(row.as_bytes())?;
+        }
+        Ok(())
+    });
+    Ok((tx, thread))
+}
+
+/// Write `samples` and `markers` to a new CSV file at `path`, in the format `start_writer` uses.
+pub fn write(path: &Path, hosts: &[String], samples: &[Sample], markers: &[Marker]) -> Result<()> {
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Error creating CSV file {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(HEADER)?;
+    for sample in samples {
+        writer.write_all(row(sample, hosts).as_bytes())?;
+    }
+    for marker in markers {
+        writer.write_all(marker_row(marker).as_bytes())?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read back the samples and markers in a CSV file written by `start_writer`.
+pub fn read(path: &Path) -> Result<(Vec<Record>, Vec<Marker>)> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Error opening CSV file {}", path.display()))?;
+    let mut records = vec![];
+    let mut markers = vec![];
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.is_empty() || (number == 0 && line.starts_with("timestamp,")) {
+            continue;
+        }
+        let [timestamp, target, rtt]: [String; 3] = match split_row(&line).try_into() {
+            Ok(fields) => fields,
+            Err(_) => bail!("Expected 3 fields on line {}: {line}", number + 1),
+        };
+        let at = DateTime::parse_from_rfc3339(&timestamp)
+            .with_context(|| format!("Invalid timestamp on line {}", number + 1))?
+            .with_timezone(&Local);
+        if rtt == "marker" {
+            markers.push(Marker { at, label: target });
+            continue;
+        }
+        records.push(Record {
+            at,
+            target,
+            rtt: match rtt.as_str() {
+                "timeout" => None,
+                ms => Some(rtt_from_ms(ms.parse().with_context(|| {
+                    format!("Invalid round trip time on line {}", number + 1)
+                })?)?),
+            },
+        });
+    }
+    Ok((records, markers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_row() {
+        let target = "sh -c \"sleep 0.1, true\"";
+        assert_eq!(
+            split_row(&format!(
+                "2024-01-01T12:00:00.000+00:00,{},1.000",
+                field(target)
+            )),
+            vec!["2024-01-01T12:00:00.000+00:00", target, "1.000"]
+        );
+    }
+}
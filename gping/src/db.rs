@@ -0,0 +1,152 @@
+use crate::plot_data::{Entry, HostNames, Marker};
+use crate::replay::{rtt_from_ms, Record};
+use anyhow::{Context, Result};
+use chrono::prelude::*;
+use rusqlite::{params, Connection, OpenFlags};
+use std::path::Path;
+use std::sync::mpsc;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::thread::JoinHandle;
+
+// Timestamps are stored in UTC in the format SQLite's date functions expect, so that they sort
+// and can be compared with `datetime()`.
+pub const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS sessions (
+    id INTEGER PRIMARY KEY,
+    started TEXT NOT NULL,
+    version TEXT NOT NULL,
+    arguments TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS samples (
+    session_id INTEGER NOT NULL REFERENCES sessions (id),
+    target TEXT NOT NULL,
+    timestamp TEXT NOT NULL,
+    -- NULL for timeouts
+    rtt_ms REAL
+);
+CREATE INDEX IF NOT EXISTS samples_by_target ON samples (target, timestamp);
+CREATE TABLE IF NOT EXISTS markers (
+    session_id INTEGER NOT NULL REFERENCES sessions (id),
+    timestamp TEXT NOT NULL,
+    label TEXT NOT NULL
+);
+";
+
+/// Record a session in the SQLite database at `path`, and every sample and marker sent to the
+/// returned channel, from a background thread. The tables are created if they don't exist, so one database
+/// can hold many sessions. The thread exits once the sender is dropped.
+pub fn start_writer(
+    path: &Path,
+    hosts: HostNames,
+    version: &str,
+) -> Result<(Sender<Entry>, JoinHandle<Result<()>>)> {
+    let connection = Connection::open(path)
+        .with_context(|| format!("Error opening database {}", path.display()))?;
+    connection.pragma_update(None, "journal_mode", "WAL")?;
+    connection.execute_batch(SCHEMA)?;
+    let arguments = std::env::args().skip(1).collect::<Vec<_>>().join(" ");
+    connection.execute(
+        "INSERT INTO sessions (started, version, arguments) VALUES (?1, ?2, ?3)",
+        params![
+            Utc::now().format(TIMESTAMP_FORMAT).to_string(),
+            version,
+            arguments
+        ],
+    )?;
+    let session_id = connection.last_insert_rowid();
+
+    let (tx, rx) = mpsc::channel::<Entry>();
+    let thread = thread::spawn(move || -> Result<()> {
+        let mut insert = connection.prepare(
+            "INSERT INTO samples (session_id, target, timestamp, rtt_ms) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        let mut insert_marker = connection
+            .prepare("INSERT INTO markers (session_id, timestamp, label) VALUES (?1, ?2, ?3)")?;
+        let timestamp =
+            |at: DateTime<Local>| at.with_timezone(&Utc).format(TIMESTAMP_FORMAT).to_string();
+        for entry in rx {
+            match entry {
+                Entry::Sample(sample) => {
+                    let target = hosts.read().unwrap()[sample.host_id].clone();
+                    insert.execute(params![
+                        session_id,
+                        target,
+                        timestamp(sample.at),
+                        sample.rtt.map(|rtt| rtt.as_secs_f64() * 1000.0),
+                    ])?;
+                }
+                Entry::Marker(marker) => {
+                    insert_marker.execute(params![
+                        session_id,
+                        timestamp(marker.at),
+                        marker.label
+                    ])?;
+                }
+            }
+        }
+        Ok(())
+    });
+    Ok((tx, thread))
+}
+
+fn parse_timestamp(timestamp: &str) -> Result<DateTime<Local>> {
+    Ok(NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .with_context(|| format!("Invalid timestamp {timestamp}"))?
+        .and_utc()
+        .with_timezone(&Local))
+}
+
+/// Read back the samples and markers of the most recent session in the database at `path`.
+pub fn read_latest_session(path: &Path) -> Result<(Vec<Record>, Vec<Marker>)> {
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Error opening database {}", path.display()))?;
+    let mut query = connection.prepare(
+        "SELECT target, timestamp, rtt_ms FROM samples
+         WHERE session_id = (SELECT max(id) FROM sessions)
+         ORDER BY timestamp",
+    )?;
+    let rows = query.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<f64>>(2)?,
+        ))
+    })?;
+    let records = rows
+        .map(|row| {
+            let (target, timestamp, rtt_ms) = row?;
+            Ok(Record {
+                at: parse_timestamp(&timestamp)?,
+                target,
+                rtt: rtt_ms.map(rtt_from_ms).transpose()?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Databases written before markers were added don't have the table
+    let has_markers = connection
+        .prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'markers'")?
+        .exists([])?;
+    let mut markers = vec![];
+    if has_markers {
+        let mut query = connection.prepare(
+            "SELECT timestamp, label FROM markers
+             WHERE session_id = (SELECT max(id) FROM sessions)
+             ORDER BY timestamp",
+        )?;
+        let rows = query.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (timestamp, label) = row?;
+            markers.push(Marker {
+                at: parse_timestamp(&timestamp)?,
+                label,
+            });
+        }
+    }
+    Ok((records, markers))
+}
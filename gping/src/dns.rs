@@ -0,0 +1,81 @@
+use anyhow::{bail, Context, Result};
+#[cfg(target_os = "windows")]
+use std::process::Command;
+
+/// The DNS servers the system is configured to use, in the order it tries them.
+pub fn resolvers() -> Result<Vec<String>> {
+    let mut resolvers = find_resolvers()?;
+    let mut seen = vec![];
+    resolvers.retain(|resolver| {
+        let new = !seen.contains(resolver);
+        seen.push(resolver.clone());
+        new
+    });
+    if resolvers.is_empty() {
+        bail!("Could not find any configured DNS servers");
+    }
+    Ok(resolvers)
+}
+
+#[cfg(target_os = "windows")]
+fn find_resolvers() -> Result<Vec<String>> {
+    let output = Command::new("powershell")
+        .args([
+            "-NoProfile",
+            "-NonInteractive",
+            "-Command",
+            "(Get-DnsClientServerAddress).ServerAddresses",
+        ])
+        .output()
+        .context("Error running powershell")?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(not(target_os = "windows"))]
+fn find_resolvers() -> Result<Vec<String>> {
+    let config =
+        std::fs::read_to_string("/etc/resolv.conf").context("Error reading /etc/resolv.conf")?;
+    let resolvers = parse_resolv_conf(&config);
+    // With systemd-resolved, resolv.conf points at a local stub, which would always reply
+    // instantly. The servers it forwards to are in a file of their own.
+    if resolvers.iter().all(|resolver| resolver == "127.0.0.53") {
+        if let Ok(config) = std::fs::read_to_string("/run/systemd/resolve/resolv.conf") {
+            return Ok(parse_resolv_conf(&config));
+        }
+    }
+    Ok(resolvers)
+}
+
+/// The servers on the "nameserver" lines of a resolv.conf file.
+#[cfg(not(target_os = "windows"))]
+fn parse_resolv_conf(config: &str) -> Vec<String> {
+    config
+        .lines()
+        .filter_map(
+            |line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["nameserver", server, ..] => Some(server.to_string()),
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+#[cfg(all(test, not(target_os = "windows")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_resolv_conf() {
+        let config = "# Generated by NetworkManager
+search example.com
+nameserver 192.0.2.53
+nameserver  2001:db8::53
+options edns0";
+        assert_eq!(parse_resolv_conf(config), ["192.0.2.53", "2001:db8::53"]);
+    }
+}
@@ -0,0 +1,99 @@
+use anyhow::{anyhow, Context, Result};
+use std::net::IpAddr;
+#[cfg(not(target_os = "linux"))]
+use std::process::Command;
+
+/// The address of the default gateway, usually the local router.
+pub fn default_gateway() -> Result<IpAddr> {
+    find_gateway()?.ok_or_else(|| anyhow!("Could not find the default gateway. Is the network up?"))
+}
+
+#[cfg(target_os = "linux")]
+fn find_gateway() -> Result<Option<IpAddr>> {
+    let routes =
+        std::fs::read_to_string("/proc/net/route").context("Error reading /proc/net/route")?;
+    if let Some(gateway) = parse_route_table(&routes) {
+        return Ok(Some(gateway));
+    }
+    // Networks with only IPv6 have no IPv4 default route
+    let routes = std::fs::read_to_string("/proc/net/ipv6_route").unwrap_or_default();
+    Ok(parse_ipv6_route_table(&routes))
+}
+
+#[cfg(target_os = "windows")]
+fn find_gateway() -> Result<Option<IpAddr>> {
+    let output = Command::new("route")
+        .args(["print", "0.0.0.0"])
+        .output()
+        .context("Error running route")?;
+    // Lines of the active routes are like "0.0.0.0  0.0.0.0  192.168.1.1  192.168.1.20  25"
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(
+            |line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                ["0.0.0.0", "0.0.0.0", gateway, _, _] => gateway.parse().ok(),
+                _ => None,
+            },
+        ))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn find_gateway() -> Result<Option<IpAddr>> {
+    let output = Command::new("route")
+        .args(["-n", "get", "default"])
+        .output()
+        .context("Error running route")?;
+    // The gateway is on a line like "    gateway: 192.168.1.1"
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("gateway:"))
+        .and_then(|gateway| gateway.trim().parse().ok()))
+}
+
+/// The gateway of the default route in the contents of /proc/net/route, where addresses are
+/// written as hex in the machine's byte order.
+#[cfg(target_os = "linux")]
+fn parse_route_table(routes: &str) -> Option<IpAddr> {
+    // Set on routes that go through a gateway
+    const RTF_GATEWAY: u16 = 0x2;
+    routes.lines().skip(1).find_map(|line| {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        let (destination, gateway, flags) = (fields.get(1)?, fields.get(2)?, fields.get(3)?);
+        let flags = u16::from_str_radix(flags, 16).ok()?;
+        if *destination != "00000000" || flags & RTF_GATEWAY == 0 {
+            return None;
+        }
+        let gateway = u32::from_str_radix(gateway, 16).ok()?;
+        Some(IpAddr::from(gateway.to_ne_bytes()))
+    })
+}
+
+/// The next hop of the default route in the contents of /proc/net/ipv6_route.
+#[cfg(target_os = "linux")]
+fn parse_ipv6_route_table(routes: &str) -> Option<IpAddr> {
+    routes.lines().find_map(|line| {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        let (destination, prefix, next_hop) = (fields.first()?, fields.get(1)?, fields.get(4)?);
+        if destination.chars().any(|c| c != '0') || *prefix != "00" {
+            return None;
+        }
+        let next_hop = u128::from_str_radix(next_hop, 16).ok()?;
+        (next_hop != 0).then(|| IpAddr::from(next_hop.to_be_bytes()))
+    })
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_route_table() {
+        let routes =
+            "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+eth0\t000200C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\t0\t0\t0
+eth0\t00000000\t010200C0\t0003\t0\t0\t0\t00000000\t0\t0\t0";
+        assert_eq!(parse_route_table(routes), "192.0.2.1".parse().ok());
+        let routes = "00000000000000000000000000000000 00 00000000000000000000000000000000 00 fd000000000000000000000000000001 00000400 00000001 00000000 00000003     eth0";
+        assert_eq!(parse_ipv6_route_table(routes), "fd00::1".parse().ok());
+    }
+}
@@ -0,0 +1,328 @@
+use crate::scene::{Canvas, Scene};
+use crate::snapshot;
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use clap::ValueEnum;
+use crossterm::cursor::MoveTo;
+use crossterm::queue;
+use crossterm::style::Print;
+use itertools::Itertools;
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::io::Write;
+use tiny_skia::{FillRule, LineCap, Paint, PathBuilder, Pixmap, Stroke, Transform};
+use tui::layout::Rect;
+use tui::style::Color;
+
+/// The size of a cell in pixels when the terminal doesn't say. Terminals scale images to fit the
+/// cells they're placed over, so this only changes how sharp they are.
+const CELL: (u32, u32) = (10, 20);
+
+/// How much of an image is sent in each escape sequence, which Kitty limits to 4096 bytes.
+const CHUNK: usize = 4096;
+
+/// How charts are drawn with `--graphics`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Graphics {
+    /// With whichever protocol the terminal supports, or with braille if none.
+    Auto,
+    /// With Kitty's graphics protocol, which Kitty, Ghostty, WezTerm and Konsole support.
+    Kitty,
+    /// As iTerm2's inline images, which iTerm2 and WezTerm support.
+    Iterm,
+    /// As sixels, which mlterm, foot, WezTerm and xterm with `-ti 340` support.
+    Sixel,
+}
+
+/// A protocol for terminals to draw images with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Protocol {
+    Kitty,
+    Iterm,
+    Sixel,
+}
+
+impl Graphics {
+    /// The protocol to draw charts with, or `None` if the terminal doesn't support one.
+    pub fn protocol(self) -> Option<Protocol> {
+        match self {
+            Graphics::Auto => detect(),
+            Graphics::Kitty => Some(Protocol::Kitty),
+            Graphics::Iterm => Some(Protocol::Iterm),
+            Graphics::Sixel => Some(Protocol::Sixel),
+        }
+    }
+}
+
+/// Guess the protocol the terminal supports from the variables it sets, as asking it would mean
+/// picking its reply out of the key presses. tmux doesn't pass images through, so isn't guessed.
+fn detect() -> Option<Protocol> {
+    let var = |name| std::env::var(name).unwrap_or_default();
+    if std::env::var_os("TMUX").is_some() {
+        return None;
+    }
+    let (term, program) = (var("TERM"), var("TERM_PROGRAM"));
+    if std::env::var_os("KITTY_WINDOW_ID").is_some()
+        || matches!(term.as_str(), "xterm-kitty" | "xterm-ghostty")
+        || matches!(program.as_str(), "ghostty" | "WezTerm")
+    {
+        Some(Protocol::Kitty)
+    } else if program == "iTerm.app" || var("LC_TERMINAL") == "iTerm2" {
+        Some(Protocol::Iterm)
+    } else if term.starts_with("mlterm") || term.starts_with("foot") {
+        Some(Protocol::Sixel)
+    } else {
+        None
+    }
+}
+
+/// The size of a cell in pixels, if the terminal says how big its window is.
+pub fn cell_size() -> (u32, u32) {
+    match crossterm::terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => (
+            (size.width / size.columns).into(),
+            (size.height / size.rows).into(),
+        ),
+        _ => CELL,
+    }
+}
+
+/// A chart's lines, outages and dots, drawn as an image over the cells of `area` that they would
+/// otherwise be drawn in with braille.
+pub struct Image {
+    pub area: Rect,
+    pub scene: Scene,
+}
+
+fn paint(color: Color, alpha: u8) -> Paint<'static> {
+    let (r, g, b) = snapshot::rgb(color);
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(r, g, b, alpha);
+    paint.anti_alias = true;
+    paint
+}
+
+impl Canvas for Pixmap {
+    fn rect(&mut self, from: (f32, f32), to: (f32, f32), color: Color, alpha: u8) {
+        if let Some(rect) = tiny_skia::Rect::from_ltrb(from.0, from.1, to.0, to.1) {
+            self.fill_rect(rect, &paint(color, alpha), Transform::identity(), None);
+        }
+    }
+
+    fn polyline(&mut self, points: &[(f32, f32)], color: Color, alpha: u8, width: f32) {
+        let mut path = PathBuilder::new();
+        for (idx, (x, y)) in points.iter().enumerate() {
+            match idx {
+                0 => path.move_to(*x, *y),
+                _ => path.line_to(*x, *y),
+            }
+        }
+        let Some(path) = path.finish() else {
+            return;
+        };
+        let stroke = Stroke {
+            width,
+            line_cap: LineCap::Round,
+            ..Stroke::default()
+        };
+        let paint = paint(color, alpha);
+        self.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+    }
+
+    fn circle(&mut self, center: (f32, f32), radius: f32, color: Color) {
+        if let Some(dot) = PathBuilder::from_circle(center.0, center.1, radius) {
+            let paint = paint(color, 255);
+            self.fill_path(&dot, &paint, FillRule::Winding, Transform::identity(), None);
+        }
+    }
+}
+
+impl Image {
+    /// Draw the chart with each cell `cell` pixels in size, over `background` if given.
+    fn pixmap(&self, cell: (u32, u32), background: Option<Color>) -> Result<Pixmap> {
+        let width = self.area.width as u32 * cell.0;
+        let height = self.area.height as u32 * cell.1;
+        let mut pixmap = Pixmap::new(width.max(1), height.max(1)).context("Error drawing chart")?;
+        if let Some(color) = background {
+            let (r, g, b) = snapshot::rgb(color);
+            pixmap.fill(tiny_skia::Color::from_rgba8(r, g, b, 255));
+        }
+        // Lines are as thick as the lines of a braille dot
+        let scale = (cell.0 as f32 / 5.0).max(1.0);
+        self.scene
+            .draw(&mut pixmap, (width as f32, height as f32), scale);
+        Ok(pixmap)
+    }
+
+    /// Draw the chart with each cell `cell` pixels in size, as a PNG.
+    pub fn png(&self, cell: (u32, u32)) -> Result<Vec<u8>> {
+        self.pixmap(cell, self.scene.background)?
+            .encode_png()
+            .context("Error encoding chart")
+    }
+
+    /// Draw the chart with each cell `cell` pixels in size, as sixels. Sixels can't be drawn
+    /// over, so the chart is filled in black without a background color.
+    pub fn sixel(&self, cell: (u32, u32)) -> Result<String> {
+        let background = self.scene.background.unwrap_or(Color::Black);
+        Ok(sixel(&self.pixmap(cell, Some(background))?))
+    }
+
+    /// Write the escape sequences that draw the chart over its area with `protocol`, replacing
+    /// the image with the same `id` drawn before it.
+    pub fn draw(&self, protocol: Protocol, id: usize, out: &mut impl Write) -> Result<()> {
+        let cell = cell_size();
+        let Rect {
+            x,
+            y,
+            width,
+            height,
+            ..
+        } = self.area;
+        queue!(out, MoveTo(x, y))?;
+        match protocol {
+            Protocol::Kitty => {
+                // Drawn under the text, so the legend and markers show over it, without replies
+                // or moving the cursor
+                let png = STANDARD.encode(self.png(cell)?);
+                let chunks = png.as_bytes().chunks(CHUNK).collect::<Vec<_>>();
+                for (idx, chunk) in chunks.iter().enumerate() {
+                    let more = u8::from(idx + 1 < chunks.len());
+                    let chunk = std::str::from_utf8(chunk)?;
+                    let keys = match idx {
+                        0 => format!("a=T,f=100,i={id},q=2,C=1,z=-1,c={width},r={height},"),
+                        _ => String::new(),
+                    };
+                    queue!(out, Print(format!("\x1b_G{keys}m={more};{chunk}\x1b\\")))?;
+                }
+            }
+            Protocol::Iterm => {
+                let png = STANDARD.encode(self.png(cell)?);
+                let keys = format!(
+                    "inline=1;width={width};height={height};preserveAspectRatio=0;doNotMoveCursor=1"
+                );
+                queue!(out, Print(format!("\x1b]1337;File={keys}:{png}\x07")))?;
+            }
+            // Sixels aren't scaled, so are drawn at the size of the cells
+            Protocol::Sixel => {
+                let sixel = self.sixel(cell)?;
+                queue!(out, Print(sixel))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Encode `pixmap` as sixels, in up to 216 colors. Each band of six rows is drawn once for each
+/// color in it, with a sixel for each column that has the color in one of the rows.
+fn sixel(pixmap: &Pixmap) -> String {
+    let (width, height) = (pixmap.width() as usize, pixmap.height() as usize);
+    // Each channel is one of six levels, as in the terminal's 256 color cube
+    let level = |channel: u8| (channel as u16 * 5 + 127) / 255;
+    let colors = pixmap
+        .pixels()
+        .iter()
+        .map(|pixel| {
+            let pixel = pixel.demultiply();
+            level(pixel.red()) * 36 + level(pixel.green()) * 6 + level(pixel.blue())
+        })
+        .collect::<Vec<_>>();
+
+    let mut out = format!("\x1bP0;1;0q\"1;1;{width};{height}");
+    for color in colors.iter().copied().collect::<BTreeSet<_>>() {
+        let percent = |level: u16| level * 100 / 5;
+        let _ = write!(
+            out,
+            "#{color};2;{};{};{}",
+            percent(color / 36),
+            percent(color / 6 % 6),
+            percent(color % 6)
+        );
+    }
+    for top in (0..height).step_by(6) {
+        let rows = top..(top + 6).min(height);
+        let band = rows
+            .clone()
+            .flat_map(|row| &colors[row * width..(row + 1) * width])
+            .copied()
+            .collect::<BTreeSet<_>>();
+        for color in band {
+            let _ = write!(out, "#{color}");
+            let sixels = (0..width).map(|column| {
+                let bits = rows
+                    .clone()
+                    .enumerate()
+                    .filter(|(_, row)| colors[row * width + column] == color)
+                    .fold(0, |bits, (bit, _)| bits | 1 << bit);
+                (63 + bits) as u8 as char
+            });
+            // Repeated sixels are run length encoded
+            for (count, sixel) in sixels.dedup_with_count() {
+                match count {
+                    1..=3 => out.extend(std::iter::repeat_n(sixel, count)),
+                    _ => {
+                        let _ = write!(out, "!{count}{sixel}");
+                    }
+                }
+            }
+            out.push('$');
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Write the escape sequence that removes the images drawn with `protocol` with `ids`, which
+/// only Kitty's protocol has a way to do. Other terminals draw over them with the next frame.
+pub fn remove(
+    protocol: Protocol,
+    ids: impl Iterator<Item = usize>,
+    out: &mut impl Write,
+) -> Result<()> {
+    if protocol == Protocol::Kitty {
+        for id in ids {
+            queue!(out, Print(format!("\x1b_Ga=d,d=I,i={id},q=2\x1b\\")))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tui::style::Style;
+
+    #[test]
+    fn test_png() {
+        let image = Image {
+            area: Rect::new(0, 0, 4, 2),
+            scene: Scene {
+                x_bounds: [0.0, 10.0],
+                y_bounds: [0.0, 100.0],
+                lines: vec![(
+                    Style::default().fg(Color::Red),
+                    vec![(0.0, 10.0), (5.0, f64::NAN), (10.0, 90.0)],
+                )],
+                outages: vec![(4.0, 6.0)],
+                dots: vec![(Color::Red, vec![(5.0, 100.0)])],
+                background: Some(Color::Black),
+            },
+        };
+        let png = image.png((10, 20)).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+        let pixmap = Pixmap::decode_png(&png).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (40, 40));
+    }
+
+    #[test]
+    fn test_sixel() {
+        let mut pixmap = Pixmap::new(4, 7).unwrap();
+        pixmap.fill(tiny_skia::Color::from_rgba8(255, 0, 0, 255));
+        assert_eq!(
+            sixel(&pixmap),
+            "\x1bP0;1;0q\"1;1;4;7#180;2;100;0;0#180!4~$-#180!4@$-\x1b\\"
+        );
+    }
+}
@@ -1,41 +1,64 @@
-use crate::plot_data::PlotData;
+use crate::plot_data::{Entry, HostNames, Marker, PlotData, Sample, Thresholds, Units};
 use anyhow::{anyhow, bail, Context, Result};
+use chrono::format::{Item, StrftimeItems};
 use chrono::prelude::*;
-use clap::{CommandFactory, Parser};
-use crossterm::event::KeyModifiers;
+use clap::{CommandFactory, Parser, ValueEnum};
+use crossterm::event::{
+    DisableMouseCapture, EnableMouseCapture, KeyEvent, KeyModifiers, MouseButton, MouseEvent,
+    MouseEventKind,
+};
 use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
 use crossterm::{
     event::{self, Event as CEvent, KeyCode},
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, SetSize},
+    terminal::{disable_raw_mode, enable_raw_mode},
 };
 use itertools::{Itertools, MinMaxResult};
 use pinger::{ping, PingOptions, PingResult};
+use std::cell::RefCell;
 use std::io;
-use std::io::BufWriter;
+use std::io::{BufWriter, Write};
 use std::iter;
-use std::net::{IpAddr, ToSocketAddrs};
+use std::net::IpAddr;
 use std::ops::Add;
 use std::path::Path;
 use std::process::{Command, ExitStatus, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::Sender;
-use std::sync::{mpsc, Arc};
+use std::sync::mpsc::{RecvTimeoutError, Sender};
+use std::sync::{mpsc, Arc, RwLock};
 use std::thread;
 use std::thread::{sleep, JoinHandle};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 use tui::backend::{Backend, CrosstermBackend};
-use tui::layout::{Constraint, Direction, Flex, Layout};
-use tui::style::{Color, Style};
-use tui::text::Span;
-use tui::widgets::{Axis, Block, Borders, Chart, Dataset};
-use tui::Terminal;
+use tui::layout::{Constraint, Direction, Flex, Layout, Rect};
+use tui::style::{Color, Modifier, Style, Stylize};
+use tui::symbols;
+use tui::text::{Line, Span};
+use tui::widgets::{
+    Axis, Block, Borders, Chart, Clear, Dataset, GraphType, LegendPosition, Paragraph,
+};
+use tui::{Frame, Terminal};
 
+mod asn;
 mod colors;
+mod csv;
+#[cfg(feature = "sqlite")]
+mod db;
+mod dns;
+mod gateway;
+mod graphics;
+mod notify;
 mod plot_data;
+mod prometheus;
 mod region_map;
-
-use colors::Colors;
+mod replay;
+mod scene;
+mod snapshot;
+mod state;
+
+use colors::{Colors, Theme};
+use graphics::{Graphics, Protocol};
+use scene::Scene;
 use shadow_rs::{formatcp, shadow};
 use tui::prelude::Position;
 
@@ -53,37 +76,300 @@ build_env: {},{}"#,
     build::RUST_CHANNEL
 );
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum LayoutMode {
+    Vertical,
+    Horizontal,
+}
+
+/// What is plotted for each host.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum PlotMode {
+    /// The round trip time of each sample
+    Latency,
+    /// The change in round trip time from one sample to the next
+    Jitter,
+    /// Both, with the jitter dimmed
+    Both,
+}
+
+impl PlotMode {
+    fn next(self) -> Self {
+        match self {
+            PlotMode::Latency => PlotMode::Jitter,
+            PlotMode::Jitter => PlotMode::Both,
+            PlotMode::Both => PlotMode::Latency,
+        }
+    }
+}
+
+/// What the panel under the chart shows, with `--loss-chart` or `--dual`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Panel {
+    /// The percentage of probes lost over `--loss-window`
+    Loss,
+    /// The change in round trip time from one sample to the next
+    Jitter,
+}
+
+impl Panel {
+    fn next(self) -> Self {
+        match self {
+            Panel::Loss => Panel::Jitter,
+            Panel::Jitter => Panel::Loss,
+        }
+    }
+}
+
+/// The keys and mouse actions of the graph, shown after the options with --help.
+const KEYS: &str = "\
+Keys:
+  q, Esc          Quit
+  + and -         Zoom the time axis in and out
+  z               Zoom back out to the window set with --buffer
+  } and {         Keep more or less history
+  Left and Right  Scroll back and forward through the history, and End back to the latest
+  Up and Down     Select a host
+  a               Add a host
+  d               Remove the selected host
+  1 to 9          Hide and show the line of each host
+  l               Hide and show the legend
+  j               Plot latency, jitter or both
+  u               Switch the units of round trip times
+  Tab             Switch the lower panel of --dual
+  < and >         Ping faster and slower
+  m               Mark the current time on the chart, with an optional label
+  Enter           Clear the alerts shown
+  S               Save the chart as an SVG file
+  T               Save the screen as text
+
+Mouse:
+  Dragging across the chart zooms into that part of it, and right clicking zooms back out.
+  Clicking a host's stats or legend entry hides and shows its line.";
+
 #[derive(Parser, Debug)]
-#[command(author, version=build::PKG_VERSION, name = "gping", about = "Ping, but with a graph.", long_version = VERSION_INFO
+#[command(author, version=build::PKG_VERSION, name = "gping", about = "Ping, but with a graph.", long_version = VERSION_INFO,
+    after_long_help = KEYS
 )]
 struct Args {
-    /// Graph the execution time for a list of commands rather than pinging hosts
-    #[arg(long)]
-    cmd: bool,
+    /// Graph the execution time for a list of commands rather than pinging hosts. Given commands,
+    /// e.g. `gping google.com --cmd 'curl -s https://example.com'`, they are graphed alongside the
+    /// hosts instead.
+    #[arg(long, num_args = 0..)]
+    cmd: Option<Vec<String>>,
+
+    /// Graph the first number each command prints rather than how long it takes, like
+    /// `gping --cmd --cmd-metric 'cat /sys/class/thermal/thermal_zone0/temp'`.
+    #[arg(long, requires = "cmd")]
+    cmd_metric: bool,
+
+    /// Run commands with the shell, `sh -c` or `cmd /C` on Windows, so pipes, quotes and
+    /// built-ins work, like `gping --cmd --cmd-shell 'curl -s example.com | wc -c'`.
+    #[arg(long, requires = "cmd")]
+    cmd_shell: bool,
 
     /// Watch interval seconds (provide partial seconds like '0.5'). Default for ping is 0.2, default for cmd is 0.5.
     #[arg(short = 'n', long)]
     watch_interval: Option<f32>,
 
-    /// Hosts or IPs to ping, or commands to run if --cmd is provided. Can use cloud shorthands like aws:eu-west-1.
+    /// Hosts or IPs to ping, or commands to run if --cmd is provided. Can use cloud shorthands like aws:eu-west-1,
+    /// gcp:me-central2, do:ams3, oci:eu-frankfurt-1, hetzner:fsn1 or cf: for Cloudflare, "gateway" for the
+    /// default gateway, usually your router, and "dns" for the DNS servers the system uses.
+    /// Hosts can be given a name to show instead, like "10.0.0.1=Office VPN", and how often to ping
+    /// them, like "example.com@2s".
     #[arg(allow_hyphen_values = false)]
     hosts_or_commands: Vec<String>,
 
+    /// Print the cloud region shorthands known, for every provider or just the one given, like
+    /// aws, and exit.
+    #[arg(long, value_name = "PROVIDER", num_args = 0..=1, default_missing_value = "")]
+    list_regions: Option<String>,
+
+    /// Ping a group of hosts, like "DNS:1.1.1.1,8.8.8.8", shown under a row with their combined
+    /// stats. Can be given more than once.
+    #[arg(long, value_parser = parse_group)]
+    group: Vec<Group>,
+
     /// Determines the number of seconds to display in the graph.
     #[arg(short, long, default_value = "30")]
     buffer: u64,
+
+    /// Seconds of samples to keep, which the graph can be zoomed out to. At least --buffer.
+    #[arg(long, default_value = "600")]
+    history: u64,
     /// Resolve ping targets to IPv4 address
-    #[arg(short = '4', conflicts_with = "ipv6")]
+    #[arg(short = '4')]
     ipv4: bool,
     /// Resolve ping targets to IPv6 address
-    #[arg(short = '6', conflicts_with = "ipv4")]
+    #[arg(short = '6')]
     ipv6: bool,
+    /// Ping both the IPv4 and IPv6 address of each hostname, shown as two hosts. The same as giving
+    /// -4 and -6 together.
+    #[arg(long, conflicts_with_all = ["ipv4", "ipv6"])]
+    both: bool,
+    /// Ping every address each hostname resolves to, shown as a host each, to find the one bad
+    /// server behind a name with several.
+    #[arg(long)]
+    all_ips: bool,
+
+    /// Spread the first probe of each host over the interval, so hosts aren't all probed at the
+    /// same instant.
+    #[arg(long)]
+    stagger: bool,
+
+    /// Resolve hostnames again this often, like "5m", to follow DNS changes in long sessions.
+    /// Changes of address are marked on the chart.
+    #[arg(long, value_parser = plot_data::parse_duration)]
+    reresolve: Option<Duration>,
+
+    /// Show the network (AS) and country each host's address belongs to in the header, looked up
+    /// with Team Cymru's whois service.
+    #[arg(long)]
+    asn: bool,
 
-    #[cfg(not(target_os = "windows"))]
     /// Interface to use when pinging.
     #[arg(short = 'i', long)]
     interface: Option<String>,
 
+    /// Source IP address to send pings from, for hosts with more than one address.
+    #[arg(long)]
+    source: Option<IpAddr>,
+
+    /// Ping from a remote host by running ping on it over SSH, e.g. user@host.
+    #[arg(long)]
+    ssh: Option<String>,
+
+    /// Scale the Y axis to this percentile of the round trip times shown, e.g. 99, rather than the
+    /// maximum, so that rare spikes don't flatten the rest of the graph.
+    #[arg(long)]
+    clamp_percentile: Option<f64>,
+
+    /// Show a host's last round trip time in yellow from this time, e.g. 100ms, and in green
+    /// below it.
+    #[arg(long, value_parser = plot_data::parse_rtt)]
+    warn: Option<Duration>,
+
+    /// Show a host's last round trip time in red from this time, e.g. 250ms.
+    #[arg(long, value_parser = plot_data::parse_rtt)]
+    crit: Option<Duration>,
+
+    /// Flash a host's stats and show an alert above the chart when a reply takes --crit or
+    /// longer, or after --alert-timeouts timeouts in a row. Alerts stay until acknowledged with
+    /// Enter.
+    #[arg(long)]
+    alert: bool,
+
+    /// Timeouts in a row that raise an alert with --alert.
+    #[arg(long, default_value = "3")]
+    alert_timeouts: u32,
+
+    /// Send a desktop notification when a host times out --notify-timeouts times in a row, and
+    /// when it replies again.
+    #[arg(long)]
+    notify: bool,
+
+    /// Timeouts in a row before a host is reported as down with --notify.
+    #[arg(long, default_value = "3", value_parser = clap::value_parser!(u32).range(1..))]
+    notify_timeouts: u32,
+
+    /// Ring the terminal bell when a probe times out, like `ping -a`.
+    #[arg(long)]
+    bell: bool,
+
+    /// The least seconds between bells, so that an outage doesn't ring on every probe.
+    #[arg(long, requires = "bell")]
+    bell_interval: Option<f64>,
+
+    /// Save the chart as an SVG file when gping exits.
+    #[arg(long)]
+    snapshot_on_exit: Option<std::path::PathBuf>,
+
+    /// Stop after sending this many probes to each host, and print a summary, like `ping -c`.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    count: Option<u64>,
+
+    /// Stop after this long, e.g. 5m or 1h, and print a summary.
+    #[arg(long, value_parser = plot_data::parse_duration)]
+    duration: Option<Duration>,
+
+    /// Exit with status 2 if any host lost more than this percentage of probes over the session,
+    /// for using gping as a check in scripts.
+    #[arg(long)]
+    fail_on_loss: Option<f64>,
+
+    /// Exit with status 2 if the average round trip time of any host over the session is more
+    /// than this, e.g. 100ms.
+    #[arg(long, value_parser = plot_data::parse_rtt)]
+    fail_on_latency: Option<Duration>,
+
+    /// Where to show the stats for each host: above the chart, or in a column to its left, which
+    /// suits wide terminals.
+    #[arg(long, value_enum, default_value_t = LayoutMode::Vertical)]
+    layout: LayoutMode,
+
+    /// Give each host its own chart, with its own Y axis, rather than drawing them all on one.
+    #[arg(long)]
+    split: bool,
+
+    /// Show the percentage of probes lost by each host in a small chart under the main one.
+    #[arg(long)]
+    loss_chart: bool,
+
+    /// The window the loss chart's percentages are over, like "60s" or "10m".
+    #[arg(long, value_parser = plot_data::parse_duration, default_value = "60s")]
+    loss_window: Duration,
+
+    /// Split the chart into two panels of the same height sharing the time axis: the latency, and
+    /// under it the loss or jitter of each host. Switch the lower panel with Tab.
+    #[arg(long, value_enum, conflicts_with = "loss_chart")]
+    dual: Option<Panel>,
+
+    /// Plot the round trip time, the jitter between samples, or both. Switch between them with j.
+    #[arg(long, value_enum, default_value_t = PlotMode::Latency)]
+    plot: PlotMode,
+
+    /// Show round trip times in the unit that suits each one, or all in milliseconds or
+    /// microseconds so columns don't jump around. Switch between them with u.
+    #[arg(long, value_enum, default_value_t = Units::Auto)]
+    units: Units,
+
+    /// Mark each timeout with a red dot along the top of the chart, rather than only leaving a
+    /// gap in the line.
+    #[arg(long)]
+    timeout_markers: bool,
+
+    /// Label the X axis with how long ago each point was, like "-30s" to "now", rather than
+    /// the time of day.
+    #[arg(long)]
+    elapsed: bool,
+
+    /// Draw the chart's lines as an image with the terminal's graphics protocol, for smooth lines
+    /// rather than braille. Without a protocol, uses whichever the terminal supports, or braille if
+    /// it doesn't support one.
+    #[arg(
+        long,
+        alias = "renderer",
+        value_enum,
+        value_name = "PROTOCOL",
+        num_args = 0..=1,
+        default_missing_value = "auto"
+    )]
+    graphics: Option<Graphics>,
+
+    /// Leave the mouse to the terminal, so text can be selected, rather than dragging across the
+    /// chart to zoom in.
+    #[arg(long)]
+    no_mouse: bool,
+
+    /// Show times in UTC rather than the local time zone.
+    #[arg(long)]
+    utc: bool,
+
+    /// How times are shown on the X axis, in the status bar and with `--text`, as a strftime
+    /// format like "%H:%M:%S%.3f".
+    #[arg(long, default_value = "%H:%M:%S")]
+    time_format: String,
+
     /// Uses dot characters instead of braille
     #[arg(short = 's', long, help = "")]
     simple_graphics: bool,
@@ -96,6 +382,23 @@ struct Args {
     #[arg(long, default_value = "0")]
     horizontal_margin: u16,
 
+    /// The colors to give hosts without a --color, and to draw the axes and stats with.
+    #[arg(long, value_enum, default_value_t = Theme::Default)]
+    theme: Theme,
+
+    /// Color of the axes, as a name or '#RRGGBB', e.g. for light terminals where the default gray
+    /// is hard to see. Defaults to the theme's.
+    #[arg(long, value_parser = colors::parse_color)]
+    axis_color: Option<Color>,
+
+    /// Color of the axis labels. Defaults to the axis color.
+    #[arg(long, value_parser = colors::parse_color)]
+    label_color: Option<Color>,
+
+    /// Color to fill the background with, rather than leaving the terminal's.
+    #[arg(long, value_parser = colors::parse_color)]
+    background_color: Option<Color>,
+
     #[arg(
         name = "color",
         short = 'c',
@@ -115,53 +418,822 @@ following color names: 'black', 'red', 'green', 'yellow', 'blue', 'magenta',
     )]
     color_codes_or_names: Vec<String>,
 
-    /// Clear the graph from the terminal after closing the program
+    /// Clear the graph from the terminal after closing the program, leaving a summary of each host
+    /// with a sparkline of its round trip times.
     #[arg(name = "clear", long = "clear", action)]
     clear: bool,
 
+    /// Print stats for each host every second instead of drawing the graph, for dumb terminals
+    /// and logs.
+    #[arg(long)]
+    no_tui: bool,
+
+    /// Print a line with each host's latest round trip time and a sparkline every second, for
+    /// tmux's status line or a waybar or polybar module. With --count or --duration, only prints
+    /// the line once they're reached.
+    #[arg(long, conflicts_with = "no_tui")]
+    status_line: bool,
+
+    /// Append every sample to this CSV file while running, with the time, target and round trip
+    /// time in milliseconds or "timeout". Markers added with m are rows with their label as the
+    /// target and "marker" as the round trip time.
+    #[arg(long)]
+    csv: Option<std::path::PathBuf>,
+
+    /// Record the session, every sample and any markers to this SQLite database while running.
+    /// Sessions are added to any already in the database.
+    #[cfg(feature = "sqlite")]
+    #[arg(long)]
+    db: Option<std::path::PathBuf>,
+
+    /// Replay a session recorded with --csv or --db, or exported as JSON, instead of pinging.
+    #[arg(long, conflicts_with_all = ["cmd", "hosts_or_commands", "group"])]
+    replay: Option<std::path::PathBuf>,
+
+    /// Save the samples shown, and any markers, to this file every 30 seconds and on exit, and
+    /// restore them when started again with the same hosts, so the chart picks up where it left
+    /// off.
+    #[arg(long, conflicts_with = "replay")]
+    state_file: Option<std::path::PathBuf>,
+
+    /// How many times faster than real time to replay the session, e.g. 10.
+    #[arg(long, default_value = "1", requires = "replay")]
+    speed: f64,
+
+    /// Serve per-host metrics for Prometheus on this address while running, e.g. 127.0.0.1:9100.
+    #[arg(long)]
+    prometheus: Option<std::net::SocketAddr>,
+
+    #[cfg(not(target_os = "windows"))]
+    /// Path to the ping binary to use, instead of finding `ping` on the PATH.
+    #[arg(long)]
+    ping_binary: Option<std::path::PathBuf>,
+
     #[cfg(not(target_os = "windows"))]
     /// Extra arguments to pass to `ping`. These are platform dependent.
-    #[arg(long, allow_hyphen_values = true, num_args = 0.., conflicts_with="cmd")]
+    #[arg(long, allow_hyphen_values = true, num_args = 0..)]
     ping_args: Option<Vec<String>>,
 }
 
+/// Something to graph: a host to ping, with the name to show for it and how often to ping it if
+/// given, or a command to time.
+enum Target {
+    Host(String, Option<String>, Option<Duration>),
+    Command(String),
+}
+
+impl Target {
+    fn name(&self) -> &str {
+        match self {
+            Target::Host(name, ..) | Target::Command(name) => name,
+        }
+    }
+
+    /// What the header shows for the target, until pinging starts.
+    fn display(&self) -> &str {
+        match self {
+            Target::Host(_, Some(label), _) => label,
+            _ => self.name(),
+        }
+    }
+}
+
+/// Split a host given like "10.0.0.1=Office VPN" into the host and the name to show for it.
+fn split_label(host: &str) -> (&str, Option<&str>) {
+    match host.split_once('=') {
+        Some((host, label)) if !label.trim().is_empty() => (host.trim(), Some(label.trim())),
+        Some((host, _)) => (host.trim(), None),
+        None => (host, None),
+    }
+}
+
+/// Split a host given like "example.com@2s" into the host and how often to ping it. Anything after
+/// an "@" that isn't a length of time is part of the host.
+fn split_interval(host: &str) -> (&str, Option<Duration>) {
+    match host.rsplit_once('@') {
+        Some((host, interval)) => match plot_data::parse_duration(interval) {
+            Ok(interval) if !interval.is_zero() => (host, Some(interval)),
+            _ => (host, None),
+        },
+        None => (host, None),
+    }
+}
+
+/// A host to ping, with the name to show for it if it has one.
+type NamedHost = (String, Option<String>);
+
+/// The hosts to ping for a shorthand like "gateway", "dns" or "aws:eu-west-1", each with the name
+/// to show for it if the host alone wouldn't say what it is, or `None` if `host` isn't a shorthand.
+fn shorthand(host: &str) -> Result<Option<Vec<NamedHost>>> {
+    if host == "gateway" {
+        let gateway = gateway::default_gateway()?;
+        return Ok(Some(vec![(
+            gateway.to_string(),
+            Some(format!("gateway ({gateway})")),
+        )]));
+    }
+    if host == "dns" {
+        return Ok(Some(
+            dns::resolvers()?
+                .into_iter()
+                .map(|resolver| {
+                    let name = format!("dns ({resolver})");
+                    (resolver, Some(name))
+                })
+                .collect(),
+        ));
+    }
+    Ok(region_map::try_host_from_cloud_region(host)?.map(|host| vec![(host, None)]))
+}
+
+/// A host for each of the IPv4 and IPv6 address of `host` with `--both`, named like "host (v4)". A
+/// host given as an IP address is kept as it is.
+fn dual_stack(host: String, label: Option<String>) -> Result<Vec<NamedHost>> {
+    if host.parse::<IpAddr>().is_ok() || host.contains("://") {
+        return Ok(vec![(host, label)]);
+    }
+    let (ipv4, ipv6) = pinger::Target::new_dual_stack(ascii_host(&host)?)
+        .resolve_dual_stack(&pinger::Resolver::System)
+        .with_context(|| format!("Could not resolve hostname {host}"))?;
+    let name = label.unwrap_or_else(|| host.clone());
+    let hosts = ipv4
+        .map(|ip| (ip.to_string(), Some(format!("{name} (v4)"))))
+        .into_iter()
+        .chain(ipv6.map(|ip| (ip.to_string(), Some(format!("{name} (v6)")))))
+        .collect::<Vec<_>>();
+    if hosts.is_empty() {
+        bail!("Could not resolve hostname {host}");
+    }
+    Ok(hosts)
+}
+
+/// A host for each address `host` resolves to with `--all-ips`, of the IP version given with -4 or
+/// -6 if only one of them is. A host given as an IP address is kept as it is.
+fn all_ips(host: String, label: Option<String>, args: &Args) -> Result<Vec<NamedHost>> {
+    if host.parse::<IpAddr>().is_ok() || host.contains("://") {
+        return Ok(vec![(host, label)]);
+    }
+    let version = match (args.ipv4, args.ipv6) {
+        (true, false) => pinger::IPVersion::V4,
+        (false, true) => pinger::IPVersion::V6,
+        _ => pinger::IPVersion::Any,
+    };
+    let ips = pinger::Resolver::System
+        .lookup(&ascii_host(&host)?, version)
+        .with_context(|| format!("Could not resolve hostname {host}"))?;
+    if ips.is_empty() {
+        bail!("Could not resolve hostname {host}");
+    }
+    let name = label.unwrap_or_else(|| host.clone());
+    // The system resolver can return an address more than once
+    Ok(ips
+        .into_iter()
+        .unique()
+        .map(|ip| (ip.to_string(), Some(format!("{name} ({ip})"))))
+        .collect())
+}
+
+/// Hosts given with `--group`, under a name.
+#[derive(Clone, Debug)]
+struct Group {
+    name: String,
+    hosts: Vec<String>,
+}
+
+fn parse_group(value: &str) -> Result<Group> {
+    let Some((name, hosts)) = value.split_once(':') else {
+        bail!("Expected a group name and its hosts, like \"DNS:1.1.1.1,8.8.8.8\"");
+    };
+    let hosts = hosts
+        .split(',')
+        .map(str::trim)
+        .filter(|host| !host.is_empty())
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    if name.trim().is_empty() || hosts.is_empty() {
+        bail!("Expected a group name and its hosts, like \"DNS:1.1.1.1,8.8.8.8\"");
+    }
+    Ok(Group {
+        name: name.trim().to_string(),
+        hosts,
+    })
+}
+
+/// A row of the stats above or beside the chart.
+enum Row<'a> {
+    /// The combined stats of the hosts of a group, which follow it.
+    Group(&'a str, Vec<&'a PlotData>),
+    Host(usize, &'a PlotData),
+}
+
+/// The targets given on the command line: hosts, then commands, then the hosts of each group,
+/// with the name of the group of each of those last ones.
+fn targets(args: &Args) -> Result<(Vec<Target>, Vec<String>)> {
+    let (hosts, commands) = match &args.cmd {
+        // On its own, --cmd makes every target a command.
+        Some(commands) if commands.is_empty() => (vec![], args.hosts_or_commands.clone()),
+        Some(commands) => (args.hosts_or_commands.clone(), commands.clone()),
+        None => (args.hosts_or_commands.clone(), vec![]),
+    };
+    // A shorthand like "dns" can stand for more than one host, as can any host with --both or
+    // --all-ips
+    let host = |s: &str| -> Result<Vec<Target>> {
+        let (host, label) = split_label(s);
+        let (host, interval) = split_interval(host);
+        let label = label.map(str::to_string);
+        let hosts = match shorthand(host)? {
+            None => vec![(host.to_string(), label)],
+            Some(hosts) => hosts
+                .into_iter()
+                .map(|(new_host, name)| (new_host, label.clone().or(name)))
+                .collect(),
+        };
+        let mut targets = vec![];
+        for (host, label) in hosts {
+            let hosts = if args.all_ips {
+                all_ips(host, label, args)?
+            } else if args.both || (args.ipv4 && args.ipv6) {
+                dual_stack(host, label)?
+            } else {
+                vec![(host, label)]
+            };
+            targets.extend(
+                hosts
+                    .into_iter()
+                    .map(|(host, label)| Target::Host(host, label, interval)),
+            );
+        }
+        Ok(targets)
+    };
+    let mut targets = vec![];
+    for s in &hosts {
+        targets.extend(host(s)?);
+    }
+    targets.extend(commands.into_iter().map(Target::Command));
+    let mut groups = vec![];
+    for group in &args.group {
+        for s in &group.hosts {
+            let hosts = host(s)?;
+            groups.extend(hosts.iter().map(|_| group.name.clone()));
+            targets.extend(hosts);
+        }
+    }
+    Ok((targets, groups))
+}
+
+/// The position in a replayed session, which moves `speed` times faster than real time from its
+/// first sample and stops at its last.
+struct ReplayClock {
+    from: DateTime<Local>,
+    to: DateTime<Local>,
+    speed: f64,
+    started: Instant,
+}
+
+/// How much faster and slower than given hosts can be pinged with `<` and `>`.
+const MIN_INTERVAL_SCALE: f64 = 1.0 / 8.0;
+const MAX_INTERVAL_SCALE: f64 = 8.0;
+
+/// The smallest window that can be zoomed in to.
+const MIN_DISPLAY_INTERVAL: chrono::Duration = chrono::Duration::seconds(10);
+
+/// The most history that can be kept with `}`, unless more was given with `--history`.
+const MAX_HISTORY: chrono::Duration = chrono::Duration::hours(24);
+
+/// The most characters across the sparklines left behind by `--clear`.
+const SPARKLINE_WIDTH: usize = 60;
+
+/// The most characters across each host's sparkline with `--status-line`.
+const STATUS_SPARKLINE_WIDTH: usize = 10;
+
+/// Whether a host has breached `--crit` or `--alert-timeouts`.
+#[derive(Default)]
+struct Alert {
+    timeouts_in_a_row: u32,
+    /// Whether the latest sample breached a threshold.
+    breached: bool,
+    /// What the threshold was breached by, shown until acknowledged.
+    reason: Option<String>,
+}
+
+impl Alert {
+    /// Track a sample, with `format` giving the round trip time as the header would show it.
+    fn update(
+        &mut self,
+        rtt: Option<Duration>,
+        crit: Option<Duration>,
+        alert_timeouts: u32,
+        format: impl FnOnce(Duration) -> String,
+    ) {
+        self.timeouts_in_a_row = match rtt {
+            Some(_) => 0,
+            None => self.timeouts_in_a_row + 1,
+        };
+        let reason = match rtt {
+            Some(rtt) if crit.is_some_and(|crit| rtt >= crit) => {
+                Some(format!("{} round trip", format(rtt)))
+            }
+            None if self.timeouts_in_a_row >= alert_timeouts => match self.timeouts_in_a_row {
+                1 => Some("timed out".to_string()),
+                n => Some(format!("{n} timeouts in a row")),
+            },
+            _ => None,
+        };
+        // An alert is raised once per breach, and kept up to date until it's acknowledged
+        if reason.is_some() && (!self.breached || self.reason.is_some()) {
+            self.reason = reason.clone();
+        }
+        self.breached = reason.is_some();
+    }
+}
+
 struct App {
     data: Vec<PlotData>,
     display_interval: chrono::Duration,
+    history: chrono::Duration,
+    /// The percentile of the samples shown to use as the top of the Y axis, instead of the max.
+    clamp_percentile: Option<f64>,
+    thresholds: Thresholds,
+    /// Whether timeouts are marked along the top of the chart.
+    timeout_markers: bool,
+    /// Whether the X axis is labelled with the time since each point rather than the time of day.
+    elapsed_axis: bool,
+    /// Whether times are shown in UTC rather than local time.
+    utc: bool,
+    /// The strftime format times are shown in.
+    time_format: String,
+    plot_mode: PlotMode,
+    /// The window in seconds of the loss percentages.
+    loss_window: f64,
+    loss_chart: bool,
+    /// The lower panel of the dual panel view, if it's shown.
+    dual: Option<Panel>,
+    /// The timeouts in a row that alert, if alerts are enabled with `--alert`.
+    alert_timeouts: Option<u32>,
+    /// The alert state of each host, indexed by `host_id`.
+    alerts: Vec<Alert>,
+    theme: Theme,
+    axis_color: Color,
+    /// The color of the axis labels, if not the axis color.
+    label_color: Option<Color>,
+    background_color: Option<Color>,
     started: chrono::DateTime<Local>,
+    /// Whether charts with more than one line, or with markers, have a legend.
+    legend: bool,
+    /// Points in time marked with `m`, in the order they were added.
+    markers: Vec<Marker>,
+    /// The `host_id` of the host selected with the arrow keys, which `d` removes.
+    selected: Option<usize>,
+    /// The result of the last key pressed, such as where a snapshot was saved.
+    message: Option<String>,
+    /// A host or marker label being typed in, after pressing `a` or `m`.
+    input: Option<Input>,
+    /// The end of the window shown when scrolled back, rather than following the latest samples.
+    scrolled_to: Option<DateTime<Local>>,
+    /// The window set with `--buffer`, which `z` and right clicking go back to.
+    default_interval: chrono::Duration,
+    /// Where each chart plots its lines as of the last frame drawn, for finding the time under
+    /// the mouse.
+    chart_areas: RefCell<Vec<Rect>>,
+    /// The chart being dragged across, with the columns the drag started at and is at now.
+    drag: Option<(Rect, u16, u16)>,
+    /// Where each host's header row and legend entries are as of the last frame drawn, which hide
+    /// or show its line when clicked.
+    host_areas: RefCell<Vec<(Rect, usize)>>,
+    /// Whether the terminal draws the charts' lines as images, with `--graphics`.
+    graphics: bool,
+    /// The charts' lines as of the last frame drawn, for the terminal to draw over it.
+    images: RefCell<Vec<graphics::Image>>,
+    replay: Option<ReplayClock>,
+    /// How many times longer than given hosts are pinged at, changed with `<` and `>`.
+    interval_scale: f64,
+    units: Units,
 }
 
 impl App {
-    fn new(data: Vec<PlotData>, buffer: u64) -> Self {
+    fn new(data: Vec<PlotData>, buffer: u64, history: u64) -> Self {
+        let display_interval = chrono::Duration::from_std(Duration::from_secs(buffer)).unwrap();
         App {
             data,
-            display_interval: chrono::Duration::from_std(Duration::from_secs(buffer)).unwrap(),
+            display_interval,
+            history: chrono::Duration::from_std(Duration::from_secs(history)).unwrap(),
+            clamp_percentile: None,
+            thresholds: Thresholds::default(),
+            timeout_markers: false,
+            elapsed_axis: false,
+            utc: false,
+            time_format: "%H:%M:%S".to_string(),
+            plot_mode: PlotMode::Latency,
+            loss_window: 60.0,
+            loss_chart: false,
+            dual: None,
+            alert_timeouts: None,
+            alerts: vec![],
+            theme: Theme::Default,
+            axis_color: Theme::Default.axis(),
+            label_color: None,
+            background_color: None,
             started: Local::now(),
+            legend: true,
+            markers: vec![],
+            selected: None,
+            message: None,
+            input: None,
+            scrolled_to: None,
+            default_interval: display_interval,
+            chart_areas: RefCell::new(vec![]),
+            drag: None,
+            host_areas: RefCell::new(vec![]),
+            graphics: false,
+            images: RefCell::new(vec![]),
+            replay: None,
+            interval_scale: 1.0,
+            units: Units::Auto,
+        }
+    }
+
+    /// Show the time in the replayed session rather than the current time.
+    fn replaying(&mut self, samples: &[Sample], speed: f64) {
+        let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+            return;
+        };
+        self.started = first.at;
+        self.replay = Some(ReplayClock {
+            from: first.at,
+            to: last.at,
+            speed,
+            started: Instant::now(),
+        });
+    }
+
+    fn now(&self) -> DateTime<Local> {
+        match &self.replay {
+            Some(clock) => {
+                let length = clock.to - clock.from;
+                let elapsed =
+                    chrono::Duration::from_std(clock.started.elapsed().mul_f64(clock.speed))
+                        .map_or(length, |elapsed| elapsed.min(length));
+                clock.from + elapsed
+            }
+            None => Local::now(),
         }
     }
 
-    fn update(&mut self, host_idx: usize, item: Option<Duration>) {
+    fn update(&mut self, host_idx: usize, item: Option<Duration>, at: DateTime<Local>) {
         let host = &mut self.data[host_idx];
-        host.update(item);
+        host.update(item, at);
+        if let Some(alert_timeouts) = self.alert_timeouts {
+            if self.alerts.len() <= host_idx {
+                self.alerts.resize_with(host_idx + 1, Alert::default);
+            }
+            let (host, units) = (&self.data[host_idx], self.units);
+            self.alerts[host_idx].update(item, self.thresholds.crit, alert_timeouts, |rtt| {
+                host.format_value(rtt, units)
+            });
+        }
     }
 
-    fn y_axis_bounds(&self) -> [f64; 2] {
-        // Find the Y axis bounds for our chart.
-        // This is trickier than the x-axis. We iterate through all our PlotData structs
-        // and find the min/max of all the values. Then we add a 10% buffer to them.
-        let (min, max) = match self
-            .data
+    /// Save the chart as shown as an SVG file.
+    fn snapshot(&self, path: &Path) -> Result<()> {
+        let plots = self.plotted().collect::<Vec<_>>();
+        let scene = self.scene(&plots, snapshot::PLOT_WIDTH);
+        snapshot::write(path, &scene, &plots, self.units)
+    }
+
+    /// The lines, outages and dots of a chart of `plots`, which are drawn the same way with
+    /// braille, with `--graphics` and in snapshots. Long windows are downsampled to a few points
+    /// for each of the `columns` across the chart.
+    fn scene(&self, plots: &[(usize, &PlotData)], columns: f64) -> Scene {
+        let since = self.window_start();
+        let [start, end] = self.x_axis_bounds();
+        let y_bounds = self.y_axis_bounds(plots);
+        let [bottom, top] = y_bounds;
+        let timeouts = match self.timeout_markers {
+            true => plots
+                .iter()
+                .flat_map(|(_, plot)| plot.timeouts(since))
+                .map(|timestamp| (timestamp, top))
+                .collect(),
+            false => vec![],
+        };
+        // Failed commands are always marked, along the bottom so they aren't mistaken for
+        // timeouts
+        let failures = plots
+            .iter()
+            .flat_map(|(_, plot)| plot.failures(since))
+            .map(|timestamp| (timestamp, bottom))
+            .collect();
+        let bucket = (end - start) / columns;
+        let lines = plots
+            .iter()
+            .flat_map(|(host_id, plot)| {
+                let latency = match self.plot_mode {
+                    PlotMode::Jitter => vec![],
+                    _ => plot_data::downsample(plot.since(since), bucket).into_owned(),
+                };
+                let jitter = match self.plot_mode {
+                    PlotMode::Latency => vec![],
+                    _ => plot_data::downsample(plot.jitter_since(since), bucket).into_owned(),
+                };
+                let style = self.line_style(*host_id);
+                let jitter_style = match self.plot_mode {
+                    PlotMode::Both => style.add_modifier(Modifier::DIM),
+                    _ => style,
+                };
+                [(style, latency), (jitter_style, jitter)]
+            })
+            .collect();
+        Scene {
+            x_bounds: [start, end],
+            y_bounds,
+            lines,
+            outages: plots
+                .iter()
+                .flat_map(|(_, plot)| plot.outages(since))
+                .collect(),
+            dots: vec![(Color::Red, timeouts), (Color::Magenta, failures)],
+            background: self.background_color,
+        }
+    }
+
+    /// Clear the alerts shown, until hosts breach their thresholds again.
+    fn acknowledge(&mut self) {
+        for alert in &mut self.alerts {
+            alert.reason = None;
+        }
+    }
+
+    /// The hosts with an alert that hasn't been acknowledged, and why.
+    fn active_alerts(&self) -> impl Iterator<Item = (&PlotData, &str)> {
+        self.alerts
+            .iter()
+            .zip(&self.data)
+            .filter(|(_, host)| !host.removed)
+            .filter_map(|(alert, host)| Some((host, alert.reason.as_deref()?)))
+    }
+
+    /// The rows of stats shown, with a row for each group before its hosts. Hosts added while
+    /// running are in no group, so come under "other" if they follow a group.
+    fn rows(&self) -> Vec<Row<'_>> {
+        let mut rows = vec![];
+        let mut group: Option<&str> = None;
+        let mut group_row = None;
+        for (host_id, host) in self.hosts() {
+            if host.group.as_deref() != group {
+                group = host.group.as_deref();
+                group_row = Some(rows.len());
+                rows.push(Row::Group(group.unwrap_or("other"), vec![]));
+            }
+            if let Some(Row::Group(_, hosts)) = group_row.map(|row| &mut rows[row]) {
+                hosts.push(host);
+            }
+            rows.push(Row::Host(host_id, host));
+        }
+        rows
+    }
+
+    /// The modifier added to a host's stats, which flash while it has an alert.
+    fn header_modifier(&self, host_id: usize) -> Modifier {
+        let alerting = self
+            .alerts
+            .get(host_id)
+            .is_some_and(|alert| alert.reason.is_some());
+        if alerting && Local::now().timestamp_subsec_millis() < 500 {
+            self.theme.header() | Modifier::REVERSED
+        } else {
+            self.theme.header()
+        }
+    }
+
+    fn duplicate(&mut self, host_idx: usize) {
+        self.data[host_idx].duplicate();
+    }
+
+    /// The hosts that haven't been removed, with their `host_id`.
+    fn hosts(&self) -> impl Iterator<Item = (usize, &PlotData)> {
+        self.data
+            .iter()
+            .enumerate()
+            .filter(|(_, host)| !host.removed)
+    }
+
+    /// The hosts drawn on the chart, which excludes hidden ones.
+    fn plotted(&self) -> impl Iterator<Item = (usize, &PlotData)> {
+        self.hosts().filter(|(_, host)| !host.hidden)
+    }
+
+    /// The style of a host's line: bold if it's selected, and dimmed if another host is.
+    fn line_style(&self, host_id: usize) -> Style {
+        let style = self.data[host_id].style;
+        match self.selected {
+            Some(selected) if selected == host_id => style.add_modifier(Modifier::BOLD),
+            Some(_) => style.add_modifier(Modifier::DIM),
+            None => style,
+        }
+    }
+
+    /// Select the next or previous host, wrapping around.
+    fn select(&mut self, forward: bool) {
+        let host_ids = self.hosts().map(|(host_id, _)| host_id).collect::<Vec<_>>();
+        let position = self
+            .selected
+            .and_then(|selected| host_ids.iter().position(|host_id| *host_id == selected));
+        let position = match (position, forward) {
+            (None, true) => Some(0),
+            (None, false) => host_ids.len().checked_sub(1),
+            (Some(position), true) => Some((position + 1) % host_ids.len()),
+            (Some(position), false) => Some((position + host_ids.len() - 1) % host_ids.len()),
+        };
+        self.selected = position.map(|position| host_ids[position]);
+    }
+
+    /// Remove the selected host, selecting the next one, and return its `host_id`.
+    fn remove_selected(&mut self) -> Option<usize> {
+        let host_id = self.selected?;
+        self.select(true);
+        if self.selected == Some(host_id) {
+            self.selected = None;
+        }
+        self.data[host_id].removed = true;
+        Some(host_id)
+    }
+
+    /// Halve the window shown, down to `MIN_DISPLAY_INTERVAL`.
+    fn zoom_in(&mut self) {
+        self.display_interval =
+            (self.display_interval / 2).max(MIN_DISPLAY_INTERVAL.min(self.display_interval));
+    }
+
+    /// Double the window shown, up to the history kept.
+    fn zoom_out(&mut self) {
+        self.display_interval = (self.display_interval * 2).min(self.history);
+    }
+
+    /// Double or halve the history kept, between `MIN_DISPLAY_INTERVAL` and `MAX_HISTORY`, zooming
+    /// in if the window shown no longer fits. Samples older than it are dropped as new ones come in.
+    fn resize_history(&mut self, grow: bool) {
+        self.history = match grow {
+            true => (self.history * 2).min(MAX_HISTORY.max(self.history)),
+            false => (self.history / 2).max(MIN_DISPLAY_INTERVAL.min(self.history)),
+        };
+        self.display_interval = self.display_interval.min(self.history);
+        for host in &mut self.data {
+            host.set_buffer(self.history);
+        }
+        self.message = Some(format!(
+            "keeping {}",
+            format_seconds(self.history.num_seconds())
+        ));
+    }
+
+    /// Move the window back or forward by a quarter of its width, within the history kept. Moving
+    /// forward past the latest samples goes back to following them.
+    fn scroll(&mut self, back: bool) {
+        let now = self.now();
+        let step = self.display_interval / 4;
+        let end = self.scrolled_to.unwrap_or(now);
+        let end = if back { end - step } else { end + step };
+        let earliest = self.started.max(now - self.history) + self.display_interval;
+        self.scrolled_to = if end >= now || earliest >= now {
+            None
+        } else {
+            Some(end.max(earliest))
+        };
+    }
+
+    /// Show the window from `start` to `end` seconds, following the latest samples if it reaches
+    /// them.
+    fn zoom_to(&mut self, start: f64, end: f64) {
+        let length = chrono::Duration::milliseconds(((end - start) * 1_000.0) as i64);
+        self.display_interval = length.clamp(MIN_DISPLAY_INTERVAL.min(self.history), self.history);
+        let end = DateTime::<Utc>::from_timestamp_millis((end * 1_000.0) as i64)
+            .unwrap_or_default()
+            .with_timezone(&Local);
+        self.scrolled_to = (end < self.now()).then_some(end);
+    }
+
+    /// Go back to following the latest samples over the window set with `--buffer`.
+    fn reset_zoom(&mut self) {
+        self.display_interval = self.default_interval.min(self.history);
+        self.scrolled_to = None;
+    }
+
+    /// The time at the left edge of column `x` of the chart plotted over `area`, in seconds.
+    fn time_at(&self, area: Rect, x: u16) -> f64 {
+        let [start, end] = self.x_axis_bounds();
+        let offset = x.saturating_sub(area.x) as f64 / area.width.max(1) as f64;
+        start + (end - start) * offset
+    }
+
+    /// Clicking a host's header row or legend entry hides or shows its line. Otherwise dragging
+    /// across a chart with the left button selects a part of it, which is zoomed into once
+    /// released. Right clicking zooms back out.
+    fn mouse(&mut self, event: MouseEvent) {
+        match event.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let at = Position::new(event.column, event.row);
+                let clicked = self
+                    .host_areas
+                    .borrow()
+                    .iter()
+                    .find(|(area, _)| area.contains(at))
+                    .map(|(_, host_id)| *host_id);
+                if let Some(host_id) = clicked {
+                    self.data[host_id].hidden = !self.data[host_id].hidden;
+                    return;
+                }
+                self.drag = self
+                    .chart_areas
+                    .borrow()
+                    .iter()
+                    .find(|area| area.contains(at))
+                    .map(|area| (*area, event.column, event.column));
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                if let Some((area, _, to)) = &mut self.drag {
+                    *to = event.column.clamp(area.left(), area.right() - 1);
+                }
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                if let Some((area, from, to)) = self.drag.take() {
+                    if from != to {
+                        let (from, to) = (from.min(to), from.max(to));
+                        self.zoom_to(self.time_at(area, from), self.time_at(area, to + 1));
+                    }
+                }
+            }
+            MouseEventKind::Down(MouseButton::Right) => self.reset_zoom(),
+            _ => {}
+        }
+    }
+
+    fn change_view(&mut self, change: ViewChange) {
+        match change {
+            ViewChange::ZoomIn => self.zoom_in(),
+            ViewChange::ZoomOut => self.zoom_out(),
+            ViewChange::GrowHistory => self.resize_history(true),
+            ViewChange::ShrinkHistory => self.resize_history(false),
+            ViewChange::ScrollBack => self.scroll(true),
+            ViewChange::ScrollForward => self.scroll(false),
+            ViewChange::ScrollToLive => self.scrolled_to = None,
+            ViewChange::ResetZoom => self.reset_zoom(),
+            ViewChange::SelectPrevious => self.select(false),
+            ViewChange::SelectNext => self.select(true),
+            ViewChange::ToggleLegend => self.legend = !self.legend,
+            ViewChange::CyclePlot => self.plot_mode = self.plot_mode.next(),
+            ViewChange::CycleUnits => self.units = self.units.next(),
+            ViewChange::SwitchPanel => self.dual = self.dual.map(Panel::next),
+            ViewChange::Toggle(n) => {
+                let host_id = self.hosts().nth(n - 1).map(|(host_id, _)| host_id);
+                if let Some(host_id) = host_id {
+                    self.data[host_id].hidden = !self.data[host_id].hidden;
+                }
+            }
+        }
+    }
+
+    /// The points plotted for `plot` since `since`, depending on the plot mode.
+    fn series<'a>(&self, plot: &'a PlotData, since: f64) -> Vec<&'a [(f64, f64)]> {
+        match self.plot_mode {
+            PlotMode::Latency => vec![plot.since(since)],
+            PlotMode::Jitter => vec![plot.jitter_since(since)],
+            PlotMode::Both => vec![plot.since(since), plot.jitter_since(since)],
+        }
+    }
+
+    /// The timestamp of the start of the window shown, in seconds.
+    fn window_start(&self) -> f64 {
+        self.x_axis_bounds()[0]
+    }
+
+    fn y_axis_bounds(&self, plots: &[(usize, &PlotData)]) -> [f64; 2] {
+        // Find the Y axis bounds for a chart of `plots`.
+        // This is trickier than the x-axis. We iterate through the PlotData structs
+        // and find the min/max of all the values shown. Then we add a 10% buffer to them.
+        let since = self.window_start();
+        let values = plots
             .iter()
-            .flat_map(|b| b.data.as_slice())
+            .flat_map(|(_, b)| self.series(b, since))
+            .flatten()
             .map(|v| v.1)
-            .filter(|v| !v.is_nan())
-            .minmax()
-        {
+            .filter(|v| !v.is_nan());
+        let (min, mut max) = match values.clone().minmax() {
             MinMaxResult::NoElements => (f64::INFINITY, 0_f64),
             MinMaxResult::OneElement(elm) => (elm, elm),
             MinMaxResult::MinMax(min, max) => (min, max),
         };
 
+        // Leave spikes above the percentile off the top of the chart, rather than squashing
+        // everything else to fit them in.
+        if let Some(percentile) = self.clamp_percentile {
+            let mut sorted = values.collect::<Vec<_>>();
+            if !sorted.is_empty() {
+                sorted.sort_by(f64::total_cmp);
+                let idx = ((sorted.len() - 1) as f64 * percentile / 100_f64).round() as usize;
+                max = sorted[idx];
+            }
+        }
+
         // Add a 10% buffer to the top and bottom
         let max_10_percent = (max * 10_f64) / 100_f64;
         let min_10_percent = (min * 10_f64) / 100_f64;
@@ -169,7 +1241,7 @@ impl App {
     }
 
     fn x_axis_bounds(&self) -> [f64; 2] {
-        let now = Local::now();
+        let now = self.scrolled_to.unwrap_or_else(|| self.now());
         let now_idx;
         let before_idx;
         if (now - self.started) < self.display_interval {
@@ -184,7 +1256,17 @@ impl App {
         [before_idx, now_idx]
     }
 
-    fn x_axis_labels(&self, bounds: [f64; 2]) -> Vec<Span> {
+    fn axis_label<'a>(&self, label: String) -> Span<'a> {
+        match self.label_color {
+            Some(color) => Span::styled(label, Style::default().fg(color)),
+            None => Span::raw(label),
+        }
+    }
+
+    fn x_axis_labels(&self, bounds: [f64; 2]) -> Vec<Span<'_>> {
+        if self.elapsed_axis {
+            return self.elapsed_axis_labels(bounds);
+        }
         let lower_utc = DateTime::<Utc>::from_timestamp(bounds[0] as i64, 0)
             .expect("Error parsing x-axis bounds 0");
         let upper_utc = DateTime::<Utc>::from_timestamp(bounds[1] as i64, 0)
@@ -194,13 +1276,41 @@ impl App {
         let diff = (upper - lower) / 2;
         let midpoint = lower + diff;
         vec![
-            Span::raw(format!("{:?}", lower.time())),
-            Span::raw(format!("{:?}", midpoint.time())),
-            Span::raw(format!("{:?}", upper.time())),
+            self.axis_label(self.format_time(lower)),
+            self.axis_label(self.format_time(midpoint)),
+            self.axis_label(self.format_time(upper)),
         ]
     }
 
-    fn y_axis_labels(&self, bounds: [f64; 2]) -> Vec<Span> {
+    /// Format `time` with `--time-format`, in UTC with `--utc`.
+    fn format_time(&self, time: DateTime<Local>) -> String {
+        match self.utc {
+            true => time
+                .with_timezone(&Utc)
+                .format(&self.time_format)
+                .to_string(),
+            false => time.format(&self.time_format).to_string(),
+        }
+    }
+
+    /// The labels of the X axis as how long before now each point is. Before the window has
+    /// filled, its right edge is ahead of the clock and counts as now.
+    fn elapsed_axis_labels(&self, bounds: [f64; 2]) -> Vec<Span<'_>> {
+        let now = self.now().timestamp_millis() as f64 / 1_000f64;
+        let now = now.min(bounds[1]);
+        let midpoint = (bounds[0] + bounds[1]) / 2_f64;
+        [bounds[0], midpoint, bounds[1]]
+            .iter()
+            .map(|bound| match (now - bound).round() as i64 {
+                0 => self.axis_label("now".to_string()),
+                ago => self.axis_label(format!("-{}", format_seconds(ago))),
+            })
+            .collect()
+    }
+
+    /// The labels of the Y axis, as numbers rather than times if every host plotted is a
+    /// `metric`.
+    fn y_axis_labels(&self, bounds: [f64; 2], metric: bool) -> Vec<Span<'_>> {
         // Create 7 labels for our y axis, based on the y-axis bounds we computed above.
         let min = bounds[0];
         let max = bounds[1];
@@ -212,15 +1322,24 @@ impl App {
         let duration = Duration::from_micros(min as u64);
 
         (0..num_labels)
-            .map(|i| Span::raw(format!("{:?}", duration.add(increment * i))))
+            .map(|i| duration.add(increment * i))
+            .map(|value| match metric {
+                true => self.axis_label(plot_data::format_metric(value)),
+                false => self.axis_label(self.units.format(value)),
+            })
             .collect()
     }
 }
 
 #[derive(Debug)]
 enum Update {
-    Result(Duration),
+    Result(Duration, Option<SystemTime>),
+    Duplicate,
     Timeout,
+    /// A command exited with an error, or without printing a number with `--cmd-metric`.
+    Failed,
+    /// The host now resolves to a different address.
+    AddressChanged(IpAddr),
     Unknown,
     Terminated(ExitStatus, String),
 }
@@ -228,10 +1347,86 @@ enum Update {
 impl From<PingResult> for Update {
     fn from(result: PingResult) -> Self {
         match result {
-            PingResult::Pong(duration, _) => Update::Result(duration),
-            PingResult::Timeout(_) => Update::Timeout,
-            PingResult::Unknown(_) => Update::Unknown,
-            PingResult::PingExited(e, stderr) => Update::Terminated(e, stderr),
+            PingResult::Pong(duration, _, arrived) => Update::Result(duration, arrived),
+            PingResult::NtpReply(delay, ..) => Update::Result(delay, None),
+            PingResult::Duplicate(..) => Update::Duplicate,
+            // ICMP errors mean the probe was lost, so they're plotted like timeouts.
+            PingResult::Timeout(_)
+            | PingResult::Unreachable(_)
+            | PingResult::TtlExceeded(_)
+            | PingResult::Filtered(_) => Update::Timeout,
+            PingResult::AddressChanged(ip) => Update::AddressChanged(ip),
+            PingResult::PingExited(e, stderr, _) => Update::Terminated(e, stderr),
+            // Including any kind of result added to pinger later.
+            _ => Update::Unknown,
+        }
+    }
+}
+
+/// What is being typed in.
+#[derive(Default)]
+enum Prompt {
+    /// A host to add to the chart, after pressing `a`.
+    #[default]
+    Host,
+    /// The label of a marker at the time `m` was pressed.
+    Marker(DateTime<Local>),
+}
+
+/// A host or marker label being typed in.
+#[derive(Default)]
+struct Input {
+    prompt: Prompt,
+    text: String,
+    /// Why the host last entered couldn't be added.
+    error: Option<String>,
+}
+
+/// A change to what the chart shows, from the keyboard.
+#[derive(Debug)]
+enum ViewChange {
+    ZoomIn,
+    ZoomOut,
+    /// Keep more or less history to zoom out and scroll back through.
+    GrowHistory,
+    ShrinkHistory,
+    ScrollBack,
+    ScrollForward,
+    ScrollToLive,
+    /// Go back to the window set with `--buffer`, following the latest samples.
+    ResetZoom,
+    SelectPrevious,
+    SelectNext,
+    /// Hide or show the line of the nth host, counting from 1.
+    Toggle(usize),
+    ToggleLegend,
+    /// Switch between plotting latency, jitter and both.
+    CyclePlot,
+    /// Switch between showing times in the unit that suits them, milliseconds and microseconds.
+    CycleUnits,
+    /// Switch the lower panel of the dual panel view between loss and jitter.
+    SwitchPanel,
+}
+
+impl ViewChange {
+    fn from_key(code: KeyCode) -> Option<Self> {
+        match code {
+            KeyCode::Char('+' | '=' | ']') => Some(ViewChange::ZoomIn),
+            KeyCode::Char('-' | '[') => Some(ViewChange::ZoomOut),
+            KeyCode::Char('}') => Some(ViewChange::GrowHistory),
+            KeyCode::Char('{') => Some(ViewChange::ShrinkHistory),
+            KeyCode::Left => Some(ViewChange::ScrollBack),
+            KeyCode::Right => Some(ViewChange::ScrollForward),
+            KeyCode::End => Some(ViewChange::ScrollToLive),
+            KeyCode::Char('z') => Some(ViewChange::ResetZoom),
+            KeyCode::Up => Some(ViewChange::SelectPrevious),
+            KeyCode::Down => Some(ViewChange::SelectNext),
+            KeyCode::Char('l') => Some(ViewChange::ToggleLegend),
+            KeyCode::Char('j') => Some(ViewChange::CyclePlot),
+            KeyCode::Char('u') => Some(ViewChange::CycleUnits),
+            KeyCode::Tab => Some(ViewChange::SwitchPanel),
+            KeyCode::Char(c @ '1'..='9') => Some(ViewChange::Toggle(c as usize - '0' as usize)),
+            _ => None,
         }
     }
 }
@@ -239,54 +1434,116 @@ impl From<PingResult> for Update {
 #[derive(Debug)]
 enum Event {
     Update(usize, Update),
+    Replayed(Sample),
+    Key(KeyEvent),
+    Mouse(MouseEvent),
+    /// The terminal was resized to this many columns and rows.
+    Resize(u16, u16),
+    /// The network of each host's address, looked up with `--asn`.
+    Networks(Vec<(usize, Option<String>)>),
     Terminate,
     Render,
 }
 
+/// Tells the thread sampling a host to stop, once gping exits or the host is removed.
+#[derive(Clone)]
+struct StopSignal {
+    exiting: Arc<AtomicBool>,
+    removed: Arc<AtomicBool>,
+}
+
+impl StopSignal {
+    fn new(exiting: &Arc<AtomicBool>) -> Self {
+        StopSignal {
+            exiting: Arc::clone(exiting),
+            removed: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn remove(&self) {
+        self.removed.store(true, Ordering::Release);
+    }
+
+    fn is_set(&self) -> bool {
+        self.exiting.load(Ordering::Acquire) || self.removed.load(Ordering::Acquire)
+    }
+}
+
 fn start_render_thread(
+    interval: Duration,
     kill_event: Arc<AtomicBool>,
     cmd_tx: Sender<Event>,
 ) -> JoinHandle<Result<()>> {
     thread::spawn(move || {
         while !kill_event.load(Ordering::Acquire) {
-            sleep(Duration::from_millis(250));
+            sleep(interval);
             cmd_tx.send(Event::Render)?;
         }
         Ok(())
     })
 }
 
-fn start_cmd_thread(
-    watch_cmd: &str,
-    host_id: usize,
-    watch_interval: Option<f32>,
-    cmd_tx: Sender<Event>,
-    kill_event: Arc<AtomicBool>,
-) -> JoinHandle<Result<()>> {
+/// The program to run for a command given with `--cmd` and its arguments. Without `shell`, the
+/// command is split on whitespace.
+fn command_line(watch_cmd: &str, shell: bool) -> (String, Vec<String>) {
+    if shell {
+        #[cfg(target_os = "windows")]
+        return (
+            "cmd".to_string(),
+            vec!["/C".to_string(), watch_cmd.to_string()],
+        );
+        #[cfg(not(target_os = "windows"))]
+        return (
+            "sh".to_string(),
+            vec!["-c".to_string(), watch_cmd.to_string()],
+        );
+    }
     let mut words = watch_cmd.split_ascii_whitespace();
     let cmd = words
         .next()
         .expect("Must specify a command to watch")
         .to_string();
-    let cmd_args = words.map(|w| w.to_string()).collect::<Vec<String>>();
+    (cmd, words.map(|w| w.to_string()).collect())
+}
+
+/// Run a command over and over, sending how long each run took, or with `metric` the first number
+/// it printed.
+fn start_cmd_thread(
+    watch_cmd: &str,
+    host_id: usize,
+    args: &Args,
+    cmd_tx: Sender<Event>,
+    stop: StopSignal,
+) -> JoinHandle<Result<()>> {
+    let (cmd, cmd_args) = command_line(watch_cmd, args.cmd_shell);
+    let watch_interval = args.watch_interval;
+    let metric = args.cmd_metric;
 
     let interval = Duration::from_millis((watch_interval.unwrap_or(0.5) * 1000.0) as u64);
 
     // Pump cmd watches into the queue
     thread::spawn(move || -> Result<()> {
-        while !kill_event.load(Ordering::Acquire) {
+        while !stop.is_set() {
             let start = Instant::now();
-            let mut child = Command::new(&cmd)
+            let child = Command::new(&cmd)
                 .args(&cmd_args)
                 .stderr(Stdio::null())
-                .stdout(Stdio::null())
+                .stdout(if metric {
+                    Stdio::piped()
+                } else {
+                    Stdio::null()
+                })
                 .spawn()?;
-            let status = child.wait()?;
+            let output = child.wait_with_output()?;
             let duration = start.elapsed();
-            let update = if status.success() {
-                Update::Result(duration)
-            } else {
-                Update::Timeout
+            let value = match metric {
+                true => plot_data::parse_metric(&String::from_utf8_lossy(&output.stdout))
+                    .and_then(plot_data::metric_duration),
+                false => Some(duration),
+            };
+            let update = match value {
+                Some(value) if output.status.success() => Update::Result(value, None),
+                _ => Update::Failed,
             };
             cmd_tx.send(Event::Update(host_id, update))?;
             sleep(interval);
@@ -295,62 +1552,1195 @@ fn start_cmd_thread(
     })
 }
 
-fn start_ping_thread(
-    options: PingOptions,
-    host_id: usize,
-    ping_tx: Sender<Event>,
+/// Send the samples of a recorded session at the times they were taken, `speed` times faster,
+/// then render and terminate if `exit_when_done` is set.
+fn start_replay_thread(
+    samples: Vec<Sample>,
+    speed: f64,
+    exit_when_done: bool,
+    cmd_tx: Sender<Event>,
     kill_event: Arc<AtomicBool>,
-) -> Result<JoinHandle<Result<()>>> {
-    let stream = ping(options)?;
-    // Pump ping messages into the queue
-    Ok(thread::spawn(move || -> Result<()> {
-        while !kill_event.load(Ordering::Acquire) {
-            match stream.recv() {
-                Ok(v) => {
-                    ping_tx.send(Event::Update(host_id, v.into()))?;
-                }
-                Err(_) => {
-                    // Stream closed, just break
+) -> JoinHandle<Result<()>> {
+    thread::spawn(move || -> Result<()> {
+        let started = Instant::now();
+        let Some(first) = samples.first().map(|sample| sample.at) else {
+            return Ok(());
+        };
+        for sample in samples {
+            let due = (sample.at - first).to_std()?.div_f64(speed);
+            // Sleep in short steps, so quitting isn't held up by a long gap in the session.
+            while let Some(remaining) = due.checked_sub(started.elapsed()) {
+                if kill_event.load(Ordering::Acquire) {
                     return Ok(());
                 }
+                sleep(remaining.min(Duration::from_millis(100)));
             }
+            cmd_tx.send(Event::Replayed(sample))?;
+        }
+        if exit_when_done {
+            // Show the stats for the end of the session, however quickly it was replayed.
+            cmd_tx.send(Event::Render)?;
+            cmd_tx.send(Event::Terminate)?;
         }
         Ok(())
-    }))
+    })
+}
+
+/// Look up the network of each host's address in the background, as it can take a few seconds.
+/// Failing to is not worth stopping for, so hosts are just shown without one.
+fn start_asn_thread(addresses: Vec<(usize, IpAddr)>, tx: Sender<Event>) {
+    if addresses.is_empty() {
+        return;
+    }
+    thread::spawn(move || {
+        let ips = addresses.iter().map(|(_, ip)| *ip).collect::<Vec<_>>();
+        if let Ok(networks) = asn::lookup(&ips) {
+            let networks = addresses
+                .iter()
+                .map(|(host_id, _)| *host_id)
+                .zip(networks)
+                .collect();
+            let _ = tx.send(Event::Networks(networks));
+        }
+    });
+}
+
+type PingThread = JoinHandle<Result<()>>;
+
+/// Start pinging every host, returning the address each one is pinging along with the thread
+/// forwarding its results. No threads are started unless every host can be pinged. With `stagger`,
+/// the hosts are started spread over their interval, rather than all probed at the same instant.
+fn start_ping_threads(
+    options: Vec<(usize, PingOptions, StopSignal)>,
+    stagger: bool,
+    ping_tx: Sender<Event>,
+) -> Result<Vec<(usize, Option<IpAddr>, PingThread)>> {
+    let mut streams = vec![];
+    let count = options.len();
+    let started = Instant::now();
+    for (idx, (host_id, options, stop)) in options.into_iter().enumerate() {
+        if stagger {
+            let due = options.interval.mul_f64(idx as f64 / count as f64);
+            sleep(due.saturating_sub(started.elapsed()));
+        }
+        let target = options.target.to_string();
+        let remote = options.ssh.is_some();
+        let stream = ping(options)?;
+        // Over SSH the remote host resolves the target, and WebSocket URLs are resolved when
+        // connecting.
+        if stream.destination().is_none() && !remote && !target.contains("://") {
+            bail!("Could not resolve hostname {target}");
+        }
+        streams.push((host_id, stream, stop));
+    }
+    Ok(streams
+        .into_iter()
+        .map(|(host_id, stream, stop)| {
+            let destination = stream.destination();
+            let ping_tx = ping_tx.clone();
+            // Pump ping messages into the queue. Waiting in short steps stops the pinger soon after
+            // it's told to, rather than at its next result.
+            let thread = thread::spawn(move || -> Result<()> {
+                while !stop.is_set() {
+                    match stream.recv_timeout(Duration::from_millis(100)) {
+                        // A host started again at a new interval is already sampled by the new
+                        // pinger, so a late result from this one isn't recorded.
+                        Ok(_) if stop.is_set() => break,
+                        Ok(v) => {
+                            ping_tx.send(Event::Update(host_id, v.into()))?;
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => {
+                            // Stream closed, just break
+                            return Ok(());
+                        }
+                    }
+                }
+                Ok(())
+            });
+            (host_id, destination, thread)
+        })
+        .collect())
+}
+
+/// Join the threads that have finished, such as those of pingers started again at a new
+/// interval, so they don't pile up while gping runs.
+fn join_finished(threads: &mut Vec<PingThread>) -> Result<()> {
+    let (finished, running) = threads
+        .drain(..)
+        .partition::<Vec<_>, _>(|t| t.is_finished());
+    *threads = running;
+    for thread in finished {
+        thread.join().unwrap()?;
+    }
+    Ok(())
+}
+
+/// The options for pinging `host`, from the command line arguments and the `interval` given for
+/// the host, if any.
+fn ping_options(host: &str, interval: Option<Duration>, args: &Args) -> Result<PingOptions> {
+    let interval = interval.unwrap_or_else(|| {
+        Duration::from_millis((args.watch_interval.unwrap_or(0.2) * 1000.0) as u64)
+    });
+    let interface = args.interface.clone();
+
+    let host = ascii_host(host)?;
+    // With both, hosts are resolved to an address of each version upfront
+    let mut ping_opts = if args.ipv4 && !args.ipv6 {
+        PingOptions::new_ipv4(host, interval, interface)
+    } else if args.ipv6 && !args.ipv4 {
+        PingOptions::new_ipv6(host, interval, interface)
+    } else {
+        PingOptions::new(host, interval, interface)
+    };
+    #[cfg(not(target_os = "windows"))]
+    if let Some(ping_args) = &args.ping_args {
+        ping_opts = ping_opts.with_raw_arguments(ping_args.clone());
+    }
+    if let Some(source) = args.source {
+        ping_opts = ping_opts.with_source(source);
+    }
+    if let Some(ssh) = &args.ssh {
+        ping_opts = ping_opts.via_ssh(ssh);
+    }
+    if let Some(every) = args.reresolve {
+        ping_opts = ping_opts.with_reresolve(every);
+    }
+    #[cfg(not(target_os = "windows"))]
+    if let Some(ping_binary) = &args.ping_binary {
+        ping_opts = ping_opts.with_ping_binary(ping_binary);
+    }
+    Ok(ping_opts)
+}
+
+/// Start pinging a host typed in while running, adding it to the chart.
+fn add_host(
+    host: &str,
+    args: &Args,
+    app: &mut App,
+    names: &HostNames,
+    color: Color,
+    ping_tx: Sender<Event>,
+    stop: StopSignal,
+) -> Result<PingThread> {
+    let (host, label) = split_label(host);
+    let (host, interval) = split_interval(host);
+    let (host, name) = match shorthand(host)?.as_deref() {
+        None => (host.to_string(), None),
+        Some([(host, name)]) => (host.clone(), name.clone()),
+        Some(_) => {
+            bail!("{host} stands for more than one host, so can only be given on the command line")
+        }
+    };
+    let label = label.map(str::to_string).or(name);
+    let host_id = app.data.len();
+    let options = ping_options(&host, interval, args)?;
+    let options_interval = options.interval;
+    let Some((_, destination, thread)) =
+        start_ping_threads(vec![(host_id, options, stop)], false, ping_tx)?.pop()
+    else {
+        bail!("Could not start pinging {host}");
+    };
+    let display = match (label, destination) {
+        (Some(label), _) => label,
+        (None, Some(ip)) => format!("{host} ({ip})"),
+        (None, None) => host.clone(),
+    };
+    let mut data = PlotData::new(
+        display,
+        app.history.num_seconds() as u64,
+        Style::default().fg(color),
+        args.simple_graphics,
+    );
+    data.address = destination;
+    data.interval = Some(options_interval);
+    app.data.push(data);
+    names.write().unwrap().push(host);
+    Ok(thread)
+}
+
+/// Encode internationalised hostnames to punycode, as ping and the system resolver expect.
+fn ascii_host(host: &str) -> Result<String> {
+    if host.is_ascii() {
+        return Ok(host.to_string());
+    }
+    let Ok(encoded_host) = idna::domain_to_ascii(host) else {
+        bail!("Could not encode host {host} to punycode")
+    };
+    Ok(encoded_host)
+}
+
+/// Draw the stats for each host above the chart, or to its left with `--layout horizontal`, the
+/// status bar below them, and the box for adding a host while it's open.
+fn draw(f: &mut Frame, app: &App, args: &Args) {
+    app.chart_areas.borrow_mut().clear();
+    app.host_areas.borrow_mut().clear();
+    app.images.borrow_mut().clear();
+    if let Some(color) = app.background_color {
+        f.render_widget(Block::default().bg(color), f.area());
+    }
+    let [main, status] = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)])
+        .horizontal_margin(args.horizontal_margin)
+        .areas(f.area());
+    match args.layout {
+        LayoutMode::Vertical => draw_vertical(f, app, args, main),
+        LayoutMode::Horizontal => draw_horizontal(f, app, args, main),
+    }
+    f.render_widget(status_bar(app, args), status);
+    if let Some(input) = &app.input {
+        draw_input(f, input);
+    }
+}
+
+/// Draw the box for typing in a host to add or a marker's label, over the bottom of the chart.
+fn draw_input(f: &mut Frame, input: &Input) {
+    let area = f.area();
+    let width = area.width.min(60);
+    let height = area.height.min(4);
+    let rect = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.bottom() - height,
+        width,
+        height,
+    );
+    let mut lines = vec![Line::from(format!("{}▏", input.text))];
+    if let Some(error) = &input.error {
+        lines.push(Line::styled(
+            error.as_str(),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    let title = match input.prompt {
+        Prompt::Host => " Add a host (Enter to add, Esc to cancel) ",
+        Prompt::Marker(_) => " Label the marker (Enter to add, Esc to cancel) ",
+    };
+    f.render_widget(Clear, rect);
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)),
+        rect,
+    );
 }
 
-fn get_host_ipaddr(host: &str, force_ipv4: bool, force_ipv6: bool) -> Result<String> {
-    let mut host = host.to_string();
-    if !host.is_ascii() {
-        let Ok(encoded_host) = idna::domain_to_ascii(&host) else {
-            bail!("Could not encode host {host} to punycode")
+/// Draw the stats for each host in a row above the chart.
+fn draw_vertical(f: &mut Frame, app: &App, args: &Args, area: Rect) {
+    let rows = app.rows();
+    let chunks = Layout::default()
+        .flex(Flex::Legacy)
+        .direction(Direction::Vertical)
+        .vertical_margin(args.vertical_margin)
+        .horizontal_margin(args.horizontal_margin)
+        .constraints(
+            // The selected host has a second row, for its stats over the whole session
+            rows.iter()
+                .map(|row| match row {
+                    Row::Host(host_id, _) if app.selected == Some(*host_id) => {
+                        Constraint::Length(2)
+                    }
+                    _ => Constraint::Length(1),
+                })
+                .chain(iter::once(Constraint::Percentage(10)))
+                .collect::<Vec<_>>(),
+        )
+        .split(area);
+
+    let total_chunks = chunks.len();
+
+    let header_chunks = &chunks[0..total_chunks - 1];
+    let chart_chunk = &chunks[total_chunks - 1];
+
+    let since = app.window_start();
+    for (row, chunk) in rows.into_iter().zip(header_chunks) {
+        let (host_id, plot_data) = match row {
+            Row::Group(name, hosts) => {
+                let fields = PlotData::group_fields(name, &hosts, since, app.units)
+                    .into_iter()
+                    .map(Paragraph::new)
+                    .collect();
+                draw_header_row(f, Modifier::BOLD, fields, *chunk);
+                continue;
+            }
+            Row::Host(host_id, plot_data) => (host_id, plot_data),
         };
-        host = encoded_host;
+        app.host_areas.borrow_mut().push((*chunk, host_id));
+        let selected = app.selected == Some(host_id);
+        let header_stats = plot_data.header_stats(since, selected, &app.thresholds, app.units);
+        if selected {
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(1), Constraint::Length(1)])
+                .split(*chunk);
+            let modifier = app.header_modifier(host_id);
+            draw_header_row(f, modifier, header_stats, rows[0]);
+            draw_header_row(f, modifier, plot_data.session_stats(app.units), rows[1]);
+        } else {
+            draw_header_row(f, app.header_modifier(host_id), header_stats, *chunk);
+        }
     }
-    let ipaddr: Vec<_> = (host.as_str(), 80)
-        .to_socket_addrs()
-        .with_context(|| format!("Resolving {host}"))?
-        .map(|s| s.ip())
-        .collect();
-    if ipaddr.is_empty() {
-        bail!("Could not resolve hostname {}", host)
+
+    draw_charts(f, app, args, *chart_chunk);
+}
+
+fn draw_header_row(f: &mut Frame, modifier: Modifier, header_stats: Vec<Paragraph>, area: Rect) {
+    // The host takes 30% of the width, and the stats share the rest
+    let header_layout = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            iter::once(Constraint::Percentage(30))
+                .chain(iter::repeat_n(
+                    Constraint::Fill(1),
+                    header_stats.len().saturating_sub(1),
+                ))
+                .collect::<Vec<_>>(),
+        )
+        .split(area);
+
+    for (area, paragraph) in header_layout.iter().zip(header_stats) {
+        f.render_widget(paragraph.add_modifier(modifier), *area);
     }
-    let ipaddr = if force_ipv4 {
-        ipaddr
-            .iter()
-            .find(|ip| matches!(ip, IpAddr::V4(_)))
-            .ok_or_else(|| anyhow!("Could not resolve '{}' to IPv4", host))
-    } else if force_ipv6 {
-        ipaddr
+}
+
+/// Draw the stats for each host in a column, with the chart taking the rest of the width.
+fn draw_horizontal(f: &mut Frame, app: &App, args: &Args, area: Rect) {
+    let since = app.window_start();
+    let mut lines = vec![];
+    // The lines of each host's stats, for finding the host clicked on
+    let mut host_lines = vec![];
+    for row in app.rows() {
+        if !lines.is_empty() {
+            lines.push(Line::default());
+        }
+        let (host_id, plot_data) = match row {
+            Row::Group(name, hosts) => {
+                let fields = PlotData::group_fields(name, &hosts, since, app.units);
+                for (idx, field) in fields
+                    .into_iter()
+                    .filter(|field| !field.is_empty())
+                    .enumerate()
+                {
+                    let field = match idx {
+                        0 => field,
+                        _ => format!("  {field}"),
+                    };
+                    lines.push(Line::styled(
+                        field,
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ));
+                }
+                continue;
+            }
+            Row::Host(host_id, plot_data) => (host_id, plot_data),
+        };
+        let selected = app.selected == Some(host_id);
+        let mut fields = plot_data.header_fields(since, app.units);
+        if selected {
+            fields.extend(plot_data.session_fields(app.units));
+        }
+        host_lines.push((lines.len(), fields.len(), host_id));
+        for (idx, field) in fields.into_iter().enumerate() {
+            // Indent the stats under the host, which is marked if it's selected
+            let field = match idx {
+                0 if selected => format!("▶ {field}"),
+                0 => field,
+                _ => format!("  {field}"),
+            };
+            let style = match idx {
+                1 => plot_data.last_style(since, &app.thresholds),
+                _ => plot_data.header_style(),
+            };
+            lines.push(Line::styled(
+                field,
+                style.add_modifier(app.header_modifier(host_id)),
+            ));
+        }
+    }
+    // Fit the longest line, but leave most of the width for the chart
+    let width = lines.iter().map(Line::width).max().unwrap_or_default() as u16 + 2;
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .vertical_margin(args.vertical_margin)
+        .horizontal_margin(args.horizontal_margin)
+        .constraints([
+            Constraint::Max(width.min(area.width * 2 / 5)),
+            Constraint::Fill(1),
+        ])
+        .split(area);
+
+    f.render_widget(Paragraph::new(lines), chunks[0]);
+    for (first, count, host_id) in host_lines {
+        let area = Rect {
+            y: chunks[0].y + first as u16,
+            height: count as u16,
+            ..chunks[0]
+        };
+        app.host_areas
+            .borrow_mut()
+            .push((area.intersection(chunks[0]), host_id));
+    }
+    draw_charts(f, app, args, chunks[1]);
+}
+
+/// Format a duration in whole seconds like `1h02m03s`, for the status bar.
+fn format_seconds(seconds: i64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}h{minutes:02}m{seconds:02}s")
+    } else if minutes > 0 {
+        format!("{minutes}m{seconds:02}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
+
+/// How often targets are probed, which depends on whether they are hosts or commands unless set
+/// with `--watch-interval`, or for a host like "example.com@2s".
+fn probe_interval(args: &Args) -> String {
+    // Without resolving shorthands, which can be slow to do on every frame
+    let (hosts, commands) = match &args.cmd {
+        Some(commands) if commands.is_empty() => (false, true),
+        Some(commands) => (!args.hosts_or_commands.is_empty(), !commands.is_empty()),
+        None => (true, false),
+    };
+    let hosts = hosts || !args.group.is_empty();
+    let interval = match (args.watch_interval, hosts, commands) {
+        (Some(interval), ..) => format!("every {interval}s"),
+        (None, _, false) => "every 0.2s".to_string(),
+        (None, false, true) => "every 0.5s".to_string(),
+        (None, true, true) => "every 0.2s, commands 0.5s".to_string(),
+    };
+    let host_intervals = match &args.cmd {
+        Some(commands) if commands.is_empty() => vec![],
+        _ => args.hosts_or_commands.iter().collect(),
+    };
+    let own_intervals = host_intervals
+        .into_iter()
+        .chain(args.group.iter().flat_map(|group| &group.hosts))
+        .any(|host| split_interval(split_label(host).0).1.is_some());
+    match own_intervals {
+        true => format!("{interval}, some hosts their own"),
+        false => interval,
+    }
+}
+
+/// A line below the chart with how long the session has run, how often and how many times each
+/// host has been probed, the window shown, and which view options are on.
+fn status_bar<'a>(app: &'a App, args: &Args) -> Paragraph<'a> {
+    let mut parts = vec![format!(
+        "up {}",
+        format_seconds((app.now() - app.started).num_seconds())
+    )];
+    parts.push(match &app.replay {
+        Some(clock) => format!("replay {}x", clock.speed),
+        None if app.interval_scale != 1.0 => {
+            format!("{} x{}", probe_interval(args), app.interval_scale)
+        }
+        None => probe_interval(args),
+    });
+    parts.push(format!(
+        "window {}",
+        format_seconds(app.display_interval.num_seconds())
+    ));
+    parts.push(format!(
+        "sent {}",
+        app.hosts()
+            .map(|(_, plot_data)| format!("{} {}", plot_data.display, plot_data.sent()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+
+    let hidden = app
+        .hosts()
+        .filter(|(_, plot_data)| plot_data.hidden)
+        .count();
+    if hidden > 0 {
+        parts.push(format!("{hidden} hidden"));
+    }
+    if app.scrolled_to.is_some() {
+        parts.push("paused".to_string());
+    }
+    if let Some(percentile) = app.clamp_percentile {
+        parts.push(format!("clamped to p{percentile}"));
+    }
+    if args.split {
+        parts.push("split".to_string());
+    }
+    if !app.legend {
+        parts.push("legend off".to_string());
+    }
+    match app.plot_mode {
+        PlotMode::Latency => {}
+        PlotMode::Jitter => parts.push("jitter".to_string()),
+        PlotMode::Both => parts.push("latency and jitter".to_string()),
+    }
+    if let Some(message) = &app.message {
+        parts.push(message.clone());
+    }
+    Paragraph::new(parts.join(" │ ")).style(Style::default().fg(app.theme.muted()))
+}
+
+/// Draw the chart, or a chart for each host with `--split`, in `area`.
+fn draw_charts(f: &mut Frame, app: &App, args: &Args, area: Rect) {
+    // Whether the chart is following the latest samples, or scrolled back to an earlier time.
+    let position = match app.scrolled_to {
+        Some(end) => Span::styled(
+            format!("◀ {} (End to go live)", app.format_time(end)),
+            Style::default().fg(Color::Yellow),
+        ),
+        None => Span::styled("● live", Style::default().fg(Color::Green)),
+    };
+    let alerts = app
+        .active_alerts()
+        .map(|(host, reason)| format!("{}: {reason}", host.display))
+        .collect::<Vec<_>>();
+    let mut block = Block::default()
+        .borders(Borders::NONE)
+        .title(Line::from(position).right_aligned());
+    if !alerts.is_empty() {
+        block = block.title(Line::styled(
+            format!("⚠ {} (Enter to acknowledge)", alerts.join(", ")),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+    // The latest outages shown, with the host if there's more than one
+    let since = app.window_start();
+    let plotted = app.plotted().count();
+    let outages = app
+        .plotted()
+        .flat_map(|(_, host)| {
+            host.outages(since)
+                .into_iter()
+                .map(move |outage| (outage, host))
+        })
+        .sorted_by(|(a, _), (b, _)| a.0.total_cmp(&b.0))
+        .map(|((start, end), host)| {
+            let start_time = DateTime::<Utc>::from_timestamp_millis((start * 1_000.0) as i64)
+                .unwrap_or_default()
+                .with_timezone(&Local);
+            let length = format_seconds((end - start).round() as i64);
+            match plotted {
+                1 => format!("✕ {} for {length}", app.format_time(start_time)),
+                _ => format!(
+                    "✕ {} {} for {length}",
+                    host.display,
+                    app.format_time(start_time)
+                ),
+            }
+        })
+        .collect::<Vec<_>>();
+    if !outages.is_empty() {
+        block = block.title(Line::styled(
+            outages[outages.len().saturating_sub(3)..].join("  "),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    let chart_area = block.inner(area);
+    f.render_widget(block, area);
+
+    if args.split {
+        // Each host gets its own chart, stacked in the order of the headers
+        let panes = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(
+                iter::repeat_n(Constraint::Fill(1), app.plotted().count()).collect::<Vec<_>>(),
+            )
+            .split(chart_area);
+        for (plot, pane) in app.plotted().zip(panes.iter()) {
+            draw_chart(f, app, &[plot], *pane);
+        }
+    } else {
+        draw_chart(f, app, &app.plotted().collect::<Vec<_>>(), chart_area);
+    }
+}
+
+/// Pad axis labels to `width`, so that the Y axes of charts stacked with the same width line up.
+fn pad_labels(labels: Vec<Span>, width: usize) -> Vec<Span> {
+    labels
+        .into_iter()
+        .map(|label| Span {
+            content: format!("{:width$}", label.content).into(),
+            ..label
+        })
+        .collect()
+}
+
+/// Draw a chart of `plots` in `area`, with their outages shaded and the markers shown, and the
+/// loss chart or lower panel of the dual panel view under it if enabled.
+fn draw_chart(f: &mut Frame, app: &App, plots: &[(usize, &PlotData)], area: Rect) {
+    // The panel under the chart, and how many times its height the chart takes
+    let panel = match (app.dual, app.loss_chart) {
+        (Some(panel), _) => Some((panel, 1)),
+        (None, true) => Some((Panel::Loss, 3)),
+        (None, false) => None,
+    };
+    let [start, end] = app.x_axis_bounds();
+    let y_axis_bounds = app.y_axis_bounds(plots);
+    let [bottom, top] = y_axis_bounds;
+
+    // The space left of the Y axis is also taken by the start of the first X axis label, which
+    // only the chart has.
+    let mut label_width = app
+        .x_axis_labels([start, end])
+        .first()
+        .map_or(0, |label| label.width().saturating_sub(1));
+    let metric = !plots.is_empty() && plots.iter().all(|(_, plot)| plot.metric);
+    let y_axis_labels = app.y_axis_labels(y_axis_bounds, metric);
+    label_width = label_width.max(y_axis_labels.iter().map(Span::width).max().unwrap_or(0));
+    let area = match panel {
+        Some((panel, fill)) => {
+            let [area, panel_area] =
+                Layout::vertical([Constraint::Fill(fill), Constraint::Fill(1)]).areas(area);
+            let (lines, bounds, labels) = panel_lines(app, plots, panel);
+            label_width = label_width.max(labels.iter().map(Span::width).max().unwrap_or(0));
+            draw_panel(
+                f,
+                app,
+                plots,
+                &lines,
+                bounds,
+                pad_labels(labels, label_width),
+                panel_area,
+            );
+            area
+        }
+        None => area,
+    };
+
+    // Long windows are downsampled to a few points for each dot across the chart
+    let scene = app.scene(plots, area.width.max(1) as f64 * 2.0);
+    // Each outage is filled by a line up and down the chart at every dot across it, of which
+    // there are two per column with braille
+    let step = (end - start) / (area.width.max(1) as f64 * 2.0);
+    let mut shading = scene
+        .outages
+        .iter()
+        .map(|&(from, to)| {
+            let mut points = vec![];
+            let mut x = from;
+            while x <= to {
+                let (first, second) = match points.len() % 4 {
+                    0 => (bottom, top),
+                    _ => (top, bottom),
+                };
+                points.push((x, first));
+                points.push((x, second));
+                x += step;
+            }
+            points
+        })
+        .collect::<Vec<_>>();
+    // Each marker is a line from the bottom to the top of the chart, named in the legend
+    let markers = app
+        .markers
+        .iter()
+        .map(|marker| (marker.at.timestamp_millis() as f64 / 1_000f64, marker))
+        .filter(|(x, _)| (start..=end).contains(x))
+        .map(|(x, marker)| {
+            let name = format!("{} {}", app.format_time(marker.at), marker.label);
+            (name.trim_end().to_string(), [(x, bottom), (x, top)])
+        })
+        .collect::<Vec<_>>();
+
+    // The lines are plotted right of the Y axis labels and line, and above the X axis line and
+    // labels
+    let left = (label_width as u16).min(area.width / 3) + 1;
+    let plotted = Rect {
+        x: area.x + left,
+        width: area.width.saturating_sub(left),
+        height: area.height.saturating_sub(2),
+        ..area
+    };
+    app.chart_areas.borrow_mut().push(plotted);
+    let (lines, dots) = match app.graphics {
+        // The terminal draws the lines, outages and dots over the chart instead, which keeps its
+        // axes, legend and markers. The lines are left empty, so the legend still names them.
+        true => {
+            let lines = scene
+                .lines
+                .iter()
+                .map(|(style, _)| (*style, vec![]))
+                .collect();
+            app.images.borrow_mut().push(graphics::Image {
+                area: plotted,
+                scene,
+            });
+            shading.clear();
+            (lines, vec![])
+        }
+        false => (scene.lines, scene.dots),
+    };
+    let labels = pad_labels(y_axis_labels, label_width);
+    f.render_widget(
+        chart(app, plots, &lines, &shading, &markers, &dots, labels),
+        area,
+    );
+    for (area, host_id) in legend_entries(app, plots, &markers, plotted) {
+        app.host_areas.borrow_mut().push((area, host_id));
+    }
+    // The part of the chart being dragged across is highlighted on every chart
+    if let Some((_, from, to)) = app.drag {
+        let selected = Rect {
+            x: from.min(to),
+            width: from.abs_diff(to) + 1,
+            ..plotted
+        };
+        f.buffer_mut().set_style(
+            selected.intersection(plotted),
+            Style::default().bg(app.theme.muted()),
+        );
+    }
+}
+
+/// Where the legend of the chart of `plots` and `markers` lists each host, if it's shown. Like
+/// the chart, this puts it in the top left of the `plotted` area as long as it takes up at most
+/// half of its width and height, with a row for each line named.
+fn legend_entries(
+    app: &App,
+    plots: &[(usize, &PlotData)],
+    markers: &[(String, [(f64, f64); 2])],
+    plotted: Rect,
+) -> Vec<(Rect, usize)> {
+    let mut names = vec![];
+    for (host_id, plot) in plots {
+        if app.plot_mode != PlotMode::Jitter {
+            names.push((Span::raw(plot.display.as_str()).width(), Some(*host_id)));
+        }
+        if app.plot_mode != PlotMode::Latency {
+            let name = format!("{} jitter", plot.display);
+            names.push((Span::raw(name).width(), Some(*host_id)));
+        }
+    }
+    let lines = names.len();
+    names.extend(
+        markers
             .iter()
-            .find(|ip| matches!(ip, IpAddr::V6(_)))
-            .ok_or_else(|| anyhow!("Could not resolve '{}' to IPv6", host))
+            .map(|(name, _)| (Span::raw(name).width(), None)),
+    );
+    let width = names.iter().map(|(width, _)| *width).max().unwrap_or(0) as u16 + 2;
+    let height = names.len() as u16 + 2;
+    if !app.legend
+        || (lines <= 1 && markers.is_empty())
+        || width > plotted.width / 2
+        || height > plotted.height / 2
+    {
+        return vec![];
+    }
+    names
+        .into_iter()
+        .enumerate()
+        .filter_map(|(idx, (_, host_id))| {
+            let area = Rect::new(plotted.x, plotted.y + 1 + idx as u16, width, 1);
+            Some((area, host_id?))
+        })
+        .collect()
+}
+
+type Points = Vec<(f64, f64)>;
+
+/// The lines of the panel under the chart for each of `plots`, with the panel's Y axis bounds and
+/// labels.
+fn panel_lines<'a>(
+    app: &App,
+    plots: &[(usize, &PlotData)],
+    panel: Panel,
+) -> (Vec<Points>, [f64; 2], Vec<Span<'a>>) {
+    let since = app.window_start();
+    match panel {
+        Panel::Loss => (
+            plots
+                .iter()
+                .map(|(_, plot)| plot.loss(since, app.loss_window))
+                .collect(),
+            [0.0, 100.0],
+            ["0%", "loss", "100%"]
+                .map(|label| app.axis_label(label.to_string()))
+                .to_vec(),
+        ),
+        Panel::Jitter => {
+            let lines = plots
+                .iter()
+                .map(|(_, plot)| plot.jitter_since(since).to_vec())
+                .collect::<Vec<_>>();
+            let max = lines
+                .iter()
+                .flatten()
+                .map(|(_, jitter)| *jitter)
+                .filter(|jitter| !jitter.is_nan())
+                .fold(0_f64, f64::max);
+            // Leave room over the highest jitter, as the chart above does
+            let bounds = [0.0, (max * 1.1).max(1.0)];
+            let metric = !plots.is_empty() && plots.iter().all(|(_, plot)| plot.metric);
+            let labels = app
+                .y_axis_labels(bounds, metric)
+                .into_iter()
+                .map(|label| app.axis_label(label.content.into_owned()))
+                .collect();
+            (lines, bounds, labels)
+        }
+    }
+}
+
+/// Draw the `lines` of the panel under the chart of `plots`, with the markers, lined up with the
+/// chart above it.
+fn draw_panel(
+    f: &mut Frame,
+    app: &App,
+    plots: &[(usize, &PlotData)],
+    lines: &[Points],
+    y_axis_bounds: [f64; 2],
+    labels: Vec<Span>,
+    area: Rect,
+) {
+    let x_axis_bounds = app.x_axis_bounds();
+    let [bottom, top] = y_axis_bounds;
+    let markers = app
+        .markers
+        .iter()
+        .map(|marker| marker.at.timestamp_millis() as f64 / 1_000f64)
+        .filter(|x| (x_axis_bounds[0]..=x_axis_bounds[1]).contains(x))
+        .map(|x| [(x, bottom), (x, top)])
+        .collect::<Vec<_>>();
+    let mut datasets = plots
+        .iter()
+        .zip(lines)
+        .map(|((host_id, plot), points)| {
+            Dataset::default()
+                .marker(plot.marker())
+                .graph_type(GraphType::Line)
+                .style(app.line_style(*host_id))
+                .data(points)
+        })
+        .collect::<Vec<_>>();
+    if let Some((_, plot)) = plots.first() {
+        datasets.extend(markers.iter().map(|points| {
+            Dataset::default()
+                .marker(plot.marker())
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Yellow))
+                .data(points)
+        }));
+    }
+    let chart = Chart::new(datasets)
+        .style(Style {
+            bg: app.background_color,
+            ..Style::default()
+        })
+        .x_axis(Axis::default().bounds(x_axis_bounds))
+        .y_axis(y_axis(app, y_axis_bounds, labels));
+    f.render_widget(chart, area);
+}
+
+fn y_axis<'a>(app: &App, bounds: [f64; 2], labels: Vec<Span<'a>>) -> Axis<'a> {
+    Axis::default()
+        .style(Style::default().fg(app.axis_color))
+        .bounds(bounds)
+        .labels(labels)
+}
+
+/// A chart of `plots`, with the Y axis scaled to fit them, over the lines in `shading` and with
+/// the named lines in `markers`, the points in `dots` in their color and the Y axis `labels`. Each
+/// plot's latency and jitter are drawn through its points in `lines`, if shown.
+fn chart<'a>(
+    app: &'a App,
+    plots: &[(usize, &'a PlotData)],
+    lines: &'a [(Style, Points)],
+    shading: &'a [Points],
+    markers: &'a [(String, [(f64, f64); 2])],
+    dots: &'a [(Color, Points)],
+    labels: Vec<Span<'a>>,
+) -> Chart<'a> {
+    let mut datasets = vec![];
+    if let Some((_, plot)) = plots.first() {
+        datasets.extend(shading.iter().map(|points| {
+            Dataset::default()
+                .marker(plot.marker())
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::DarkGray))
+                .data(points)
+        }));
+    }
+    // Each host has a latency and a jitter line, in that order
+    for ((_, d), host_lines) in plots.iter().zip(lines.chunks(2)) {
+        let [(style, latency), (jitter_style, jitter)] = host_lines else {
+            continue;
+        };
+        if app.plot_mode != PlotMode::Jitter {
+            datasets.push(d.dataset(latency).style(*style));
+        }
+        if app.plot_mode != PlotMode::Latency {
+            datasets.push(d.jitter_dataset(jitter).style(*jitter_style));
+        }
+    }
+    if let Some((_, plot)) = plots.first() {
+        datasets.extend(markers.iter().map(|(name, points)| {
+            Dataset::default()
+                .name(name.as_str())
+                .marker(plot.marker())
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Yellow))
+                .data(points)
+        }));
+    }
+    // Drawn as dots whatever the marker, so they stand out from the lines
+    datasets.extend(dots.iter().map(|(color, points)| {
+        Dataset::default()
+            .marker(symbols::Marker::Dot)
+            .graph_type(GraphType::Scatter)
+            .style(Style::default().fg(*color).add_modifier(Modifier::BOLD))
+            .data(points)
+    }));
+
+    let y_axis_bounds = app.y_axis_bounds(plots);
+    let x_axis_bounds = app.x_axis_bounds();
+
+    let lines = match app.plot_mode {
+        PlotMode::Both => plots.len() * 2,
+        _ => plots.len(),
+    };
+    let legend_position = if app.legend && (lines > 1 || !markers.is_empty()) {
+        Some(LegendPosition::TopLeft)
     } else {
-        ipaddr
-            .first()
-            .ok_or_else(|| anyhow!("Could not resolve '{}' to IP", host))
+        None
     };
-    Ok(ipaddr?.to_string())
+
+    Chart::new(datasets)
+        // The chart draws its own background, which is the terminal's unless one is set
+        .style(Style {
+            bg: app.background_color,
+            ..Style::default()
+        })
+        .legend_position(legend_position)
+        // Only hide the legend if it would cover most of the chart
+        .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(app.axis_color))
+                .bounds(x_axis_bounds)
+                .labels(app.x_axis_labels(x_axis_bounds)),
+        )
+        .y_axis(y_axis(app, y_axis_bounds, labels))
+}
+
+type Tui = Terminal<CrosstermBackend<BufWriter<io::Stdout>>>;
+
+/// Where results are shown: the chart, stats lines printed for each host, or a line for status
+/// bars.
+enum Output {
+    /// The terminal, which draws the charts' lines as images with `graphics`, and how many were
+    /// drawn with the last frame.
+    Tui {
+        terminal: Tui,
+        graphics: Option<Protocol>,
+        images: usize,
+    },
+    Text,
+    Status,
+}
+
+impl Output {
+    fn new(args: &Args) -> Result<Self> {
+        if args.no_tui {
+            return Ok(Output::Text);
+        }
+        if args.status_line {
+            return Ok(Output::Status);
+        }
+        enable_raw_mode()?;
+        let stdout = io::stdout();
+        let mut backend = CrosstermBackend::new(BufWriter::with_capacity(1024 * 1024 * 4, stdout));
+        if args.clear {
+            execute!(backend, EnterAlternateScreen)?;
+        }
+        if !args.no_mouse {
+            execute!(backend, EnableMouseCapture)?;
+        }
+
+        let mut terminal = Terminal::new(backend)?;
+        terminal.clear()?;
+        Ok(Output::Tui {
+            terminal,
+            graphics: args.graphics.and_then(Graphics::protocol),
+            images: 0,
+        })
+    }
+
+    /// How often to render.
+    fn interval(&self) -> Duration {
+        match self {
+            Output::Tui { .. } => Duration::from_millis(250),
+            Output::Text | Output::Status => Duration::from_secs(1),
+        }
+    }
+
+    /// Draw the screen, and save it to `{stem}.ans` with its colors and to `{stem}.txt` without.
+    fn dump(&mut self, app: &App, args: &Args, stem: &str) -> Result<()> {
+        if let Output::Tui { terminal, .. } = self {
+            let frame = terminal.draw(|f| draw(f, app, args))?;
+            for (extension, ansi) in [("ans", true), ("txt", false)] {
+                let path = format!("{stem}.{extension}");
+                std::fs::write(&path, snapshot::screen_text(frame.buffer, ansi)?)
+                    .with_context(|| format!("Error writing {path}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn render(&mut self, app: &App, args: &Args) -> Result<()> {
+        match self {
+            Output::Tui {
+                terminal,
+                graphics,
+                images,
+            } => {
+                terminal.draw(|f| draw(f, app, args))?;
+                if let Some(protocol) = *graphics {
+                    let out = terminal.backend_mut();
+                    let drawn = app.images.borrow();
+                    // Kitty's images are numbered from 1
+                    for (id, image) in drawn.iter().enumerate() {
+                        image.draw(protocol, id + 1, out)?;
+                    }
+                    graphics::remove(protocol, drawn.len() + 1..=*images, out)?;
+                    *images = drawn.len();
+                    Backend::flush(out)?;
+                }
+            }
+            Output::Text => {
+                let now = app.format_time(app.now());
+                let since = app.window_start();
+                for (_, plot_data) in app.hosts() {
+                    println!(
+                        "{now} {}",
+                        plot_data.header_fields(since, app.units).join("  ")
+                    );
+                }
+            }
+            // Status bars that run gping each time they update only want the line at the end
+            Output::Status if args.count.is_none() && args.duration.is_none() => {
+                println!("{}", status_line(app));
+                io::stdout().flush()?;
+            }
+            Output::Status => {}
+        }
+        Ok(())
+    }
+
+    /// Restore the terminal before the remaining threads are joined.
+    fn restore(&mut self) -> Result<()> {
+        if let Output::Tui {
+            terminal,
+            graphics,
+            images,
+        } = self
+        {
+            if let Some(protocol) = *graphics {
+                graphics::remove(protocol, 1..=*images, terminal.backend_mut())?;
+            }
+            disable_raw_mode()?;
+            execute!(terminal.backend_mut(), DisableMouseCapture)?;
+            terminal.show_cursor()?;
+
+            // Leave the cursor on a line of its own under the chart, at the size the terminal is
+            // now rather than when it was last drawn
+            let size = terminal.size()?;
+            terminal.set_cursor_position(Position {
+                x: 0,
+                y: size.height.saturating_sub(1),
+            })?;
+            writeln!(terminal.backend_mut())?;
+            Backend::flush(terminal.backend_mut())?;
+        }
+        Ok(())
+    }
+
+    /// Fit the layout to the terminal's new size and redraw all of it, rather than waiting for
+    /// the next render to notice.
+    fn resize(&mut self, app: &App, args: &Args, width: u16, height: u16) -> Result<()> {
+        if let Output::Tui { terminal, .. } = self {
+            terminal.resize(Rect::new(0, 0, width, height))?;
+        }
+        self.render(app, args)
+    }
+
+    fn leave_alternate_screen(&mut self) -> Result<()> {
+        if let Output::Tui { terminal, .. } = self {
+            execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+        }
+        Ok(())
+    }
+}
+
+/// The latest sample of each host shown and a sparkline of the window shown, on one line.
+fn status_line(app: &App) -> String {
+    let since = app.window_start();
+    app.plotted()
+        .map(|(_, plot_data)| plot_data.status(since, app.units, STATUS_SPARKLINE_WIDTH))
+        .join("  ")
+}
+
+/// Print the stats of each host over the session, like ping does when it exits, and with
+/// `sparklines` the round trip times kept as a line that fits the terminal.
+fn print_summary(app: &App, sparklines: bool) {
+    let width = crossterm::terminal::size().map_or(SPARKLINE_WIDTH, |(columns, _)| {
+        // Leaving room for the length of time before it
+        (columns as usize).saturating_sub(16).min(SPARKLINE_WIDTH)
+    });
+    for (_, plot_data) in app.hosts() {
+        let session = plot_data.session();
+        println!("--- {} ---", plot_data.display);
+        println!(
+            "{} sent, {} received, {:.1}% loss",
+            session.sent(),
+            session.received(),
+            session.loss() * 100.0
+        );
+        if let (Some(min), Some(avg), Some(max), Some(p95)) =
+            (session.min(), session.avg(), session.max(), session.p95())
+        {
+            println!(
+                "rtt min/avg/max/p95 = {}",
+                [min, avg, max, p95]
+                    .map(|rtt| plot_data.format_value(rtt, app.units))
+                    .join("/")
+            );
+        }
+        if let (true, Some(first), Some(last)) =
+            (sparklines, plot_data.data.first(), plot_data.data.last())
+        {
+            println!(
+                "last {}: {}",
+                format_seconds((last.0 - first.0).round() as i64),
+                // No wider than the samples, so it isn't mostly gaps between them
+                plot_data::sparkline(&plot_data.data, width.min(plot_data.data.len()))
+            );
+        }
+    }
+}
+
+/// The hosts that breached `--fail-on-loss` or `--fail-on-latency` over the session, and how.
+fn threshold_breaches(app: &App, args: &Args) -> Vec<String> {
+    let mut breaches = vec![];
+    for (_, plot_data) in app.hosts() {
+        let session = plot_data.session();
+        if let Some(max_loss) = args.fail_on_loss {
+            let loss = session.loss() * 100.0;
+            if loss > max_loss {
+                breaches.push(format!(
+                    "{}: {loss:.1}% loss is over --fail-on-loss {max_loss}%",
+                    plot_data.display
+                ));
+            }
+        }
+        if let (Some(max_latency), Some(avg)) = (args.fail_on_latency, session.avg()) {
+            if avg > max_latency {
+                breaches.push(format!(
+                    "{}: average round trip time {} is over --fail-on-latency {}",
+                    plot_data.display,
+                    plot_data.format_value(avg, app.units),
+                    plot_data.format_value(max_latency, app.units)
+                ));
+            }
+        }
+    }
+    breaches
 }
 
 fn generate_man_page(path: &Path) -> anyhow::Result<()> {
@@ -368,50 +2758,92 @@ fn main() -> Result<()> {
     };
     let args: Args = Args::parse();
 
-    if args.hosts_or_commands.is_empty() {
+    if let Some(provider) = &args.list_regions {
+        println!("{}", region_map::list_regions(provider)?);
+        return Ok(());
+    }
+
+    if args.speed <= 0.0 {
+        bail!("--speed must be greater than 0");
+    }
+    if let Some(percentile) = args.clamp_percentile {
+        if !(0.0..=100.0).contains(&percentile) {
+            bail!("--clamp-percentile must be between 0 and 100");
+        }
+    }
+    if StrftimeItems::new(&args.time_format).any(|item| item == Item::Error) {
+        bail!("--time-format {} is not a valid format", args.time_format);
+    }
+    if let Some(path) = &args.snapshot_on_exit {
+        snapshot::check_path(path)?;
+    }
+    if let Some(loss) = args.fail_on_loss {
+        if !(0.0..=100.0).contains(&loss) {
+            bail!("--fail-on-loss must be between 0 and 100");
+        }
+    }
+    if args
+        .bell_interval
+        .is_some_and(|interval| !(interval >= 0.0 && interval.is_finite()))
+    {
+        bail!("--bell-interval must be a number of seconds");
+    }
+    if let (Some(warn), Some(crit)) = (args.warn, args.crit) {
+        if crit < warn {
+            bail!("--crit must not be lower than --warn");
+        }
+    }
+    let recording = args.replay.as_deref().map(replay::load).transpose()?;
+
+    // A replayed session is sent from a thread started with the app below.
+    let (targets, groups) = match &recording {
+        Some(_) => (
+            vec![],
+            args.group
+                .iter()
+                .flat_map(|group| group.hosts.iter().map(move |_| group.name.clone()))
+                .collect(),
+        ),
+        None => targets(&args)?,
+    };
+    if recording.is_none() && targets.is_empty() {
         return Err(anyhow!("At least one host or command must be given (i.e gping google.com). Use --help for a full list of arguments."));
     }
 
     let mut data = vec![];
+    let history = args.history.max(args.buffer);
 
-    let colors = Colors::from(args.color_codes_or_names.iter());
-    let hosts_or_commands: Vec<String> = args
-        .hosts_or_commands
-        .clone()
-        .into_iter()
-        .map(|s| match region_map::try_host_from_cloud_region(&s) {
-            None => s,
-            Some(new_domain) => new_domain,
-        })
-        .collect();
+    let mut colors = Colors::from(args.color_codes_or_names.iter()).with_theme(args.theme);
+    let hosts_or_commands: Vec<String> = match &recording {
+        Some(recording) => recording.targets.clone(),
+        None => targets
+            .iter()
+            .map(|target| target.name().to_string())
+            .collect(),
+    };
 
-    for (host_or_cmd, color) in hosts_or_commands.iter().zip(colors) {
+    let displays: Vec<&str> = match &recording {
+        Some(recording) => recording.targets.iter().map(String::as_str).collect(),
+        None => targets.iter().map(Target::display).collect(),
+    };
+    for (display, color) in displays.into_iter().zip(&mut colors) {
         let color = color?;
-        let display = match args.cmd {
-            true => host_or_cmd.to_string(),
-            false => format!(
-                "{} ({})",
-                host_or_cmd,
-                get_host_ipaddr(host_or_cmd, args.ipv4, args.ipv6)?
-            ),
-        };
+        // The address pinged is added once pinging starts, unless the host was named.
         data.push(PlotData::new(
-            display,
-            args.buffer,
+            display.to_string(),
+            history,
             Style::default().fg(color),
             args.simple_graphics,
         ));
     }
 
-    #[cfg(not(target_os = "windows"))]
-    let interface: Option<String> = args.interface.clone();
-    #[cfg(target_os = "windows")]
-    let interface: Option<String> = None;
+    // Grouped hosts are the last targets
+    let first_grouped = data.len() - groups.len();
+    for (host, group) in data[first_grouped..].iter_mut().zip(groups) {
+        host.group = Some(group);
+    }
 
-    #[cfg(not(target_os = "windows"))]
-    let ping_args: Option<Vec<String>> = args.ping_args.clone();
-    #[cfg(target_os = "windows")]
-    let ping_args: Option<Vec<String>> = None;
+    let names: HostNames = Arc::new(RwLock::new(hosts_or_commands.clone()));
 
     let (key_tx, rx) = mpsc::channel();
 
@@ -419,193 +2851,475 @@ fn main() -> Result<()> {
 
     let killed = Arc::new(AtomicBool::new(false));
 
-    for (host_id, host_or_cmd) in hosts_or_commands.iter().cloned().enumerate() {
-        if args.cmd {
-            let cmd_thread = start_cmd_thread(
-                &host_or_cmd,
-                host_id,
-                args.watch_interval,
-                key_tx.clone(),
-                std::sync::Arc::clone(&killed),
-            );
-            threads.push(cmd_thread);
-        } else {
-            let interval =
-                Duration::from_millis((args.watch_interval.unwrap_or(0.2) * 1000.0) as u64);
-
-            let mut ping_opts = if args.ipv4 {
-                PingOptions::new_ipv4(host_or_cmd, interval, interface.clone())
-            } else if args.ipv6 {
-                PingOptions::new_ipv6(host_or_cmd, interval, interface.clone())
-            } else {
-                PingOptions::new(host_or_cmd, interval, interface.clone())
-            };
-            if let Some(ping_args) = &ping_args {
-                ping_opts = ping_opts.with_raw_arguments(ping_args.clone());
+    // Every sample is sent to each recorder, which writes them from its own thread.
+    let mut recorders = vec![];
+    if let Some(path) = &args.csv {
+        let (csv_tx, csv_thread) = csv::start_writer(path, names.clone())?;
+        recorders.push(csv_tx);
+        threads.push(csv_thread);
+    }
+    #[cfg(feature = "sqlite")]
+    if let Some(path) = &args.db {
+        let (db_tx, db_thread) = db::start_writer(path, names.clone(), build::PKG_VERSION)?;
+        recorders.push(db_tx);
+        threads.push(db_thread);
+    }
+    let exporter = match args.prometheus {
+        Some(addr) => Some(prometheus::Exporter::start(addr, names.clone())?),
+        None => None,
+    };
+    let mut last_bell: Option<Instant> = None;
+    let mut notifier = args
+        .notify
+        .then(|| notify::Notifier::new(args.notify_timeouts));
+
+    // Indexed by host_id, for stopping hosts when they're removed
+    let mut stops: Vec<StopSignal> = (0..hosts_or_commands.len())
+        .map(|_| StopSignal::new(&killed))
+        .collect();
+    let mut all_ping_opts = vec![];
+    let mut named = vec![];
+    for (host_id, target) in targets.into_iter().enumerate() {
+        match target {
+            Target::Command(cmd) => {
+                data[host_id].command = true;
+                data[host_id].metric = args.cmd_metric;
+                let cmd_thread =
+                    start_cmd_thread(&cmd, host_id, &args, key_tx.clone(), stops[host_id].clone());
+                threads.push(cmd_thread);
+            }
+            Target::Host(host, label, interval) => {
+                if label.is_some() {
+                    named.push(host_id);
+                }
+                let ping_opts = ping_options(&host, interval, &args)?;
+                data[host_id].interval = Some(ping_opts.interval);
+                all_ping_opts.push((host_id, ping_opts, stops[host_id].clone()));
             }
-
-            threads.push(start_ping_thread(
-                ping_opts,
-                host_id,
-                key_tx.clone(),
-                std::sync::Arc::clone(&killed),
-            )?);
         }
     }
+    if !all_ping_opts.is_empty() {
+        let started = start_ping_threads(all_ping_opts, args.stagger, key_tx.clone())?;
+        for (host_id, destination, thread) in started {
+            let host = &mut data[host_id];
+            host.address = destination;
+            match destination {
+                Some(ip) if !named.contains(&host_id) => {
+                    host.display = format!("{} ({})", host.display, ip)
+                }
+                _ => {}
+            }
+            threads.push(thread);
+        }
+    }
+    let mut app = App::new(data, args.buffer, history);
+    app.clamp_percentile = args.clamp_percentile;
+    app.thresholds = Thresholds {
+        warn: args.warn,
+        crit: args.crit,
+    };
+    if args.alert {
+        app.alert_timeouts = Some(args.alert_timeouts);
+    }
+    app.theme = args.theme;
+    app.axis_color = args.axis_color.unwrap_or(args.theme.axis());
+    app.label_color = args.label_color;
+    app.background_color = args.background_color;
+    app.timeout_markers = args.timeout_markers;
+    app.elapsed_axis = args.elapsed;
+    app.utc = args.utc;
+    app.time_format = args.time_format.clone();
+    app.plot_mode = args.plot;
+    app.units = args.units;
+    app.loss_window = args.loss_window.as_secs_f64();
+    app.loss_chart = args.loss_chart;
+    app.dual = args.dual;
+    if let Some(path) = &args.state_file {
+        let history = chrono::Duration::from_std(Duration::from_secs(history))?;
+        if let Some((samples, markers)) = state::load(path, &hosts_or_commands, history)? {
+            // The chart starts from the restored samples, but they aren't alerted on again
+            if let Some(first) = samples.first() {
+                app.started = first.at;
+            }
+            for sample in samples {
+                app.data[sample.host_id].update(sample.rtt, sample.at);
+            }
+            app.markers = markers;
+        }
+    }
+    let mut last_saved = Instant::now();
+    if let Some(recording) = recording {
+        app.replaying(&recording.samples, args.speed);
+        app.markers = recording.markers;
+        threads.push(start_replay_thread(
+            recording.samples,
+            args.speed,
+            args.no_tui || args.status_line,
+            key_tx.clone(),
+            std::sync::Arc::clone(&killed),
+        ));
+    }
+    let mut output = Output::new(&args)?;
+    app.graphics = matches!(
+        output,
+        Output::Tui {
+            graphics: Some(_),
+            ..
+        }
+    );
     threads.push(start_render_thread(
+        output.interval(),
         std::sync::Arc::clone(&killed),
         key_tx.clone(),
     ));
 
-    let mut app = App::new(data, args.buffer);
-    enable_raw_mode()?;
-    let stdout = io::stdout();
-    let mut backend = CrosstermBackend::new(BufWriter::with_capacity(1024 * 1024 * 4, stdout));
-    let rect = backend.size()?;
-
-    if args.clear {
-        execute!(
-            backend,
-            SetSize(rect.width, rect.height),
-            EnterAlternateScreen,
-        )?;
-    } else {
-        execute!(backend, SetSize(rect.width, rect.height),)?;
+    if args.asn {
+        let addresses = app
+            .data
+            .iter()
+            .enumerate()
+            .filter_map(|(host_id, host)| Some((host_id, host.address?)))
+            .collect::<Vec<_>>();
+        start_asn_thread(addresses, key_tx.clone());
     }
 
-    let mut terminal = Terminal::new(backend)?;
-    terminal.clear()?;
-
     // Pump keyboard messages into the queue
+    let ping_tx = key_tx.clone();
     let killed_thread = std::sync::Arc::clone(&killed);
-    thread::spawn(move || -> Result<()> {
-        while !killed_thread.load(Ordering::Acquire) {
-            if event::poll(Duration::from_secs(5))? {
-                if let CEvent::Key(key) = event::read()? {
-                    match key.code {
-                        KeyCode::Char('q') | KeyCode::Esc => {
-                            key_tx.send(Event::Terminate)?;
-                            break;
-                        }
-                        KeyCode::Char('c') if key.modifiers == KeyModifiers::CONTROL => {
-                            key_tx.send(Event::Terminate)?;
-                            break;
+    if let Output::Tui { .. } = output {
+        thread::spawn(move || -> Result<()> {
+            while !killed_thread.load(Ordering::Acquire) {
+                if event::poll(Duration::from_secs(5))? {
+                    match event::read()? {
+                        CEvent::Key(key) => key_tx.send(Event::Key(key))?,
+                        CEvent::Mouse(mouse) => key_tx.send(Event::Mouse(mouse))?,
+                        CEvent::Resize(width, height) => {
+                            key_tx.send(Event::Resize(width, height))?
                         }
                         _ => {}
                     }
                 }
             }
-        }
-        Ok(())
-    });
+            Ok(())
+        });
+    }
 
+    let deadline = args.duration.map(|duration| Instant::now() + duration);
     loop {
-        match rx.recv()? {
-            Event::Update(host_id, update) => {
-                match update {
-                    Update::Result(duration) => app.update(host_id, Some(duration)),
-                    Update::Timeout => app.update(host_id, None),
-                    Update::Unknown => (),
-                    Update::Terminated(e, _) if e.success() => {
-                        break;
+        // Renders are sent at least every second, so this is checked often enough
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            break;
+        }
+        let sample = match rx.recv()? {
+            // Results can arrive after a host is removed, before its thread stops
+            Event::Update(host_id, _) if app.data[host_id].removed => continue,
+            Event::Replayed(sample) if app.data[sample.host_id].removed => continue,
+            Event::Update(host_id, update) => match update {
+                // Prefer the time ping saw the reply arrive over when it was read
+                Update::Result(duration, arrived) => Sample {
+                    at: arrived.map_or_else(Local::now, DateTime::from),
+                    host_id,
+                    rtt: Some(duration),
+                },
+                Update::Timeout => Sample {
+                    at: Local::now(),
+                    host_id,
+                    rtt: None,
+                },
+                // Lost like a timeout, but marked on the chart as a failure
+                Update::Failed => {
+                    let at = Local::now();
+                    app.data[host_id].failure(at);
+                    Sample {
+                        at,
+                        host_id,
+                        rtt: None,
                     }
-                    Update::Terminated(e, stderr) => {
-                        eprintln!("There was an error running ping: {e}\nStderr: {stderr}\n");
-                        break;
+                }
+                Update::Duplicate => {
+                    app.duplicate(host_id);
+                    continue;
+                }
+                Update::AddressChanged(ip) => {
+                    let host = &mut app.data[host_id];
+                    let old = host.address.replace(ip);
+                    // The header shows the address after the host, unless the host was named
+                    if let Some(name) = old.and_then(|old| {
+                        host.display
+                            .strip_suffix(&format!(" ({old})"))
+                            .map(str::to_string)
+                    }) {
+                        host.display = format!("{name} ({ip})");
                     }
-                };
+                    host.network = None;
+                    if args.asn {
+                        start_asn_thread(vec![(host_id, ip)], ping_tx.clone());
+                    }
+                    let marker = Marker {
+                        at: Local::now(),
+                        label: format!("{} now {ip}", names.read().unwrap()[host_id]),
+                    };
+                    for recorder in &recorders {
+                        recorder.send(Entry::Marker(marker.clone()))?;
+                    }
+                    app.markers.push(marker);
+                    continue;
+                }
+                Update::Unknown => continue,
+                Update::Terminated(e, _) if e.success() => {
+                    break;
+                }
+                Update::Terminated(e, stderr) => {
+                    eprintln!("There was an error running ping: {e}\nStderr: {stderr}\n");
+                    break;
+                }
+            },
+            Event::Replayed(sample) => sample,
+            Event::Networks(networks) => {
+                for (host_id, network) in networks {
+                    app.data[host_id].network = network;
+                }
+                continue;
             }
             Event::Render => {
-                terminal.draw(|f| {
-                    let chunks = Layout::default()
-                        .flex(Flex::Legacy)
-                        .direction(Direction::Vertical)
-                        .vertical_margin(args.vertical_margin)
-                        .horizontal_margin(args.horizontal_margin)
-                        .constraints(
-                            iter::repeat(Constraint::Length(1))
-                                .take(app.data.len())
-                                .chain(iter::once(Constraint::Percentage(10)))
-                                .collect::<Vec<_>>(),
-                        )
-                        .split(f.area());
-
-                    let total_chunks = chunks.len();
-
-                    let header_chunks = &chunks[0..total_chunks - 1];
-                    let chart_chunk = &chunks[total_chunks - 1];
-
-                    for (plot_data, chunk) in app.data.iter().zip(header_chunks) {
-                        let header_layout = Layout::default()
-                            .direction(Direction::Horizontal)
-                            .constraints(
-                                [
-                                    Constraint::Percentage(30),
-                                    Constraint::Percentage(10),
-                                    Constraint::Percentage(10),
-                                    Constraint::Percentage(10),
-                                    Constraint::Percentage(10),
-                                    Constraint::Percentage(10),
-                                    Constraint::Percentage(10),
-                                    Constraint::Percentage(10),
-                                ]
-                                .as_ref(),
+                output.render(&app, &args)?;
+                continue;
+            }
+            Event::Key(key) => {
+                app.message = None;
+                let ctrl_c =
+                    key.code == KeyCode::Char('c') && key.modifiers == KeyModifiers::CONTROL;
+                match (&mut app.input, key.code) {
+                    _ if ctrl_c => {
+                        killed.store(true, Ordering::Release);
+                        break;
+                    }
+                    (
+                        Some(Input {
+                            prompt: Prompt::Marker(at),
+                            text,
+                            ..
+                        }),
+                        KeyCode::Enter,
+                    ) => {
+                        let marker = Marker {
+                            at: *at,
+                            label: text.trim().to_string(),
+                        };
+                        for recorder in &recorders {
+                            recorder.send(Entry::Marker(marker.clone()))?;
+                        }
+                        app.markers.push(marker);
+                        app.input = None;
+                    }
+                    (Some(input), KeyCode::Enter) => {
+                        let host = input.text.trim().to_string();
+                        let stop = StopSignal::new(&killed);
+                        let added = colors.next().unwrap_or(Ok(Color::Reset)).and_then(|color| {
+                            add_host(
+                                &host,
+                                &args,
+                                &mut app,
+                                &names,
+                                color,
+                                ping_tx.clone(),
+                                stop.clone(),
                             )
-                            .split(*chunk);
-
-                        for (area, paragraph) in header_layout.iter().zip(plot_data.header_stats())
-                        {
-                            f.render_widget(paragraph, *area);
+                        });
+                        match added {
+                            Ok(thread) => {
+                                threads.push(thread);
+                                stops.push(stop);
+                                app.input = None;
+                                let host_id = app.data.len() - 1;
+                                if let (true, Some(ip)) = (args.asn, app.data[host_id].address) {
+                                    start_asn_thread(vec![(host_id, ip)], ping_tx.clone());
+                                }
+                            }
+                            Err(e) => {
+                                if let Some(input) = &mut app.input {
+                                    input.error = Some(format!("{e:#}"));
+                                }
+                            }
                         }
                     }
-
-                    let datasets: Vec<Dataset> = app.data.iter().map(|d| d.into()).collect();
-
-                    let y_axis_bounds = app.y_axis_bounds();
-                    let x_axis_bounds = app.x_axis_bounds();
-
-                    let chart = Chart::new(datasets)
-                        .block(Block::default().borders(Borders::NONE))
-                        .x_axis(
-                            Axis::default()
-                                .style(Style::default().fg(Color::Gray))
-                                .bounds(x_axis_bounds)
-                                .labels(app.x_axis_labels(x_axis_bounds)),
-                        )
-                        .y_axis(
-                            Axis::default()
-                                .style(Style::default().fg(Color::Gray))
-                                .bounds(y_axis_bounds)
-                                .labels(app.y_axis_labels(y_axis_bounds)),
-                        );
-
-                    f.render_widget(chart, *chart_chunk)
-                })?;
+                    (Some(_), KeyCode::Esc) => app.input = None,
+                    (Some(input), KeyCode::Backspace) => {
+                        input.text.pop();
+                    }
+                    (Some(input), KeyCode::Char(c)) => input.text.push(c),
+                    (Some(_), _) => {}
+                    (None, KeyCode::Char('q') | KeyCode::Esc) => {
+                        killed.store(true, Ordering::Release);
+                        break;
+                    }
+                    // Hosts can't be added to a replayed session
+                    (None, KeyCode::Char('a')) if app.replay.is_none() => {
+                        app.input = Some(Input::default());
+                    }
+                    (None, KeyCode::Char('m')) => {
+                        app.input = Some(Input {
+                            prompt: Prompt::Marker(app.now()),
+                            ..Input::default()
+                        });
+                    }
+                    (None, KeyCode::Enter) => app.acknowledge(),
+                    (None, KeyCode::Char('S')) => {
+                        let path = format!("gping-{}.svg", Local::now().format("%Y%m%d-%H%M%S"));
+                        app.message = Some(match app.snapshot(Path::new(&path)) {
+                            Ok(()) => format!("saved {path}"),
+                            Err(e) => format!("{e:#}"),
+                        });
+                    }
+                    (None, KeyCode::Char('T')) => {
+                        let stem = format!("gping-{}", Local::now().format("%Y%m%d-%H%M%S"));
+                        app.message = Some(match output.dump(&app, &args, &stem) {
+                            Ok(()) => format!("saved {stem}.ans and {stem}.txt"),
+                            Err(e) => format!("{e:#}"),
+                        });
+                    }
+                    // Ping every host faster or slower, by starting it again at its new interval
+                    (None, KeyCode::Char(key @ ('<' | '>'))) if app.replay.is_none() => {
+                        app.interval_scale = match key {
+                            '<' => app.interval_scale / 2.0,
+                            _ => app.interval_scale * 2.0,
+                        }
+                        .clamp(MIN_INTERVAL_SCALE, MAX_INTERVAL_SCALE);
+                        join_finished(&mut threads)?;
+                        for (host_id, old_stop) in stops.iter_mut().enumerate() {
+                            let host = &app.data[host_id];
+                            let Some(interval) = host.interval.filter(|_| !host.removed) else {
+                                continue;
+                            };
+                            let name = names.read().unwrap()[host_id].clone();
+                            let stop = StopSignal::new(&killed);
+                            let started = ping_options(
+                                &name,
+                                Some(interval.mul_f64(app.interval_scale)),
+                                &args,
+                            )
+                            .and_then(|options| {
+                                start_ping_threads(
+                                    vec![(host_id, options, stop.clone())],
+                                    false,
+                                    ping_tx.clone(),
+                                )
+                            });
+                            match started {
+                                Ok(started) => {
+                                    old_stop.remove();
+                                    *old_stop = stop;
+                                    threads.extend(started.into_iter().map(|(.., thread)| thread));
+                                }
+                                Err(e) => app.message = Some(format!("{name}: {e:#}")),
+                            }
+                        }
+                    }
+                    (None, KeyCode::Char('d' | 'x')) => {
+                        if let Some(host_id) = app.remove_selected() {
+                            stops[host_id].remove();
+                        }
+                    }
+                    (None, code) => {
+                        if let Some(change) = ViewChange::from_key(code) {
+                            app.change_view(change);
+                        }
+                    }
+                }
+                output.render(&app, &args)?;
+                continue;
+            }
+            Event::Mouse(mouse) => {
+                app.mouse(mouse);
+                output.render(&app, &args)?;
+                continue;
+            }
+            Event::Resize(width, height) => {
+                // The columns being dragged across may no longer be in the chart
+                app.drag = None;
+                output.resize(&app, &args, width, height)?;
+                continue;
             }
             Event::Terminate => {
                 killed.store(true, Ordering::Release);
                 break;
             }
+        };
+        // Hosts that reach --count first wait for the rest
+        if args
+            .count
+            .is_some_and(|count| app.data[sample.host_id].sent() >= count)
+        {
+            continue;
+        }
+        app.update(sample.host_id, sample.rtt, sample.at);
+        if let Some(exporter) = &exporter {
+            exporter.record(&sample);
+        }
+        if let Some(notifier) = &mut notifier {
+            notifier.record(&sample, &app.data[sample.host_id], app.units);
+        }
+        if args.bell && sample.rtt.is_none() {
+            let interval = Duration::from_secs_f64(args.bell_interval.unwrap_or_default());
+            if last_bell.is_none_or(|at| at.elapsed() >= interval) {
+                last_bell = Some(Instant::now());
+                // Written straight to the terminal, as the bell doesn't draw anything
+                let mut stdout = io::stdout();
+                stdout.write_all(b"\x07")?;
+                stdout.flush()?;
+            }
+        }
+        for recorder in &recorders {
+            recorder.send(Entry::Sample(sample.clone()))?;
+        }
+        if let Some(path) = &args.state_file {
+            if last_saved.elapsed() >= state::INTERVAL {
+                state::save(path, &names.read().unwrap(), &app.data, &app.markers)?;
+                last_saved = Instant::now();
+            }
+        }
+        if args
+            .count
+            .is_some_and(|count| app.hosts().all(|(_, host)| host.sent() >= count))
+        {
+            break;
         }
     }
     killed.store(true, Ordering::Relaxed);
+    // Closing the channels lets the recorders finish.
+    drop(recorders);
 
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut())?;
-    terminal.show_cursor()?;
-
-    let new_size = terminal.size()?;
-    terminal.set_cursor_position(Position {
-        x: new_size.width,
-        y: new_size.height,
-    })?;
+    output.restore()?;
     for thread in threads {
         thread.join().unwrap()?;
     }
 
     if args.clear {
-        execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+        output.leave_alternate_screen()?;
     };
 
+    if let Some(path) = &args.snapshot_on_exit {
+        app.snapshot(path)?;
+    }
+    if let Some(path) = &args.state_file {
+        state::save(path, &names.read().unwrap(), &app.data, &app.markers)?;
+    }
+    // Leaving the alternate screen takes the chart with it, so a summary is left in its place
+    if args.status_line {
+        if args.count.is_some() || args.duration.is_some() {
+            println!("{}", status_line(&app));
+        }
+    } else if args.count.is_some() || args.duration.is_some() || args.clear {
+        print_summary(&app, args.clear);
+    }
+
+    let breaches = threshold_breaches(&app, &args);
+    if !breaches.is_empty() {
+        for breach in breaches {
+            eprintln!("{breach}");
+        }
+        std::process::exit(2);
+    }
+
     Ok(())
 }
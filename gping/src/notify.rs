@@ -0,0 +1,102 @@
+use crate::plot_data::{PlotData, Sample, Units};
+use std::process::{Command, Stdio};
+use std::thread;
+
+#[cfg(target_os = "windows")]
+const TOAST: &str = r#"
+[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] > $null
+$template = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02)
+$text = $template.GetElementsByTagName('text')
+$text.Item(0).AppendChild($template.CreateTextNode($env:GPING_TITLE)) > $null
+$text.Item(1).AppendChild($template.CreateTextNode($env:GPING_BODY)) > $null
+[Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier('gping').Show([Windows.UI.Notifications.ToastNotification]::new($template))
+"#;
+
+/// The command that shows a desktop notification on this platform. The title and body are passed
+/// in the environment where the command is a script, so they don't need quoting.
+fn command(title: &str, body: &str) -> Command {
+    #[cfg(target_os = "windows")]
+    let command = {
+        let mut command = Command::new("powershell");
+        command.args(["-NoProfile", "-NonInteractive", "-Command", TOAST]);
+        command
+    };
+    #[cfg(target_os = "macos")]
+    let command = {
+        let mut command = Command::new("osascript");
+        command.args([
+            "-e",
+            r#"display notification (system attribute "GPING_BODY") with title (system attribute "GPING_TITLE")"#,
+        ]);
+        command
+    };
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let command = {
+        let mut command = Command::new("notify-send");
+        command.args(["--app-name", "gping", title, body]);
+        command
+    };
+    let mut command = command;
+    command.env("GPING_TITLE", title).env("GPING_BODY", body);
+    command
+}
+
+/// Show a desktop notification from a background thread. Notifications are best effort, so
+/// failures, such as `notify-send` not being installed, are ignored.
+fn send(title: String, body: String) {
+    thread::spawn(move || {
+        let _ = command(&title, &body)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    });
+}
+
+/// Sends a notification when a host has timed out `timeouts` times in a row, and when it replies
+/// again.
+pub struct Notifier {
+    timeouts: u32,
+    /// The timeouts in a row of each host, indexed by `host_id`.
+    timeouts_in_a_row: Vec<u32>,
+}
+
+impl Notifier {
+    pub fn new(timeouts: u32) -> Self {
+        Notifier {
+            timeouts,
+            timeouts_in_a_row: vec![],
+        }
+    }
+
+    /// Track a sample of `host`, with its round trip time shown in `units`.
+    pub fn record(&mut self, sample: &Sample, host: &PlotData, units: Units) {
+        if self.timeouts_in_a_row.len() <= sample.host_id {
+            self.timeouts_in_a_row.resize(sample.host_id + 1, 0);
+        }
+        let timeouts_in_a_row = &mut self.timeouts_in_a_row[sample.host_id];
+        match sample.rtt {
+            None => {
+                *timeouts_in_a_row += 1;
+                if *timeouts_in_a_row == self.timeouts {
+                    send(
+                        format!("{} is down", host.display),
+                        format!("{} timeouts in a row", self.timeouts),
+                    );
+                }
+            }
+            Some(rtt) => {
+                if *timeouts_in_a_row >= self.timeouts {
+                    send(
+                        format!("{} is back up", host.display),
+                        format!(
+                            "Replied in {} after {timeouts_in_a_row} timeouts",
+                            host.format_value(rtt, units)
+                        ),
+                    );
+                }
+                *timeouts_in_a_row = 0;
+            }
+        }
+    }
+}
@@ -1,17 +1,257 @@
-use anyhow::Context;
+use anyhow::{bail, Context};
 use chrono::prelude::*;
 use core::option::Option;
 use core::option::Option::{None, Some};
 use core::time::Duration;
 use itertools::Itertools;
-use tui::style::Style;
+use pinger::PingStats;
+use std::borrow::Cow;
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+use tui::style::{Color, Modifier, Style};
 use tui::symbols;
 use tui::widgets::{Dataset, GraphType, Paragraph};
 
+/// The name of each host, indexed by `host_id`. Hosts added while running are appended.
+pub type HostNames = Arc<RwLock<Vec<String>>>;
+
+/// A single reply or timeout from one of the hosts.
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub at: DateTime<Local>,
+    pub host_id: usize,
+    pub rtt: Option<Duration>,
+}
+
+/// A note at a point in time, such as "restarted router", added with `m`.
+#[derive(Debug, Clone)]
+pub struct Marker {
+    pub at: DateTime<Local>,
+    pub label: String,
+}
+
+/// What is sent to the recorders: every sample, and any markers added.
+#[derive(Debug, Clone)]
+pub enum Entry {
+    Sample(Sample),
+    Marker(Marker),
+}
+
+/// Timeouts in a row that make an outage, which is shaded on the chart.
+pub const MIN_OUTAGE_TIMEOUTS: usize = 3;
+
+/// Round trip times from which a host's last value is shown in yellow, then red.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Thresholds {
+    pub warn: Option<Duration>,
+    pub crit: Option<Duration>,
+}
+
+impl Thresholds {
+    /// The color for `rtt`, which is green below the thresholds, or none if there aren't any.
+    pub fn color(&self, rtt: Duration) -> Option<Color> {
+        match (self.warn, self.crit) {
+            (None, None) => None,
+            (_, Some(crit)) if rtt >= crit => Some(Color::Red),
+            (Some(warn), _) if rtt >= warn => Some(Color::Yellow),
+            _ => Some(Color::Green),
+        }
+    }
+}
+
+/// Parse a duration like `5m`, `1.5s` or `500us`, in `default_unit` if it doesn't have one.
+fn parse_duration_in(value: &str, default_unit: &str) -> anyhow::Result<Duration> {
+    let (number, unit) = value
+        .find(|c: char| c.is_alphabetic())
+        .map_or((value, default_unit), |idx| value.split_at(idx));
+    let number: f64 = number
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid duration `{value}`"))?;
+    let seconds = match unit {
+        "h" => number * 3_600.0,
+        "m" => number * 60.0,
+        "s" => number,
+        "ms" => number / 1_000.0,
+        "us" | "µs" => number / 1_000_000.0,
+        _ => bail!("Invalid unit in `{value}`, expected h, m, s, ms or us"),
+    };
+    Duration::try_from_secs_f64(seconds).with_context(|| format!("Invalid duration `{value}`"))
+}
+
+/// Parse a round trip time like `100ms` or `1.5s`, where a bare number is milliseconds.
+pub fn parse_rtt(value: &str) -> anyhow::Result<Duration> {
+    parse_duration_in(value, "ms")
+}
+
+/// Parse a length of time like `5m` or `1h`, where a bare number is seconds.
+pub fn parse_duration(value: &str) -> anyhow::Result<Duration> {
+    parse_duration_in(value, "s")
+}
+
+/// How round trip times are shown, set with `--units` and switched between with `u`.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum Units {
+    /// In whichever unit suits each time
+    #[default]
+    Auto,
+    /// In milliseconds, to two decimal places
+    Ms,
+    /// In whole microseconds
+    Us,
+}
+
+impl Units {
+    pub fn next(self) -> Self {
+        match self {
+            Units::Auto => Units::Ms,
+            Units::Ms => Units::Us,
+            Units::Us => Units::Auto,
+        }
+    }
+
+    pub fn format(self, rtt: Duration) -> String {
+        match self {
+            Units::Auto => format!("{rtt:?}"),
+            Units::Ms => format!("{:.2}ms", rtt.as_secs_f64() * 1_000.0),
+            Units::Us => format!("{}µs", rtt.as_micros()),
+        }
+    }
+}
+
+/// The first number in the output of a command run with `--cmd-metric`, such as "48000" or the
+/// "0.253" in "time_total: 0.253".
+pub fn parse_metric(output: &str) -> Option<f64> {
+    output
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | ':' | '='))
+        .filter_map(|word| word.parse::<f64>().ok())
+        .find(|value| value.is_finite())
+}
+
+/// A number plotted with `--cmd-metric`, which is kept as if it were milliseconds so it can be
+/// plotted like a round trip time. Negative numbers can't be.
+pub fn metric_duration(value: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(value / 1_000.0).ok()
+}
+
+/// A number plotted with `--cmd-metric`, as the command printed it.
+pub fn format_metric(value: Duration) -> String {
+    let value = value.as_secs_f64() * 1_000.0;
+    format!("{}", (value * 1_000.0).round() / 1_000.0)
+}
+
+/// How many more points than buckets there must be before they are downsampled.
+const DOWNSAMPLE_FACTOR: usize = 4;
+
+/// Reduce `points` to the lowest and highest reply in each `bucket` seconds, and the first
+/// timeout, in the order they were taken, if there are many more points than buckets. Spikes and
+/// gaps are kept, while long windows stay quick to draw.
+pub fn downsample(points: &[(f64, f64)], bucket: f64) -> Cow<'_, [(f64, f64)]> {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return Cow::Borrowed(points);
+    };
+    if bucket <= 0.0 {
+        return Cow::Borrowed(points);
+    }
+    let buckets = ((last.0 - first.0) / bucket).ceil() as usize + 1;
+    if points.len() < buckets * DOWNSAMPLE_FACTOR {
+        return Cow::Borrowed(points);
+    }
+    let mut downsampled = Vec::with_capacity(buckets * 3);
+    let same_bucket =
+        |a: &(f64, f64), b: &(f64, f64)| (a.0 / bucket).floor() == (b.0 / bucket).floor();
+    for chunk in points.chunk_by(same_bucket) {
+        let replies = chunk.iter().filter(|(_, rtt)| !rtt.is_nan());
+        let kept = replies
+            .clone()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .into_iter()
+            .chain(replies.max_by(|a, b| a.1.total_cmp(&b.1)))
+            .chain(chunk.iter().find(|(_, rtt)| rtt.is_nan()))
+            .sorted_by(|a, b| a.0.total_cmp(&b.0))
+            .dedup_by(|a, b| a.0 == b.0);
+        downsampled.extend(kept.copied());
+    }
+    Cow::Owned(downsampled)
+}
+
+/// The characters of a sparkline, from lowest to highest.
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// `points` as a line of `width` characters across the time they were taken over, each as high as
+/// the average reply in its part of that time, `✕` if every sample in it timed out, and blank if
+/// there were none.
+pub fn sparkline(points: &[(f64, f64)], width: usize) -> String {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return String::new();
+    };
+    let length = (last.0 - first.0).max(f64::EPSILON);
+    // The total and count of the replies in each part, and whether anything timed out
+    let mut parts = vec![(0.0, 0, false); width];
+    for (timestamp, rtt) in points {
+        let idx = ((timestamp - first.0) / length * width as f64) as usize;
+        let Some(part) = parts.get_mut(idx.min(width.saturating_sub(1))) else {
+            continue;
+        };
+        if rtt.is_nan() {
+            part.2 = true;
+        } else {
+            part.0 += rtt;
+            part.1 += 1;
+        }
+    }
+    let averages = parts
+        .iter()
+        .map(|(total, replies, _)| (*replies > 0).then(|| total / *replies as f64))
+        .collect::<Vec<_>>();
+    let (min, max) = averages
+        .iter()
+        .flatten()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), average| {
+            (min.min(*average), max.max(*average))
+        });
+    let height = (max - min).max(f64::EPSILON);
+    parts
+        .iter()
+        .zip(averages)
+        .map(|((_, _, timed_out), average)| match average {
+            Some(average) => SPARKS[((average - min) / height * 7.0).round() as usize],
+            None if *timed_out => '✕',
+            None => ' ',
+        })
+        .collect()
+}
+
 pub struct PlotData {
     pub display: String,
     pub data: Vec<(f64, f64)>,
+    /// The change in round trip time from the sample before, for each reply that followed a
+    /// reply. Timeouts are NaN, like in `data`.
+    jitter: Vec<(f64, f64)>,
+    /// Timestamps of duplicate replies
+    pub duplicates: Vec<f64>,
+    /// Timestamps of the runs of a command that failed, which are also gaps in the line.
+    failures: Vec<f64>,
+    /// Stats for every sample since gping started, not just those kept.
+    session: PingStats,
     pub style: Style,
+    /// Removed while running, so no longer shown or sampled.
+    pub removed: bool,
+    /// Hidden from the chart while running, but still sampled.
+    pub hidden: bool,
+    /// The name of the group given with `--group`, if any.
+    pub group: Option<String>,
+    /// The address being pinged, once known.
+    pub address: Option<IpAddr>,
+    /// The network and country of the address, shown in the header with `--asn`.
+    pub network: Option<String>,
+    /// How often the host is pinged as given, which `<` and `>` speed up and slow down from.
+    /// `None` for commands and replayed hosts.
+    pub interval: Option<Duration>,
+    /// A command given with `--cmd`, whose failures are counted rather than duplicates.
+    pub command: bool,
+    /// Plots a number printed by a command with `--cmd-metric`, rather than a time.
+    pub metric: bool,
     buffer: chrono::Duration,
     simple_graphics: bool,
 }
@@ -21,96 +261,445 @@ impl PlotData {
         PlotData {
             display,
             data: Vec::with_capacity(150),
+            jitter: Vec::with_capacity(150),
+            duplicates: Vec::new(),
+            failures: Vec::new(),
+            session: PingStats::new(),
             style,
+            removed: false,
+            hidden: false,
+            group: None,
+            address: None,
+            network: None,
+            interval: None,
+            command: false,
+            metric: false,
             buffer: chrono::Duration::try_seconds(buffer as i64)
                 .with_context(|| format!("Error converting {buffer} to seconds"))
                 .unwrap(),
             simple_graphics,
         }
     }
-    pub fn update(&mut self, item: Option<Duration>) {
-        let now = Local::now();
-        let idx = now.timestamp_millis() as f64 / 1_000f64;
+    pub fn update(&mut self, item: Option<Duration>, at: DateTime<Local>) {
+        let idx = at.timestamp_millis() as f64 / 1_000f64;
+        match (self.data.last(), item) {
+            (Some((_, previous)), Some(dur)) if !previous.is_nan() => {
+                let jitter = (dur.as_micros() as f64 - previous).abs();
+                self.jitter.push((idx, jitter));
+            }
+            (_, None) => self.jitter.push((idx, f64::NAN)),
+            _ => {}
+        }
         match item {
-            Some(dur) => self.data.push((idx, dur.as_micros() as f64)),
-            None => self.data.push((idx, f64::NAN)),
+            Some(dur) => {
+                self.data.push((idx, dur.as_micros() as f64));
+                self.session.record_pong(dur);
+            }
+            None => {
+                self.data.push((idx, f64::NAN));
+                self.session.record_timeout();
+            }
         }
         // Find the last index that we should remove.
-        let earliest_timestamp = (now - self.buffer).timestamp_millis() as f64 / 1_000f64;
+        let earliest_timestamp = (at - self.buffer).timestamp_millis() as f64 / 1_000f64;
         let last_idx = self
             .data
             .iter()
             .enumerate()
             .filter(|(_, (timestamp, _))| *timestamp < earliest_timestamp)
             .map(|(idx, _)| idx)
-            .last();
+            .next_back();
         if let Some(idx) = last_idx {
             self.data.drain(0..idx).for_each(drop)
         }
+        let jitter_idx = self
+            .jitter
+            .partition_point(|(timestamp, _)| *timestamp < earliest_timestamp);
+        self.jitter.drain(0..jitter_idx.saturating_sub(1));
+        self.duplicates
+            .retain(|timestamp| *timestamp >= earliest_timestamp);
+        self.failures
+            .retain(|timestamp| *timestamp >= earliest_timestamp);
     }
 
-    pub fn header_stats(&self) -> Vec<Paragraph> {
-        let ping_header = Paragraph::new(self.display.clone()).style(self.style);
-        let items: Vec<&f64> = self
-            .data
+    /// Keep samples for `buffer`, dropping older ones as new samples come in.
+    pub fn set_buffer(&mut self, buffer: chrono::Duration) {
+        self.buffer = buffer;
+    }
+
+    pub fn duplicate(&mut self) {
+        let now = Local::now();
+        self.duplicates
+            .push(now.timestamp_millis() as f64 / 1_000f64);
+    }
+
+    /// Record a failed run of a command at `at`, before its sample is.
+    pub fn failure(&mut self, at: DateTime<Local>) {
+        self.failures.push(at.timestamp_millis() as f64 / 1_000f64);
+    }
+
+    /// The times of the failed runs of a command since `since`.
+    pub fn failures(&self, since: f64) -> impl Iterator<Item = f64> + '_ {
+        self.failures
             .iter()
-            .filter(|(_, x)| !x.is_nan())
-            .map(|(_, v)| v)
-            .sorted_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
-            .collect();
-        if items.is_empty() {
-            return vec![ping_header];
-        }
-
-        let min = **items.first().unwrap();
-        let max = **items.last().unwrap();
-        let avg = items.iter().copied().sum::<f64>() / items.len() as f64;
-        let jtr = items
+            .copied()
+            .filter(move |timestamp| *timestamp >= since)
+    }
+
+    /// The samples taken at or after `since`, a timestamp in seconds.
+    pub fn since(&self, since: f64) -> &[(f64, f64)] {
+        let start = self
+            .data
+            .partition_point(|(timestamp, _)| *timestamp < since);
+        &self.data[start..]
+    }
+
+    /// The jitter of the samples taken at or after `since`.
+    pub fn jitter_since(&self, since: f64) -> &[(f64, f64)] {
+        let start = self
+            .jitter
+            .partition_point(|(timestamp, _)| *timestamp < since);
+        &self.jitter[start..]
+    }
+
+    /// The percentage of samples that timed out in the `window` seconds up to each sample since
+    /// `since`.
+    pub fn loss(&self, since: f64, window: f64) -> Vec<(f64, f64)> {
+        let mut points = vec![];
+        let mut start = 0;
+        let mut timeouts = 0;
+        for (end, (timestamp, rtt)) in self.data.iter().enumerate() {
+            if rtt.is_nan() {
+                timeouts += 1;
+            }
+            while self.data[start].0 <= timestamp - window {
+                if self.data[start].1.is_nan() {
+                    timeouts -= 1;
+                }
+                start += 1;
+            }
+            if *timestamp >= since {
+                let loss = timeouts as f64 * 100.0 / (end + 1 - start) as f64;
+                points.push((*timestamp, loss));
+            }
+        }
+        points
+    }
+
+    /// The header fields as paragraphs, with the host marked if it's selected.
+    pub fn header_stats(
+        &self,
+        since: f64,
+        selected: bool,
+        thresholds: &Thresholds,
+        units: Units,
+    ) -> Vec<Paragraph<'_>> {
+        let mut fields = self.header_fields(since, units);
+        if selected {
+            fields[0] = format!("▶ {}", fields[0]);
+        }
+        fields
+            .into_iter()
+            .enumerate()
+            .map(|(idx, field)| match idx {
+                1 => Paragraph::new(field).style(self.last_style(since, thresholds)),
+                _ => Paragraph::new(field).style(self.header_style()),
+            })
+            .collect()
+    }
+
+    /// The style of the last round trip time in the header, colored by how it compares to
+    /// `thresholds`.
+    pub fn last_style(&self, since: f64, thresholds: &Thresholds) -> Style {
+        let last = self
+            .since(since)
             .iter()
-            .zip(items.iter().skip(1))
-            .map(|(&prev, &curr)| (curr - prev).abs())
-            .sum::<f64>()
-            / (items.len() - 1) as f64;
+            .rev()
+            .find(|(_, rtt)| !rtt.is_nan());
+        match last.and_then(|(_, rtt)| thresholds.color(Duration::from_micros(*rtt as u64))) {
+            Some(color) => self.header_style().fg(color),
+            None => self.header_style(),
+        }
+    }
+
+    /// Stats over every sample since gping started, shown for the selected host.
+    pub fn session_fields(&self, units: Units) -> Vec<String> {
+        let stats = &self.session;
+        let stat = |name: &str, value: Option<Duration>| self.format(name, value, units);
+        vec![
+            "session".to_string(),
+            format!("sent {}", stats.sent()),
+            stat("min", stats.min()),
+            stat("max", stats.max()),
+            stat("avg", stats.avg()),
+            stat("med", stats.median()),
+            stat("p95", stats.p95()),
+            format!("t/o {}", stats.timeouts()),
+        ]
+    }
+
+    /// Probes sent over the whole session.
+    pub fn sent(&self) -> u64 {
+        self.session.sent()
+    }
+
+    /// Stats over the whole session.
+    pub fn session(&self) -> &PingStats {
+        &self.session
+    }
+
+    pub fn session_stats(&self, units: Units) -> Vec<Paragraph<'_>> {
+        self.session_fields(units)
+            .into_iter()
+            .map(|field| Paragraph::new(field).style(self.header_style()))
+            .collect()
+    }
+
+    /// The style of the header, which is dimmed while the host is hidden.
+    pub fn header_style(&self) -> Style {
+        if self.hidden {
+            self.style.add_modifier(Modifier::DIM)
+        } else {
+            self.style
+        }
+    }
 
-        let percentile_position = 0.95 * items.len() as f32;
-        let rounded_position = percentile_position.round() as usize;
-        let p95 = items.get(rounded_position).map(|i| **i).unwrap_or(0f64);
+    /// The host followed by its stats since `since`, if it has had any replies.
+    /// Add the samples since `since` to `stats`.
+    fn stats(&self, since: f64, stats: &mut PingStats) {
+        for (_, value) in self.since(since) {
+            if value.is_nan() {
+                stats.record_timeout();
+            } else {
+                stats.record_pong(Duration::from_micros(*value as u64));
+            }
+        }
+    }
 
-        // count timeouts
-        let to = self.data.iter().filter(|(_, x)| x.is_nan()).count();
+    /// A stat like "avg 12.34ms", or the number printed by a command with `--cmd-metric`.
+    fn format(&self, name: &str, value: Option<Duration>, units: Units) -> String {
+        format!(
+            "{name} {}",
+            self.format_value(value.unwrap_or_default(), units)
+        )
+    }
 
-        let last = self.data.last().unwrap_or(&(0f64, 0f64)).1;
+    /// A round trip time in `units`, or the number printed by a command with `--cmd-metric`.
+    pub fn format_value(&self, value: Duration, units: Units) -> String {
+        match self.metric {
+            true => format_metric(value),
+            false => units.format(value),
+        }
+    }
+
+    /// A line for status bars like "example.com 12.34ms ▁▂▁▅", with the latest sample, or "t/o"
+    /// if it timed out, and a sparkline up to `width` wide of the samples since `since`.
+    pub fn status(&self, since: f64, units: Units, width: usize) -> String {
+        let points = self.since(since);
+        let last = match points.last() {
+            None => "-".to_string(),
+            Some((_, rtt)) if rtt.is_nan() => "t/o".to_string(),
+            Some((_, rtt)) => self.format_value(Duration::from_micros(*rtt as u64), units),
+        };
+        let sparkline = sparkline(points, width.min(points.len()));
+        format!("{} {last} {sparkline}", self.display)
+            .trim_end()
+            .to_string()
+    }
+
+    pub fn header_fields(&self, since: f64, units: Units) -> Vec<String> {
+        let mut stats = PingStats::new();
+        self.stats(since, &mut stats);
+        let display = match &self.network {
+            Some(network) => format!("{} {network}", self.display),
+            None => self.display.clone(),
+        };
+        if stats.received() == 0 {
+            return vec![display];
+        }
+
+        let stat = |name: &str, value: Option<Duration>| self.format(name, value, units);
 
         vec![
-            ping_header,
-            Paragraph::new(format!("last {:?}", Duration::from_micros(last as u64)))
-                .style(self.style),
-            Paragraph::new(format!("min {:?}", Duration::from_micros(min as u64)))
-                .style(self.style),
-            Paragraph::new(format!("max {:?}", Duration::from_micros(max as u64)))
-                .style(self.style),
-            Paragraph::new(format!("avg {:?}", Duration::from_micros(avg as u64)))
-                .style(self.style),
-            Paragraph::new(format!("jtr {:?}", Duration::from_micros(jtr as u64)))
-                .style(self.style),
-            Paragraph::new(format!("p95 {:?}", Duration::from_micros(p95 as u64)))
-                .style(self.style),
-            Paragraph::new(format!("t/o {to:?}")).style(self.style),
+            display,
+            stat("last", stats.last()),
+            stat("min", stats.min()),
+            stat("max", stats.max()),
+            stat("avg", stats.avg()),
+            stat("jtr", stats.jitter()),
+            stat("p95", stats.p95()),
+            format!("t/o {:?}", stats.timeouts()),
+            // Loss over the samples shown, then over the whole session
+            format!("loss {:.1}%", stats.loss() * 100.0),
+            format!("all {:.1}%", self.session.loss() * 100.0),
+            // Commands can't have duplicates, but can fail
+            match self.command {
+                true => format!("fail {}", self.failures(since).count()),
+                false => format!(
+                    "dup {:?}",
+                    self.duplicates
+                        .iter()
+                        .filter(|timestamp| **timestamp >= since)
+                        .count()
+                ),
+            },
+        ]
+    }
+
+    /// The header fields of a group of `hosts`, over all their samples since `since`. Each field
+    /// lines up with the same stat of the hosts, leaving out those that depend on the order of
+    /// the samples.
+    pub fn group_fields(name: &str, hosts: &[&PlotData], since: f64, units: Units) -> Vec<String> {
+        let mut stats = PingStats::new();
+        for host in hosts {
+            host.stats(since, &mut stats);
+        }
+        let name = format!("▾ {name} ({})", hosts.len());
+        if stats.received() == 0 {
+            return vec![name];
+        }
+        let stat = |name: &str, value: Option<Duration>| {
+            format!("{name} {}", units.format(value.unwrap_or_default()))
+        };
+        vec![
+            name,
+            String::new(),
+            stat("min", stats.min()),
+            stat("max", stats.max()),
+            stat("avg", stats.avg()),
+            String::new(),
+            stat("p95", stats.p95()),
+            format!("t/o {:?}", stats.timeouts()),
+            format!("loss {:.1}%", stats.loss() * 100.0),
         ]
     }
-}
 
-impl<'a> From<&'a PlotData> for Dataset<'a> {
-    fn from(plot: &'a PlotData) -> Self {
-        let slice = plot.data.as_slice();
+    /// The outages since `since`, as the times of their first timeout and of the reply that ended
+    /// them, or of their last timeout if they're ongoing.
+    pub fn outages(&self, since: f64) -> Vec<(f64, f64)> {
+        let mut outages = vec![];
+        let mut timeouts: Option<(f64, f64, usize)> = None;
+        for (timestamp, rtt) in self.since(since) {
+            if rtt.is_nan() {
+                let (first, _, count) = timeouts.unwrap_or((*timestamp, *timestamp, 0));
+                timeouts = Some((first, *timestamp, count + 1));
+                continue;
+            }
+            if let Some((first, _, count)) = timeouts.take() {
+                if count >= MIN_OUTAGE_TIMEOUTS {
+                    outages.push((first, *timestamp));
+                }
+            }
+        }
+        if let Some((first, last, count)) = timeouts {
+            if count >= MIN_OUTAGE_TIMEOUTS {
+                outages.push((first, last));
+            }
+        }
+        outages
+    }
+
+    /// The times of the timeouts since `since`, which are gaps in the line.
+    pub fn timeouts(&self, since: f64) -> impl Iterator<Item = f64> + '_ {
+        self.since(since)
+            .iter()
+            .filter(|(_, rtt)| rtt.is_nan())
+            .map(|(timestamp, _)| *timestamp)
+    }
+
+    pub fn marker(&self) -> symbols::Marker {
+        if self.simple_graphics {
+            symbols::Marker::Dot
+        } else {
+            symbols::Marker::Braille
+        }
+    }
+
+    /// The line plotted through `points`, which are from `since` or `downsample`.
+    pub fn dataset<'a>(&'a self, points: &'a [(f64, f64)]) -> Dataset<'a> {
         Dataset::default()
-            .marker(if plot.simple_graphics {
-                symbols::Marker::Dot
-            } else {
-                symbols::Marker::Braille
-            })
-            .style(plot.style)
+            .name(self.display.clone())
+            .marker(self.marker())
+            .style(self.style)
+            .graph_type(GraphType::Line)
+            .data(points)
+    }
+
+    /// The line plotted through the jitter `points`, which are from `jitter_since` or
+    /// `downsample`.
+    pub fn jitter_dataset<'a>(&'a self, points: &'a [(f64, f64)]) -> Dataset<'a> {
+        Dataset::default()
+            .name(format!("{} jitter", self.display))
+            .marker(self.marker())
+            .style(self.style)
             .graph_type(GraphType::Line)
-            .data(slice)
+            .data(points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_downsample() {
+        let mut points = (0..100).map(|i| (i as f64, 10.0)).collect::<Vec<_>>();
+        points[13].1 = 50.0;
+        points[47].1 = f64::NAN;
+        // Few points for each bucket are drawn as they are
+        assert_eq!(downsample(&points, 1.0).len(), 100);
+
+        let downsampled = downsample(&points, 10.0);
+        assert!(downsampled.len() < 30);
+        assert!(downsampled.contains(&(13.0, 50.0)));
+        assert!(downsampled
+            .iter()
+            .any(|(timestamp, rtt)| *timestamp == 47.0 && rtt.is_nan()));
+        assert!(downsampled.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn test_parse_metric() {
+        assert_eq!(parse_metric("48000\n"), Some(48000.0));
+        assert_eq!(parse_metric("temp=48.5 C"), Some(48.5));
+        assert_eq!(parse_metric("time_total:0.253"), Some(0.253));
+        assert_eq!(parse_metric("no numbers here"), None);
+        assert_eq!(
+            metric_duration(48000.0).map(format_metric),
+            Some("48000".to_string())
+        );
+        assert_eq!(metric_duration(-1.0), None);
+    }
+
+    #[test]
+    fn test_sparkline() {
+        let points = [(0.0, 10.0), (1.0, 20.0), (2.0, f64::NAN), (4.0, 30.0)];
+        assert_eq!(sparkline(&points, 5), "▁▅✕ █");
+        assert_eq!(sparkline(&[(0.0, 10.0)], 3), "▁  ");
+        assert_eq!(sparkline(&[], 3), "");
+    }
+
+    #[test]
+    fn test_status() {
+        let mut plot = PlotData::new("example.com".to_string(), 30, Style::default(), false);
+        let now = Local::now();
+        assert_eq!(plot.status(0.0, Units::Auto, 10), "example.com -");
+        plot.update(Some(Duration::from_millis(10)), now);
+        plot.update(
+            Some(Duration::from_millis(20)),
+            now + chrono::Duration::seconds(1),
+        );
+        assert_eq!(plot.status(0.0, Units::Ms, 10), "example.com 20.00ms ▁█");
+        plot.update(None, now + chrono::Duration::seconds(2));
+        assert_eq!(plot.status(0.0, Units::Ms, 3), "example.com t/o ▁█✕");
+    }
+
+    #[test]
+    fn test_units() {
+        let rtt = Duration::from_micros(12345);
+        assert_eq!(Units::Auto.format(rtt), "12.345ms");
+        assert_eq!(Units::Ms.format(rtt), "12.35ms");
+        assert_eq!(Units::Us.format(rtt), "12345µs");
+        assert_eq!(Units::Us.next(), Units::Auto);
     }
 }
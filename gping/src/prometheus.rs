@@ -0,0 +1,124 @@
+use crate::plot_data::{HostNames, Sample};
+use anyhow::{Context, Result};
+use pinger::PingStats;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Serves the stats of each host on `/metrics`, in the Prometheus text format.
+pub struct Exporter {
+    hosts: HostNames,
+    stats: Mutex<Vec<PingStats>>,
+}
+
+// Each metric's name, help text and how to read it from the stats.
+type Metric<T> = (&'static str, &'static str, fn(&PingStats) -> T);
+
+const GAUGES: [Metric<Option<Duration>>; 3] = [
+    ("last", "The most recent round trip time", PingStats::last),
+    ("avg", "The average round trip time", PingStats::avg),
+    ("p95", "The 95th percentile round trip time", PingStats::p95),
+];
+
+const COUNTERS: [Metric<u64>; 2] = [
+    ("sent", "Probes sent", PingStats::sent),
+    ("timeouts", "Probes that timed out", PingStats::timeouts),
+];
+
+/// Escape a label value, as required by the text format.
+fn label(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', "\\\"")
+        .replace('\n', r"\n")
+}
+
+impl Exporter {
+    /// Listen on `addr` from a background thread, which runs until gping exits.
+    pub fn start(addr: SocketAddr, hosts: HostNames) -> Result<Arc<Self>> {
+        let listener = TcpListener::bind(addr)
+            .with_context(|| format!("Error listening for Prometheus on {addr}"))?;
+        let exporter = Arc::new(Exporter {
+            hosts,
+            stats: Mutex::new(vec![]),
+        });
+        let serving = exporter.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // A failed request only affects that scrape.
+                let _ = serving.respond(stream);
+            }
+        });
+        Ok(exporter)
+    }
+
+    pub fn record(&self, sample: &Sample) {
+        let mut stats = self.stats.lock().unwrap();
+        if stats.len() <= sample.host_id {
+            stats.resize_with(sample.host_id + 1, PingStats::new);
+        }
+        match sample.rtt {
+            Some(rtt) => stats[sample.host_id].record_pong(rtt),
+            None => stats[sample.host_id].record_timeout(),
+        }
+    }
+
+    fn metrics(&self) -> String {
+        let hosts = self.hosts.read().unwrap();
+        let stats = self.stats.lock().unwrap();
+        // Hosts without samples yet are reported with empty stats.
+        let empty = PingStats::new();
+        let stats = |host_id: usize| stats.get(host_id).unwrap_or(&empty);
+        let mut body = String::new();
+        for (name, help, value) in GAUGES {
+            let _ = writeln!(body, "# HELP gping_rtt_{name}_seconds {help}.");
+            let _ = writeln!(body, "# TYPE gping_rtt_{name}_seconds gauge");
+            for (host_id, host) in hosts.iter().enumerate() {
+                if let Some(value) = value(stats(host_id)) {
+                    let _ = writeln!(
+                        body,
+                        "gping_rtt_{name}_seconds{{target=\"{}\"}} {}",
+                        label(host),
+                        value.as_secs_f64()
+                    );
+                }
+            }
+        }
+        for (name, help, value) in COUNTERS {
+            let _ = writeln!(body, "# HELP gping_{name}_total {help}.");
+            let _ = writeln!(body, "# TYPE gping_{name}_total counter");
+            for (host_id, host) in hosts.iter().enumerate() {
+                let _ = writeln!(
+                    body,
+                    "gping_{name}_total{{target=\"{}\"}} {}",
+                    label(host),
+                    value(stats(host_id))
+                );
+            }
+        }
+        body
+    }
+
+    fn respond(&self, mut stream: TcpStream) -> std::io::Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let mut request_line = String::new();
+        BufReader::new(&stream).read_line(&mut request_line)?;
+        let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+        let (status, body) = if path == "/metrics" {
+            ("200 OK", self.metrics())
+        } else {
+            (
+                "404 Not Found",
+                "Metrics are served on /metrics\n".to_string(),
+            )
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )
+    }
+}
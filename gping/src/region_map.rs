@@ -1,12 +1,213 @@
+use anyhow::{bail, Result};
+
 type Host = String;
 
-pub fn try_host_from_cloud_region(query: &str) -> Option<Host> {
-    match query.split_once(':') {
-        Some(("aws", region)) => Some(format!("ec2.{region}.amazonaws.com")),
-        Some(("gcp", "")) => Some("cloud.google.com".to_string()),
-        Some(("gcp", region)) => Some(format!("storage.{region}.rep.googleapis.com")),
-        _ => None,
+/// A cloud provider that can be pinged with a shorthand like `aws:eu-west-1`.
+pub struct Provider {
+    pub prefix: &'static str,
+    pub name: &'static str,
+    /// The regions known, with "" if the provider can be pinged without one.
+    pub regions: &'static [&'static str],
+    host: fn(&str) -> Host,
+}
+
+pub const PROVIDERS: [Provider; 6] = [
+    Provider {
+        prefix: "aws",
+        name: "Amazon Web Services",
+        regions: &[
+            "af-south-1",
+            "ap-east-1",
+            "ap-northeast-1",
+            "ap-northeast-2",
+            "ap-northeast-3",
+            "ap-south-1",
+            "ap-south-2",
+            "ap-southeast-1",
+            "ap-southeast-2",
+            "ap-southeast-3",
+            "ap-southeast-4",
+            "ap-southeast-5",
+            "ap-southeast-7",
+            "ca-central-1",
+            "ca-west-1",
+            "eu-central-1",
+            "eu-central-2",
+            "eu-north-1",
+            "eu-south-1",
+            "eu-south-2",
+            "eu-west-1",
+            "eu-west-2",
+            "eu-west-3",
+            "il-central-1",
+            "me-central-1",
+            "me-south-1",
+            "mx-central-1",
+            "sa-east-1",
+            "us-east-1",
+            "us-east-2",
+            "us-west-1",
+            "us-west-2",
+        ],
+        host: |region| format!("ec2.{region}.amazonaws.com"),
+    },
+    Provider {
+        prefix: "gcp",
+        name: "Google Cloud",
+        regions: &[
+            "",
+            "africa-south1",
+            "asia-east1",
+            "asia-east2",
+            "asia-northeast1",
+            "asia-northeast2",
+            "asia-northeast3",
+            "asia-south1",
+            "asia-south2",
+            "asia-southeast1",
+            "asia-southeast2",
+            "australia-southeast1",
+            "australia-southeast2",
+            "europe-central2",
+            "europe-north1",
+            "europe-north2",
+            "europe-southwest1",
+            "europe-west1",
+            "europe-west2",
+            "europe-west3",
+            "europe-west4",
+            "europe-west6",
+            "europe-west8",
+            "europe-west9",
+            "europe-west10",
+            "europe-west12",
+            "me-central1",
+            "me-central2",
+            "me-west1",
+            "northamerica-northeast1",
+            "northamerica-northeast2",
+            "northamerica-south1",
+            "southamerica-east1",
+            "southamerica-west1",
+            "us-central1",
+            "us-east1",
+            "us-east4",
+            "us-east5",
+            "us-south1",
+            "us-west1",
+            "us-west2",
+            "us-west3",
+            "us-west4",
+        ],
+        host: |region| match region {
+            "" => "cloud.google.com".to_string(),
+            region => format!("storage.{region}.rep.googleapis.com"),
+        },
+    },
+    Provider {
+        prefix: "do",
+        name: "DigitalOcean",
+        regions: &[
+            "ams3", "blr1", "fra1", "lon1", "nyc1", "nyc2", "nyc3", "sfo2", "sfo3", "sgp1", "syd1",
+            "tor1",
+        ],
+        host: |region| format!("speedtest-{region}.digitalocean.com"),
+    },
+    Provider {
+        prefix: "oci",
+        name: "Oracle Cloud",
+        regions: &[
+            "af-johannesburg-1",
+            "ap-chuncheon-1",
+            "ap-hyderabad-1",
+            "ap-melbourne-1",
+            "ap-mumbai-1",
+            "ap-osaka-1",
+            "ap-seoul-1",
+            "ap-singapore-1",
+            "ap-sydney-1",
+            "ap-tokyo-1",
+            "ca-montreal-1",
+            "ca-toronto-1",
+            "eu-amsterdam-1",
+            "eu-frankfurt-1",
+            "eu-madrid-1",
+            "eu-marseille-1",
+            "eu-milan-1",
+            "eu-paris-1",
+            "eu-stockholm-1",
+            "eu-zurich-1",
+            "il-jerusalem-1",
+            "me-abudhabi-1",
+            "me-dubai-1",
+            "me-jeddah-1",
+            "mx-queretaro-1",
+            "sa-santiago-1",
+            "sa-saopaulo-1",
+            "sa-vinhedo-1",
+            "uk-cardiff-1",
+            "uk-london-1",
+            "us-ashburn-1",
+            "us-chicago-1",
+            "us-phoenix-1",
+            "us-sanjose-1",
+        ],
+        host: |region| format!("objectstorage.{region}.oraclecloud.com"),
+    },
+    Provider {
+        prefix: "hetzner",
+        name: "Hetzner",
+        regions: &["ash", "fsn1", "hel1", "hil", "nbg1", "sin"],
+        host: |region| format!("{region}-speed.hetzner.com"),
+    },
+    Provider {
+        prefix: "cf",
+        name: "Cloudflare",
+        // Cloudflare's resolver is anycast, so there's one address for every region
+        regions: &[""],
+        host: |_| "1.1.1.1".to_string(),
+    },
+];
+
+/// The host to ping for a shorthand like `aws:eu-west-1`, or `None` if `query` isn't one. Regions
+/// a provider isn't known to have are an error, rather than a host that doesn't resolve.
+pub fn try_host_from_cloud_region(query: &str) -> Result<Option<Host>> {
+    let Some((prefix, region)) = query.split_once(':') else {
+        return Ok(None);
+    };
+    let Some(provider) = PROVIDERS.iter().find(|provider| provider.prefix == prefix) else {
+        return Ok(None);
+    };
+    if !provider.regions.contains(&region) {
+        bail!(
+            "{} has no region called \"{region}\". Run gping --list-regions {prefix} to see the regions it has.",
+            provider.name
+        );
+    }
+    Ok(Some((provider.host)(region)))
+}
+
+/// The shorthands for each region of `prefix`'s provider, or of every provider if it's empty.
+pub fn list_regions(prefix: &str) -> Result<String> {
+    let providers = PROVIDERS
+        .iter()
+        .filter(|provider| prefix.is_empty() || provider.prefix == prefix)
+        .collect::<Vec<_>>();
+    if providers.is_empty() {
+        let prefixes = PROVIDERS.map(|provider| provider.prefix);
+        bail!(
+            "Unknown provider \"{prefix}\", expected one of {}",
+            prefixes.join(", ")
+        );
     }
+    let mut lines = vec![];
+    for provider in providers {
+        lines.push(format!("{} ({})", provider.prefix, provider.name));
+        for region in provider.regions {
+            lines.push(format!("  {}:{region}", provider.prefix));
+        }
+    }
+    Ok(lines.join("\n"))
 }
 
 #[cfg(test)]
@@ -16,7 +217,7 @@ mod tests {
     #[test]
     fn test_host_from_aws() {
         assert_eq!(
-            try_host_from_cloud_region("aws:eu-west-1"),
+            try_host_from_cloud_region("aws:eu-west-1").unwrap(),
             Some("ec2.eu-west-1.amazonaws.com".to_string())
         );
     }
@@ -24,22 +225,48 @@ mod tests {
     #[test]
     fn test_host_from_gcp() {
         assert_eq!(
-            try_host_from_cloud_region("gcp:me-central2"),
+            try_host_from_cloud_region("gcp:me-central2").unwrap(),
             Some("storage.me-central2.rep.googleapis.com".to_string())
         );
         assert_eq!(
-            try_host_from_cloud_region("gcp:"),
+            try_host_from_cloud_region("gcp:").unwrap(),
             Some("cloud.google.com".to_string())
         );
     }
 
+    #[test]
+    fn test_host_from_other_providers() {
+        assert_eq!(
+            try_host_from_cloud_region("do:ams3").unwrap(),
+            Some("speedtest-ams3.digitalocean.com".to_string())
+        );
+        assert_eq!(
+            try_host_from_cloud_region("oci:eu-frankfurt-1").unwrap(),
+            Some("objectstorage.eu-frankfurt-1.oraclecloud.com".to_string())
+        );
+        assert_eq!(
+            try_host_from_cloud_region("hetzner:fsn1").unwrap(),
+            Some("fsn1-speed.hetzner.com".to_string())
+        );
+        assert_eq!(
+            try_host_from_cloud_region("cf:").unwrap(),
+            Some("1.1.1.1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unknown_region() {
+        assert!(try_host_from_cloud_region("aws:eu-west-9").is_err());
+        assert!(try_host_from_cloud_region("aws:").is_err());
+    }
+
     #[test]
     fn test_host_from_foo() {
-        assert_eq!(try_host_from_cloud_region("foo:bar"), None);
+        assert_eq!(try_host_from_cloud_region("foo:bar").unwrap(), None);
     }
 
     #[test]
     fn test_invalid_input() {
-        assert_eq!(try_host_from_cloud_region("foo"), None);
+        assert_eq!(try_host_from_cloud_region("foo").unwrap(), None);
     }
 }
@@ -0,0 +1,114 @@
+use crate::plot_data::{Marker, Sample};
+use anyhow::{bail, Context, Result};
+use chrono::prelude::*;
+use std::path::Path;
+use std::time::Duration;
+
+/// A sample read back from a recording, before its target is numbered.
+pub struct Record {
+    pub at: DateTime<Local>,
+    pub target: String,
+    pub rtt: Option<Duration>,
+}
+
+/// The targets, samples and markers of a recorded session, in the order they were taken.
+pub struct Recording {
+    pub targets: Vec<String>,
+    pub samples: Vec<Sample>,
+    pub markers: Vec<Marker>,
+}
+
+/// Convert a round trip time in milliseconds, as recordings store it.
+pub fn rtt_from_ms(ms: f64) -> Result<Duration> {
+    Duration::try_from_secs_f64(ms / 1000.0)
+        .with_context(|| format!("Invalid round trip time {ms}"))
+}
+
+/// Read JSON, either an array of samples or one sample per line, each an object like
+/// `{"timestamp": "2024-01-01T12:00:00.000+00:00", "target": "google.com", "rtt_ms": 12.5}` with a
+/// null `rtt_ms` for timeouts. Markers are objects with a `marker` label instead of a target.
+fn read_json(path: &Path) -> Result<(Vec<Record>, Vec<Marker>)> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Error reading JSON file {}", path.display()))?;
+    let values = match serde_json::from_str::<serde_json::Value>(&contents) {
+        Ok(serde_json::Value::Array(values)) => values,
+        _ => serde_json::Deserializer::from_str(&contents)
+            .into_iter()
+            .collect::<Result<Vec<serde_json::Value>, _>>()
+            .with_context(|| format!("Error parsing JSON file {}", path.display()))?,
+    };
+    let mut records = vec![];
+    let mut markers = vec![];
+    for value in &values {
+        let Some(timestamp) = value["timestamp"].as_str() else {
+            bail!("Sample is missing a timestamp: {value}");
+        };
+        let at = DateTime::parse_from_rfc3339(timestamp)
+            .with_context(|| format!("Invalid timestamp {timestamp}"))?
+            .with_timezone(&Local);
+        if let Some(label) = value["marker"].as_str() {
+            markers.push(Marker {
+                at,
+                label: label.to_string(),
+            });
+            continue;
+        }
+        let Some(target) = value["target"].as_str() else {
+            bail!("Sample is missing a target: {value}");
+        };
+        records.push(Record {
+            at,
+            target: target.to_string(),
+            rtt: value["rtt_ms"].as_f64().map(rtt_from_ms).transpose()?,
+        });
+    }
+    Ok((records, markers))
+}
+
+/// Load a session recorded with `--csv` or `--db`, or exported as JSON, based on the file's
+/// extension. Databases replay their most recent session.
+pub fn load(path: &Path) -> Result<Recording> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let (mut records, markers) = match extension.as_str() {
+        "csv" => crate::csv::read(path)?,
+        "json" | "jsonl" | "ndjson" => read_json(path)?,
+        #[cfg(feature = "sqlite")]
+        "db" | "sqlite" | "sqlite3" => crate::db::read_latest_session(path)?,
+        _ => bail!(
+            "Can't replay {}, expected a .csv, .json or SQLite file",
+            path.display()
+        ),
+    };
+    if records.is_empty() {
+        bail!("{} has no samples to replay", path.display());
+    }
+    records.sort_by_key(|record| record.at);
+
+    let mut targets: Vec<String> = vec![];
+    let samples = records
+        .into_iter()
+        .map(|record| {
+            let host_id = match targets.iter().position(|target| *target == record.target) {
+                Some(host_id) => host_id,
+                None => {
+                    targets.push(record.target);
+                    targets.len() - 1
+                }
+            };
+            Sample {
+                at: record.at,
+                host_id,
+                rtt: record.rtt,
+            }
+        })
+        .collect();
+    Ok(Recording {
+        targets,
+        samples,
+        markers,
+    })
+}
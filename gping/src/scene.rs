@@ -0,0 +1,72 @@
+use tui::style::{Color, Modifier, Style};
+
+/// A chart's lines, outages and dots between its bounds, which are drawn as an image with
+/// `--graphics` and as an SVG snapshot.
+#[derive(Clone, Debug)]
+pub struct Scene {
+    pub x_bounds: [f64; 2],
+    pub y_bounds: [f64; 2],
+    /// The points of each line and its style, which is thicker if bold and fainter if dimmed.
+    /// Timeouts are NaN, which break the line.
+    pub lines: Vec<(Style, Vec<(f64, f64)>)>,
+    /// The start and end of each outage, which are shaded.
+    pub outages: Vec<(f64, f64)>,
+    pub dots: Vec<(Color, Vec<(f64, f64)>)>,
+    /// Filled in behind the chart if set, rather than leaving the terminal's background.
+    pub background: Option<Color>,
+}
+
+/// Something a scene is drawn on, in pixels from its top left corner. Colors have an alpha, from
+/// transparent at 0 to opaque at 255.
+pub trait Canvas {
+    fn rect(&mut self, from: (f32, f32), to: (f32, f32), color: Color, alpha: u8);
+    /// A line through `points`, `width` pixels wide.
+    fn polyline(&mut self, points: &[(f32, f32)], color: Color, alpha: u8, width: f32);
+    fn circle(&mut self, center: (f32, f32), radius: f32, color: Color);
+}
+
+impl Scene {
+    /// Draw the scene to fill `size` pixels of `canvas`, with lines `scale` pixels wide.
+    pub fn draw(&self, canvas: &mut impl Canvas, size: (f32, f32), scale: f32) {
+        let (width, height) = (size.0 as f64, size.1 as f64);
+        let [start, end] = self.x_bounds;
+        let [bottom, top] = self.y_bounds;
+        let x = |timestamp: f64| ((timestamp - start) / (end - start) * width) as f32;
+        let y = |value: f64| (height - (value - bottom) / (top - bottom).max(1.0) * height) as f32;
+
+        for (from, to) in &self.outages {
+            canvas.rect(
+                (x(*from), 0.0),
+                (x(*to).max(x(*from) + 1.0), size.1),
+                Color::DarkGray,
+                128,
+            );
+        }
+        for (style, points) in &self.lines {
+            let width = match style.add_modifier.contains(Modifier::BOLD) {
+                true => scale * 2.0,
+                false => scale,
+            };
+            let alpha = match style.add_modifier.contains(Modifier::DIM) {
+                true => 128,
+                false => 255,
+            };
+            let color = style.fg.unwrap_or(Color::Reset);
+            for segment in points.split(|(_, value)| value.is_nan()) {
+                if segment.len() > 1 {
+                    let segment = segment
+                        .iter()
+                        .map(|(timestamp, value)| (x(*timestamp), y(*value)))
+                        .collect::<Vec<_>>();
+                    canvas.polyline(&segment, color, alpha, width);
+                }
+            }
+        }
+        for (color, points) in &self.dots {
+            for (timestamp, value) in points {
+                let center = (x(*timestamp), y(*value).clamp(0.0, size.1));
+                canvas.circle(center, scale * 1.5, *color);
+            }
+        }
+    }
+}
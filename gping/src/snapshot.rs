@@ -0,0 +1,339 @@
+use crate::plot_data::{PlotData, Units};
+use crate::scene::{Canvas, Scene};
+use anyhow::{bail, Context, Result};
+use chrono::prelude::*;
+use crossterm::queue;
+use crossterm::style::{Attribute, Print, SetAttribute, SetBackgroundColor, SetForegroundColor};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::Duration;
+use tui::buffer::Buffer;
+use tui::style::{Color, Modifier};
+
+const WIDTH: f64 = 1000.0;
+const HEIGHT: f64 = 500.0;
+// Room around the plot for the legend and the axis labels
+const LEFT: f64 = 90.0;
+const RIGHT: f64 = 20.0;
+const TOP: f64 = 40.0;
+const BOTTOM: f64 = 40.0;
+/// How many pixels across the lines are plotted in.
+pub const PLOT_WIDTH: f64 = WIDTH - LEFT - RIGHT;
+
+/// The colors of the 16 standard terminal colors, as xterm draws them.
+const ANSI: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// The red, green and blue of a terminal color, using xterm's palette for indexed colors.
+pub fn rgb(color: Color) -> (u8, u8, u8) {
+    match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(index @ 0..=15) => ANSI[index as usize],
+        Color::Indexed(index @ 16..=231) => {
+            let level = |value: u8| if value == 0 { 0 } else { 55 + value * 40 };
+            let index = index - 16;
+            (level(index / 36), level(index / 6 % 6), level(index % 6))
+        }
+        Color::Indexed(index) => {
+            let gray = 8 + (index - 232) * 10;
+            (gray, gray, gray)
+        }
+        Color::Black => ANSI[0],
+        Color::Red => ANSI[1],
+        Color::Green => ANSI[2],
+        Color::Yellow => ANSI[3],
+        Color::Blue => ANSI[4],
+        Color::Magenta => ANSI[5],
+        Color::Cyan => ANSI[6],
+        Color::Gray => ANSI[7],
+        Color::DarkGray => ANSI[8],
+        Color::LightRed => ANSI[9],
+        Color::LightGreen => ANSI[10],
+        Color::LightYellow => ANSI[11],
+        Color::LightBlue => ANSI[12],
+        Color::LightMagenta => ANSI[13],
+        Color::LightCyan => ANSI[14],
+        Color::White | Color::Reset => ANSI[15],
+    }
+}
+
+/// The CSS color for a terminal color.
+fn css_color(color: Color) -> String {
+    let (r, g, b) = rgb(color);
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// SVG elements, which a scene is drawn as in snapshots.
+struct Elements(String);
+
+fn opacity(alpha: u8) -> f32 {
+    alpha as f32 / 255.0
+}
+
+impl Canvas for Elements {
+    fn rect(&mut self, from: (f32, f32), to: (f32, f32), color: Color, alpha: u8) {
+        let _ = writeln!(
+            self.0,
+            r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}" fill-opacity="{:.2}"/>"#,
+            from.0,
+            from.1,
+            to.0 - from.0,
+            to.1 - from.1,
+            css_color(color),
+            opacity(alpha)
+        );
+    }
+
+    fn polyline(&mut self, points: &[(f32, f32)], color: Color, alpha: u8, width: f32) {
+        let points = points
+            .iter()
+            .map(|(x, y)| format!("{x:.1},{y:.1}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let _ = writeln!(
+            self.0,
+            r#"<polyline points="{points}" fill="none" stroke="{}" stroke-opacity="{:.2}" stroke-width="{width}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+            css_color(color),
+            opacity(alpha)
+        );
+    }
+
+    fn circle(&mut self, center: (f32, f32), radius: f32, color: Color) {
+        let _ = writeln!(
+            self.0,
+            r#"<circle cx="{:.1}" cy="{:.1}" r="{radius}" fill="{}"/>"#,
+            center.0,
+            center.1,
+            css_color(color)
+        );
+    }
+}
+
+/// Render `scene` as an SVG chart with axes labelled in `units`, and a legend of `plots`,
+/// independent of the size of the terminal. Like sixels, it's drawn on black unless the scene has
+/// a background color.
+pub fn svg(scene: &Scene, plots: &[(usize, &PlotData)], units: Units) -> String {
+    let [x_bounds, y_bounds] = [scene.x_bounds, scene.y_bounds];
+    let x = |timestamp: f64| {
+        LEFT + (timestamp - x_bounds[0]) / (x_bounds[1] - x_bounds[0]) * PLOT_WIDTH
+    };
+    let y = |rtt: f64| {
+        HEIGHT
+            - BOTTOM
+            - (rtt - y_bounds[0]) / (y_bounds[1] - y_bounds[0]).max(1.0) * (HEIGHT - TOP - BOTTOM)
+    };
+
+    // As on the terminal, the numbers printed with --cmd-metric are only shown as they are if
+    // every host has them
+    let label = |value: Duration| match plots.first() {
+        Some((_, plot)) if plots.iter().all(|(_, plot)| plot.metric) => {
+            plot.format_value(value, units)
+        }
+        _ => units.format(value),
+    };
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" font-family="monospace" font-size="12" fill="{}">"#,
+        css_color(Color::Gray)
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect width="100%" height="100%" fill="{}"/>"#,
+        css_color(scene.background.unwrap_or(Color::Black))
+    );
+
+    // The lines, outages and dots, drawn as they are with --graphics
+    let mut elements = Elements(String::new());
+    scene.draw(
+        &mut elements,
+        (
+            (WIDTH - LEFT - RIGHT) as f32,
+            (HEIGHT - TOP - BOTTOM) as f32,
+        ),
+        1.5,
+    );
+    let _ = writeln!(svg, r#"<g transform="translate({LEFT},{TOP})">"#);
+    svg.push_str(&elements.0);
+    svg.push_str("</g>\n");
+
+    // Axes, with labels like those on the terminal's chart
+    let _ = writeln!(
+        svg,
+        r##"<polyline points="{LEFT},{TOP} {LEFT},{} {},{}" fill="none" stroke="#7f7f7f"/>"##,
+        HEIGHT - BOTTOM,
+        WIDTH - RIGHT,
+        HEIGHT - BOTTOM
+    );
+    for i in 0..=4 {
+        let rtt = y_bounds[0] + (y_bounds[1] - y_bounds[0]) * i as f64 / 4.0;
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{:.1}" text-anchor="end">{}</text>"#,
+            LEFT - 6.0,
+            y(rtt) + 4.0,
+            escape(&label(Duration::from_micros(rtt as u64)))
+        );
+    }
+    for i in 0..=2 {
+        let timestamp = x_bounds[0] + (x_bounds[1] - x_bounds[0]) * i as f64 / 2.0;
+        let time = DateTime::from_timestamp(timestamp as i64, 0)
+            .unwrap_or_default()
+            .with_timezone(&Local);
+        let anchor = ["start", "middle", "end"][i];
+        let _ = writeln!(
+            svg,
+            r#"<text x="{:.1}" y="{}" text-anchor="{anchor}">{}</text>"#,
+            x(timestamp),
+            HEIGHT - BOTTOM + 18.0,
+            time.format("%H:%M:%S")
+        );
+    }
+
+    let mut legend_x = LEFT;
+    for (_, plot_data) in plots {
+        let color = css_color(plot_data.style.fg.unwrap_or(Color::Reset));
+        let _ = writeln!(
+            svg,
+            r#"<text x="{legend_x:.1}" y="{}" fill="{color}">■ {}</text>"#,
+            TOP - 16.0,
+            escape(&plot_data.display)
+        );
+        legend_x += (plot_data.display.chars().count() as f64 + 4.0) * 7.5;
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Write an SVG chart of `scene`, with a legend of `plots`, to `path`.
+pub fn write(path: &Path, scene: &Scene, plots: &[(usize, &PlotData)], units: Units) -> Result<()> {
+    std::fs::write(path, svg(scene, plots, units))
+        .with_context(|| format!("Error writing snapshot {}", path.display()))
+}
+
+// The modifiers kept in text dumps, and how they are written
+const ATTRIBUTES: [(Modifier, Attribute); 6] = [
+    (Modifier::BOLD, Attribute::Bold),
+    (Modifier::DIM, Attribute::Dim),
+    (Modifier::ITALIC, Attribute::Italic),
+    (Modifier::UNDERLINED, Attribute::Underlined),
+    (Modifier::REVERSED, Attribute::Reverse),
+    (Modifier::CROSSED_OUT, Attribute::CrossedOut),
+];
+
+/// The screen in `buffer` as lines of text, with the colors and styles as ANSI escape codes if
+/// `ansi` is set. Unlike the terminal output, there are no cursor movements, so it can be pasted
+/// or printed with `cat`.
+pub fn screen_text(buffer: &Buffer, ansi: bool) -> std::io::Result<String> {
+    let mut out = Vec::new();
+    let area = buffer.area;
+    for y in area.top()..area.bottom() {
+        let mut style = None;
+        let mut line = Vec::new();
+        for x in area.left()..area.right() {
+            let cell = &buffer[(x, y)];
+            if cell.skip {
+                continue;
+            }
+            let cell_style = (cell.fg, cell.bg, cell.modifier);
+            if ansi && style != Some(cell_style) {
+                queue!(line, SetAttribute(Attribute::Reset))?;
+                if cell.fg != Color::Reset {
+                    queue!(line, SetForegroundColor(cell.fg.into()))?;
+                }
+                if cell.bg != Color::Reset {
+                    queue!(line, SetBackgroundColor(cell.bg.into()))?;
+                }
+                for (modifier, attribute) in ATTRIBUTES {
+                    if cell.modifier.contains(modifier) {
+                        queue!(line, SetAttribute(attribute))?;
+                    }
+                }
+                style = Some(cell_style);
+            }
+            queue!(line, Print(cell.symbol()))?;
+        }
+        if ansi {
+            queue!(line, SetAttribute(Attribute::Reset))?;
+        }
+        let line = String::from_utf8_lossy(&line);
+        // Trailing spaces are only kept where they are styled
+        out.push(if ansi {
+            line.to_string()
+        } else {
+            line.trim_end().to_string()
+        });
+    }
+    Ok(out.join("\n") + "\n")
+}
+
+/// Check that a snapshot can be written to `path`, which must be an SVG file.
+pub fn check_path(path: &Path) -> Result<()> {
+    let is_svg = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"));
+    if !is_svg {
+        bail!(
+            "Snapshots are saved as SVG, so {} must end in .svg",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tui::style::Style;
+
+    #[test]
+    fn test_svg() {
+        let scene = Scene {
+            x_bounds: [0.0, 10.0],
+            y_bounds: [0.0, 100.0],
+            lines: vec![(
+                Style::default().fg(Color::Red),
+                vec![
+                    (0.0, 10.0),
+                    (2.0, 20.0),
+                    (5.0, f64::NAN),
+                    (8.0, 80.0),
+                    (10.0, 90.0),
+                ],
+            )],
+            outages: vec![(4.0, 6.0)],
+            dots: vec![(Color::Red, vec![(5.0, 100.0)])],
+            background: None,
+        };
+        let svg = svg(&scene, &[], Units::Auto);
+        // The timeout breaks the line in two, as it does with --graphics
+        assert_eq!(svg.matches(r##"stroke="#cd0000""##).count(), 2);
+        assert_eq!(svg.matches("fill-opacity").count(), 1);
+        assert_eq!(svg.matches("<circle").count(), 1);
+        assert!(svg.contains(r##"<rect width="100%" height="100%" fill="#000000"/>"##));
+    }
+}
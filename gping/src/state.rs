@@ -0,0 +1,72 @@
+use crate::csv;
+use crate::plot_data::{Marker, PlotData, Sample};
+use anyhow::{Context, Result};
+use chrono::prelude::*;
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Duration;
+
+/// How often the samples are saved to the state file while running.
+pub const INTERVAL: Duration = Duration::from_secs(30);
+
+/// Save the samples kept for each host, and the markers, to `path` in the CSV format, so that
+/// `load` can restore them. The file is replaced in one go, so it's never left half written.
+pub fn save(path: &Path, hosts: &[String], data: &[PlotData], markers: &[Marker]) -> Result<()> {
+    let mut samples = data
+        .iter()
+        .enumerate()
+        .filter(|(_, host)| !host.removed)
+        .flat_map(|(host_id, host)| {
+            host.data.iter().map(move |(timestamp, rtt)| Sample {
+                at: DateTime::<Utc>::from_timestamp_millis((timestamp * 1_000.0).round() as i64)
+                    .unwrap_or_default()
+                    .with_timezone(&Local),
+                host_id,
+                rtt: (!rtt.is_nan()).then(|| Duration::from_micros(*rtt as u64)),
+            })
+        })
+        .collect::<Vec<_>>();
+    samples.sort_by_key(|sample| sample.at);
+
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = Path::new(&partial);
+    csv::write(partial, hosts, &samples, markers)?;
+    std::fs::rename(partial, path)
+        .with_context(|| format!("Error saving state file {}", path.display()))
+}
+
+/// The samples saved to `path` in the last `history`, with their `host_id` in `targets`, and the
+/// markers. Nothing is restored if there's no state file yet, or it was saved for other targets.
+pub fn load(
+    path: &Path,
+    targets: &[String],
+    history: chrono::Duration,
+) -> Result<Option<(Vec<Sample>, Vec<Marker>)>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let (records, markers) =
+        csv::read(path).with_context(|| format!("Error loading state file {}", path.display()))?;
+    let saved = records
+        .iter()
+        .map(|record| record.target.as_str())
+        .collect::<HashSet<_>>();
+    if saved != targets.iter().map(String::as_str).collect() {
+        return Ok(None);
+    }
+    let earliest = Local::now() - history;
+    let mut samples = records
+        .into_iter()
+        .filter(|record| record.at >= earliest)
+        .filter_map(|record| {
+            Some(Sample {
+                at: record.at,
+                host_id: targets.iter().position(|target| *target == record.target)?,
+                rtt: record.rtt,
+            })
+        })
+        .collect::<Vec<_>>();
+    samples.sort_by_key(|sample| sample.at);
+    Ok(Some((samples, markers)))
+}
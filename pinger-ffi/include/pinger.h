@@ -0,0 +1,86 @@
+#ifndef PINGER_H
+#define PINGER_H
+
+/* Generated by cbindgen from src/lib.rs, see the README. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The kind of a [`PingerResult`].
+ */
+typedef enum PingerResultKind {
+  /**
+   * A reply, after `duration_us`.
+   */
+  PINGER_RESULT_KIND_PONG,
+  /**
+   * A duplicate reply, after `duration_us`.
+   */
+  PINGER_RESULT_KIND_DUPLICATE,
+  PINGER_RESULT_KIND_TIMEOUT,
+  /**
+   * An ICMP error, such as the destination being unreachable.
+   */
+  PINGER_RESULT_KIND_ERROR,
+  /**
+   * The ping process exited. No more results will follow.
+   */
+  PINGER_RESULT_KIND_EXITED,
+  /**
+   * Anything else, such as lines that couldn't be parsed.
+   */
+  PINGER_RESULT_KIND_OTHER,
+} PingerResultKind;
+
+/**
+ * A running ping, returned from [`ping`](crate::ping).
+ *
+ * Iterating over the stream yields results until the underlying ping exits or is stopped.
+ * The stream can also be used to pause, resume or stop the thread or subprocess producing them.
+ */
+typedef struct PingerStream PingerStream;
+
+typedef struct PingerResult {
+  enum PingerResultKind kind;
+  /**
+   * The round trip time in microseconds, for pongs and duplicates. Otherwise zero.
+   */
+  uint64_t duration_us;
+} PingerResult;
+
+/**
+ * Start pinging `target`, a hostname or IP address, every `interval_ms` milliseconds. Returns
+ * null if pinging could not be started, including when `interval_ms` is zero.
+ *
+ * # Safety
+ *
+ * `target` must be null or a valid null-terminated string. The returned stream must be freed
+ * with [`pinger_stop`].
+ */
+struct PingerStream *pinger_start(const char *target, uint32_t interval_ms);
+
+/**
+ * Wait up to `timeout_ms` milliseconds for the next result and write it to `result`. Returns 1 if
+ * a result was written, 0 if none arrived in time and -1 once the stream has ended.
+ *
+ * # Safety
+ *
+ * `stream` must be null or returned by [`pinger_start`] and not yet stopped, and `result` must
+ * be null or valid for writes.
+ */
+int pinger_next(struct PingerStream *stream, uint32_t timeout_ms, struct PingerResult *result);
+
+/**
+ * Stop pinging and free the stream.
+ *
+ * # Safety
+ *
+ * `stream` must be null or returned by [`pinger_start`], and must not be used afterwards.
+ */
+void pinger_stop(struct PingerStream *stream);
+
+#endif  /* PINGER_H */
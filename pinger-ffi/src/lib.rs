@@ -0,0 +1,138 @@
+//! A C interface to pinger, for embedding it in applications written in other languages. The header
+//! is at `include/pinger.h`, generated with cbindgen.
+use pinger::{ping, PingOptions, PingResult, PingStream};
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Duration;
+
+/// The kind of a [`PingerResult`].
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PingerResultKind {
+    /// A reply, after `duration_us`.
+    Pong,
+    /// A duplicate reply, after `duration_us`.
+    Duplicate,
+    Timeout,
+    /// An ICMP error, such as the destination being unreachable.
+    Error,
+    /// The ping process exited. No more results will follow.
+    Exited,
+    /// Anything else, such as lines that couldn't be parsed.
+    Other,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct PingerResult {
+    pub kind: PingerResultKind,
+    /// The round trip time in microseconds, for pongs and duplicates. Otherwise zero.
+    pub duration_us: u64,
+}
+
+impl From<&PingResult> for PingerResult {
+    fn from(result: &PingResult) -> Self {
+        let (kind, duration) = match result {
+            PingResult::Pong(duration, ..) | PingResult::NtpReply(duration, ..) => {
+                (PingerResultKind::Pong, Some(duration))
+            }
+            PingResult::Duplicate(duration, _) => (PingerResultKind::Duplicate, Some(duration)),
+            PingResult::Timeout(_) => (PingerResultKind::Timeout, None),
+            PingResult::Unreachable(_) | PingResult::TtlExceeded(_) | PingResult::Filtered(_) => {
+                (PingerResultKind::Error, None)
+            }
+            PingResult::PingExited(..) => (PingerResultKind::Exited, None),
+            _ => (PingerResultKind::Other, None),
+        };
+        Self {
+            kind,
+            duration_us: duration.map_or(0, |d| d.as_micros() as u64),
+        }
+    }
+}
+
+/// Start pinging `target`, a hostname or IP address, every `interval_ms` milliseconds. Returns
+/// null if pinging could not be started, including when `interval_ms` is zero.
+///
+/// # Safety
+///
+/// `target` must be null or a valid null-terminated string. The returned stream must be freed
+/// with [`pinger_stop`].
+#[no_mangle]
+pub unsafe extern "C" fn pinger_start(target: *const c_char, interval_ms: u32) -> *mut PingStream {
+    if target.is_null() || interval_ms == 0 {
+        return ptr::null_mut();
+    }
+    let Ok(target) = CStr::from_ptr(target).to_str() else {
+        return ptr::null_mut();
+    };
+    let interval = Duration::from_millis(interval_ms.into());
+    match ping(PingOptions::new(target, interval, None)) {
+        Ok(stream) => Box::into_raw(Box::new(stream)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Wait up to `timeout_ms` milliseconds for the next result and write it to `result`. Returns 1 if
+/// a result was written, 0 if none arrived in time and -1 once the stream has ended.
+///
+/// # Safety
+///
+/// `stream` must be null or returned by [`pinger_start`] and not yet stopped, and `result` must
+/// be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn pinger_next(
+    stream: *mut PingStream,
+    timeout_ms: u32,
+    result: *mut PingerResult,
+) -> c_int {
+    let (Some(stream), Some(result)) = (stream.as_ref(), result.as_mut()) else {
+        return -1;
+    };
+    match stream.recv_timeout(Duration::from_millis(timeout_ms.into())) {
+        Ok(next) => {
+            *result = PingerResult::from(&next);
+            1
+        }
+        Err(RecvTimeoutError::Timeout) => 0,
+        Err(RecvTimeoutError::Disconnected) => -1,
+    }
+}
+
+/// Stop pinging and free the stream.
+///
+/// # Safety
+///
+/// `stream` must be null or returned by [`pinger_start`], and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn pinger_stop(stream: *mut PingStream) {
+    if !stream.is_null() {
+        // Dropping the stream stops it.
+        drop(Box::from_raw(stream));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_null_arguments() {
+        unsafe {
+            assert!(pinger_start(ptr::null(), 1000).is_null());
+            assert!(pinger_start(b"127.0.0.1\0".as_ptr().cast(), 0).is_null());
+            let mut result = PingerResult {
+                kind: PingerResultKind::Other,
+                duration_us: 0,
+            };
+            assert_eq!(pinger_next(ptr::null_mut(), 0, &mut result), -1);
+            pinger_stop(ptr::null_mut());
+        }
+        let pong = PingResult::Pong(Duration::from_millis(12), String::new(), None);
+        let converted = PingerResult::from(&pong);
+        assert_eq!(converted.kind, PingerResultKind::Pong);
+        assert_eq!(converted.duration_us, 12_000);
+    }
+}
@@ -9,14 +9,26 @@ pub fn main() {
     let stream = ping(options).expect("Error pinging");
     for message in stream.into_iter().take(LIMIT) {
         match message {
-            pinger::PingResult::Pong(duration, line) => {
+            pinger::PingResult::Pong(duration, line, _) => {
                 println!("Duration: {:?}\t\t(raw: {:?})", duration, line)
             }
+            pinger::PingResult::Duplicate(duration, line) => {
+                println!("Duplicate: {:?}\t(raw: {:?})", duration, line)
+            }
+            pinger::PingResult::NtpReply(delay, offset, line) => {
+                println!("Delay: {delay:?}, offset: {offset}s\t(raw: {line:?})")
+            }
             pinger::PingResult::Timeout(line) => println!("Timeout! (raw: {line:?})"),
+            pinger::PingResult::Unreachable(line)
+            | pinger::PingResult::TtlExceeded(line)
+            | pinger::PingResult::Filtered(line) => println!("ICMP error! (raw: {line:?})"),
+            pinger::PingResult::AddressChanged(ip) => println!("Now pinging {ip}"),
+            pinger::PingResult::Restarted(stderr) => println!("Restarted! (stderr: {stderr:?})"),
             pinger::PingResult::Unknown(line) => println!("Unknown line: {:?}", line),
-            pinger::PingResult::PingExited(code, stderr) => {
+            pinger::PingResult::PingExited(code, stderr, _) => {
                 panic!("Ping exited! Code: {:?}. Stderr: {:?}", code, stderr)
             }
+            result => println!("Other result: {result}"),
         }
     }
 }
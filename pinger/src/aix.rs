@@ -0,0 +1,184 @@
+use crate::locale::parse_localized;
+use crate::{
+    extract_regex, spawn_ping_into, PingCreationError, PingFlag, PingOptions, PingResult,
+    PingStream, Pinger, SubprocessPinger,
+};
+use lazy_regex::*;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub static RE: Lazy<Regex> =
+    lazy_regex!(r"icmp_seq=(?P<seq>\d+) .*time=(?P<ms>\d+)(?:\.(?P<ns>\d+))? ms");
+
+static SEQ_RE: Lazy<Regex> = lazy_regex!(r"icmp_seq=(?P<seq>\d+)");
+
+/// AIX ping doesn't support `-O` or sub-second intervals, and never reports timeouts.
+/// Timeouts are synthesised from gaps in the `icmp_seq` of replies, and from replies that are
+/// overdue, instead.
+pub struct AixPinger {
+    options: PingOptions,
+}
+
+pub(crate) fn parse_aix(line: String) -> Option<PingResult> {
+    if line.starts_with("PING ") {
+        return None;
+    }
+    if !RE.is_match(&line) {
+        return parse_localized(line);
+    }
+    extract_regex(&RE, line)
+}
+
+/// How long to wait for a reply when no timeout is set.
+const DEFAULT_WAIT: Duration = Duration::from_secs(2);
+/// How often to check whether a reply is overdue.
+const TICK: Duration = Duration::from_millis(100);
+
+/// Tracks the sequence number of replies, emitting a timeout for every sequence that was skipped,
+/// and for the next one once its reply is overdue.
+pub(crate) struct SequenceTracker {
+    next_seq: u64,
+    /// When the reply to `next_seq` is overdue.
+    due: Instant,
+    interval: Duration,
+    wait: Duration,
+}
+
+impl SequenceTracker {
+    pub(crate) fn new(interval: Duration, wait: Duration, now: Instant) -> Self {
+        Self {
+            next_seq: 0,
+            due: now + interval + wait,
+            interval,
+            wait,
+        }
+    }
+
+    pub(crate) fn process(&mut self, line: String, now: Instant) -> Vec<PingResult> {
+        let seq = SEQ_RE
+            .captures(&line)
+            .and_then(|cap| cap.name("seq")?.as_str().parse::<u64>().ok());
+        let Some(result) = parse_aix(line) else {
+            return vec![];
+        };
+        let mut results = vec![];
+        // A reply to a sequence that has already timed out is passed on as it is, like iputils
+        // does after "no answer yet".
+        if let Some(seq) = seq.filter(|seq| *seq >= self.next_seq) {
+            results.extend(
+                (self.next_seq..seq)
+                    .map(|missing| PingResult::Timeout(format!("icmp_seq={missing}"))),
+            );
+            self.next_seq = seq + 1;
+            self.due = now + self.interval + self.wait;
+        }
+        results.push(result);
+        results
+    }
+
+    /// A timeout for each sequence whose reply is overdue at `now`.
+    pub(crate) fn expire(&mut self, now: Instant) -> Vec<PingResult> {
+        let mut results = vec![];
+        while self.due <= now {
+            results.push(PingResult::Timeout(format!("icmp_seq={}", self.next_seq)));
+            self.next_seq += 1;
+            self.due += self.interval;
+        }
+        results
+    }
+
+    /// Don't expect any replies before `now`, while ping is paused.
+    fn postpone(&mut self, now: Instant) {
+        self.due = self.due.max(now + self.interval + self.wait);
+    }
+}
+
+impl AixPinger {
+    /// -i only accepts whole seconds
+    fn interval(&self) -> Duration {
+        Duration::from_secs(self.options.interval.as_secs_f32().ceil().max(1.0) as u64)
+    }
+}
+
+impl Pinger for AixPinger {
+    fn from_options(options: PingOptions) -> Result<Self, PingCreationError>
+    where
+        Self: Sized,
+    {
+        options
+            .flags
+            .ensure_supported("AIX", &[PingFlag::Numeric])?;
+        Ok(Self { options })
+    }
+
+    fn start(&self) -> Result<PingStream, PingCreationError> {
+        let (cmd, args) = self.ping_args();
+        let wait = self.options.timeout.unwrap_or(DEFAULT_WAIT);
+        let tracker = Arc::new(Mutex::new(SequenceTracker::new(
+            self.interval(),
+            wait,
+            Instant::now(),
+        )));
+        let (tx, rx) = mpsc::channel();
+
+        let reader_tracker = tracker.clone();
+        let control = spawn_ping_into(
+            cmd,
+            args,
+            move |line| reader_tracker.lock().unwrap().process(line, Instant::now()),
+            tx.clone(),
+        )?;
+
+        // A host that stops answering never prints another line, so overdue replies are timed
+        // out here rather than when the next reply shows a gap.
+        let timer_control = control.clone();
+        thread::spawn(move || {
+            while timer_control.has_child() {
+                thread::sleep(TICK);
+                let mut tracker = tracker.lock().unwrap();
+                if timer_control.is_paused() {
+                    tracker.postpone(Instant::now());
+                    continue;
+                }
+                for result in tracker.expire(Instant::now()) {
+                    if tx.send(result).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(PingStream::new(rx, control))
+    }
+}
+
+impl SubprocessPinger for AixPinger {
+    fn parse_fn(&self) -> fn(String) -> Option<PingResult> {
+        parse_aix
+    }
+
+    fn ping_args(&self) -> (&str, Vec<String>) {
+        let mut args = vec!["-i".to_string(), self.interval().as_secs().to_string()];
+        if self.options.target.is_ipv6() {
+            args.push("-a".into());
+            args.push("inet6".into());
+        }
+        if let Some(interface) = &self.options.interface {
+            args.push("-o".into());
+            args.push(interface.clone());
+        }
+        if let Some(source) = &self.options.source {
+            args.push("-S".into());
+            args.push(source.to_string());
+        }
+        if self.options.flags.numeric {
+            args.push("-n".into());
+        }
+        if let Some(raw_args) = &self.options.raw_arguments {
+            args.extend(raw_args.iter().cloned());
+        }
+        args.push(self.options.target.to_string());
+        (self.options.ping_command("ping"), args)
+    }
+}
@@ -1,4 +1,8 @@
-use crate::{extract_regex, PingCreationError, PingOptions, PingResult, Pinger};
+use crate::locale::parse_localized;
+use crate::{
+    extract_regex, start_subprocess, PingCreationError, PingFlag, PingFlags, PingOptions,
+    PingResult, PingStream, Pinger, SubprocessPinger,
+};
 use lazy_regex::*;
 
 pub static RE: Lazy<Regex> = lazy_regex!(r"time=(?:(?P<ms>[0-9]+).(?P<ns>[0-9]+)\s+ms)");
@@ -7,6 +11,23 @@ pub struct BSDPinger {
     options: PingOptions,
 }
 
+pub(crate) const BSD_FLAGS: &[PingFlag] =
+    &[PingFlag::DontFragment, PingFlag::Audible, PingFlag::Numeric];
+
+pub(crate) fn bsd_flag_args(flags: &PingFlags) -> Vec<String> {
+    let mut args = vec![];
+    if flags.dont_fragment {
+        args.push("-D".into());
+    }
+    if flags.audible {
+        args.push("-a".into());
+    }
+    if flags.numeric {
+        args.push("-n".into());
+    }
+    args
+}
+
 pub(crate) fn parse_bsd(line: String) -> Option<PingResult> {
     if line.starts_with("PING ") {
         return None;
@@ -14,6 +35,9 @@ pub(crate) fn parse_bsd(line: String) -> Option<PingResult> {
     if line.starts_with("Request timeout") {
         return Some(PingResult::Timeout(line));
     }
+    if !RE.is_match(&line) {
+        return parse_localized(line);
+    }
     extract_regex(&RE, line)
 }
 
@@ -22,9 +46,16 @@ impl Pinger for BSDPinger {
     where
         Self: Sized,
     {
+        options.flags.ensure_supported("BSD", BSD_FLAGS)?;
         Ok(Self { options })
     }
 
+    fn start(&self) -> Result<PingStream, PingCreationError> {
+        start_subprocess(self)
+    }
+}
+
+impl SubprocessPinger for BSDPinger {
     fn parse_fn(&self) -> fn(String) -> Option<PingResult> {
         parse_bsd
     }
@@ -38,10 +69,15 @@ impl Pinger for BSDPinger {
             args.push("-I".into());
             args.push(interface.clone());
         }
+        if let Some(source) = &self.options.source {
+            args.push("-S".into());
+            args.push(source.to_string());
+        }
+        args.extend(bsd_flag_args(&self.options.flags));
         if let Some(raw_args) = &self.options.raw_arguments {
             args.extend(raw_args.iter().cloned());
         }
         args.push(self.options.target.to_string());
-        ("ping", args)
+        (self.options.ping_command("ping"), args)
     }
 }
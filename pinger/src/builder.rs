@@ -0,0 +1,265 @@
+use crate::target::{IPVersion, Resolver, Target};
+use crate::{PingCreationError, PingFlags, PingOptions, ResultObserver};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Builds a validated [`PingOptions`]. Created with [`PingOptions::builder`].
+///
+/// ```
+/// use std::time::Duration;
+/// use pinger::PingOptions;
+/// let options = PingOptions::builder()
+///     .target("tomforb.es")
+///     .interval(Duration::from_millis(500))
+///     .ipv4()
+///     .build()
+///     .expect("Invalid options");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PingOptionsBuilder {
+    target: Option<String>,
+    interval: Option<Duration>,
+    interface: Option<String>,
+    source: Option<IpAddr>,
+    timeout: Option<Duration>,
+    reresolve: Option<Duration>,
+    restart: bool,
+    resolver: Resolver,
+    ssh: Option<String>,
+    observers: Vec<Arc<dyn ResultObserver>>,
+    flags: PingFlags,
+    ping_binary: Option<PathBuf>,
+    raw_arguments: Option<Vec<String>>,
+    ipv4: bool,
+    ipv6: bool,
+    dual_stack: bool,
+}
+
+impl PingOptionsBuilder {
+    pub fn target(mut self, target: impl ToString) -> Self {
+        self.target = Some(target.to_string());
+        self
+    }
+
+    /// The time between each ping. Defaults to one second.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    pub fn interface(mut self, interface: impl ToString) -> Self {
+        self.interface = Some(interface.to_string());
+        self
+    }
+
+    /// The source address to send probes from.
+    pub fn source(mut self, source: IpAddr) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// How long to wait for each reply before reporting a timeout.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Resolve a hostname target again on this schedule. See [`PingOptions::with_reresolve`].
+    pub fn reresolve(mut self, every: Duration) -> Self {
+        self.reresolve = Some(every);
+        self
+    }
+
+    /// Start ping again when it exits. See [`PingOptions::with_restart`].
+    pub fn restart(mut self) -> Self {
+        self.restart = true;
+        self
+    }
+
+    /// How to resolve a hostname target. Defaults to the system resolver.
+    pub fn resolver(mut self, resolver: Resolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Run ping on a remote host over SSH. See [`PingOptions::via_ssh`].
+    pub fn via_ssh(mut self, host: impl ToString) -> Self {
+        self.ssh = Some(host.to_string());
+        self
+    }
+
+    /// Call `observer` with every result. See [`PingOptions::with_observer`].
+    pub fn observer(mut self, observer: Arc<dyn ResultObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    pub fn dont_fragment(mut self) -> Self {
+        self.flags.dont_fragment = true;
+        self
+    }
+
+    pub fn adaptive(mut self) -> Self {
+        self.flags.adaptive = true;
+        self
+    }
+
+    pub fn audible(mut self) -> Self {
+        self.flags.audible = true;
+        self
+    }
+
+    pub fn numeric(mut self) -> Self {
+        self.flags.numeric = true;
+        self
+    }
+
+    pub fn mark(mut self, mark: u32) -> Self {
+        self.flags.mark = Some(mark);
+        self
+    }
+
+    pub fn timestamps(mut self) -> Self {
+        self.flags.timestamps = true;
+        self
+    }
+
+    /// Run this ping binary instead of looking up `ping` on the `PATH`.
+    pub fn ping_binary(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ping_binary = Some(path.into());
+        self
+    }
+
+    pub fn raw_arguments(mut self, raw_arguments: Vec<impl ToString>) -> Self {
+        self.raw_arguments = Some(
+            raw_arguments
+                .into_iter()
+                .map(|item| item.to_string())
+                .collect(),
+        );
+        self
+    }
+
+    /// Only ping the IPv4 address of the target.
+    pub fn ipv4(mut self) -> Self {
+        self.ipv4 = true;
+        self
+    }
+
+    /// Only ping the IPv6 address of the target.
+    pub fn ipv6(mut self) -> Self {
+        self.ipv6 = true;
+        self
+    }
+
+    /// Ping both the IPv4 and IPv6 address of the target. See [`ping_dual_stack`](crate::ping_dual_stack).
+    pub fn dual_stack(mut self) -> Self {
+        self.dual_stack = true;
+        self
+    }
+
+    pub fn build(self) -> Result<PingOptions, PingCreationError> {
+        let target = match self.target {
+            Some(target) if !target.trim().is_empty() => target,
+            _ => return Err(invalid("a target is required")),
+        };
+        if [self.ipv4, self.ipv6, self.dual_stack]
+            .iter()
+            .filter(|&&set| set)
+            .count()
+            > 1
+        {
+            return Err(invalid("ipv4, ipv6 and dual_stack are mutually exclusive"));
+        }
+
+        let interval = self.interval.unwrap_or(Duration::from_secs(1));
+        if interval.is_zero() {
+            return Err(invalid("interval must be greater than zero"));
+        }
+
+        if self.timeout.is_some_and(|timeout| timeout.is_zero()) {
+            return Err(invalid("timeout must be greater than zero"));
+        }
+        if self.reresolve.is_some_and(|every| every.is_zero()) {
+            return Err(invalid("re-resolve interval must be greater than zero"));
+        }
+
+        if let Some(interface) = &self.interface {
+            validate_interface(interface)?;
+        }
+
+        let target = if self.ipv4 {
+            Target::new_ipv4(target)
+        } else if self.ipv6 {
+            Target::new_ipv6(target)
+        } else if self.dual_stack {
+            Target::new_dual_stack(target)
+        } else {
+            Target::new_any(target)
+        };
+        if let Some(path) = &self.ping_binary {
+            if path.to_str().is_none() {
+                return Err(invalid(format!("ping binary {path:?} is not valid UTF-8")));
+            }
+            // Over SSH the binary is on the remote host, so it can't be checked here.
+            if self.ssh.is_none() && !path.is_file() {
+                return Err(invalid(format!("ping binary {path:?} does not exist")));
+            }
+        }
+        if let Some(source) = self.source {
+            validate_source(&target, source)?;
+        }
+
+        let mut options = PingOptions::from_target(target, interval, self.interface);
+        options.source = self.source;
+        options.timeout = self.timeout;
+        options.reresolve = self.reresolve;
+        options.restart = self.restart;
+        options.resolver = self.resolver;
+        options.ssh = self.ssh;
+        options.observers = self.observers;
+        options.flags = self.flags;
+        options.ping_binary = self.ping_binary;
+        options.raw_arguments = self.raw_arguments;
+        Ok(options)
+    }
+}
+
+fn invalid(reason: impl ToString) -> PingCreationError {
+    PingCreationError::InvalidOptions(reason.to_string())
+}
+
+fn validate_interface(interface: &str) -> Result<(), PingCreationError> {
+    if interface.is_empty() {
+        return Err(invalid("interface name cannot be empty"));
+    }
+    if interface
+        .chars()
+        .any(|c| c.is_whitespace() || c.is_control())
+    {
+        return Err(invalid(format!(
+            "interface name {interface:?} contains invalid characters"
+        )));
+    }
+    Ok(())
+}
+
+fn validate_source(target: &Target, source: IpAddr) -> Result<(), PingCreationError> {
+    let target_is_ipv6 = match target {
+        Target::IP(ip) => ip.is_ipv6(),
+        Target::Hostname {
+            version: IPVersion::Any,
+            ..
+        }
+        | Target::DualStack { .. } => return Ok(()),
+        Target::Hostname { version, .. } => *version == IPVersion::V6,
+    };
+    if source.is_ipv6() != target_is_ipv6 {
+        return Err(invalid(format!(
+            "source address {source} is not the same IP version as the target {target}"
+        )));
+    }
+    Ok(())
+}
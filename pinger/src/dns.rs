@@ -0,0 +1,241 @@
+//! A minimal DNS client, for resolving targets through a specific server rather than the system
+//! resolver, or with multicast DNS. Only A and AAAA queries are supported.
+use std::convert::TryInto;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// Hosts on the local network answer quickly, if at all.
+#[cfg(feature = "mdns")]
+const MDNS_TIMEOUT: Duration = Duration::from_secs(2);
+#[cfg(feature = "mdns")]
+const MDNS_GROUP: (Ipv4Addr, u16) = (Ipv4Addr::new(224, 0, 0, 251), 5353);
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum RecordType {
+    A = 1,
+    Aaaa = 28,
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason.to_string())
+}
+
+fn query_id() -> u16 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    (nanos ^ std::process::id()) as u16
+}
+
+/// Encode a recursive query for `domain`.
+pub(crate) fn build_query(id: u16, domain: &str, record: RecordType) -> io::Result<Vec<u8>> {
+    let mut query = Vec::with_capacity(domain.len() + 18);
+    query.extend(id.to_be_bytes());
+    // Flags: recursion desired. One question, no other records.
+    query.extend([0x01, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0]);
+    for label in domain.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid domain name {domain:?}"),
+            ));
+        }
+        query.push(label.len() as u8);
+        query.extend(label.as_bytes());
+    }
+    query.push(0);
+    query.extend((record as u16).to_be_bytes());
+    // Class IN
+    query.extend([0x00, 0x01]);
+    Ok(query)
+}
+
+fn read_u16(message: &[u8], offset: usize) -> io::Result<u16> {
+    message
+        .get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| invalid("truncated DNS response"))
+}
+
+/// Return the offset just past the name starting at `offset`.
+fn skip_name(message: &[u8], mut offset: usize) -> io::Result<usize> {
+    loop {
+        let len = *message
+            .get(offset)
+            .ok_or_else(|| invalid("truncated DNS response"))?;
+        match len {
+            0 => return Ok(offset + 1),
+            // A compression pointer ends the name
+            len if len & 0xC0 == 0xC0 => return Ok(offset + 2),
+            len => offset += 1 + len as usize,
+        }
+    }
+}
+
+/// Extract the addresses from the answers to the query with the given ID.
+pub(crate) fn parse_response(message: &[u8], id: u16) -> io::Result<Vec<IpAddr>> {
+    if read_u16(message, 0)? != id {
+        return Err(invalid("DNS response does not match the query"));
+    }
+    let flags = read_u16(message, 2)?;
+    match flags & 0x000F {
+        0 => {}
+        // NXDOMAIN
+        3 => return Ok(vec![]),
+        code => {
+            return Err(io::Error::other(format!(
+                "DNS server returned error code {code}"
+            )))
+        }
+    }
+    let questions = read_u16(message, 4)?;
+    let answers = read_u16(message, 6)?;
+
+    let mut offset = 12;
+    for _ in 0..questions {
+        offset = skip_name(message, offset)? + 4;
+    }
+    let mut addresses = vec![];
+    for _ in 0..answers {
+        offset = skip_name(message, offset)?;
+        let record = read_u16(message, offset)?;
+        let len = read_u16(message, offset + 8)? as usize;
+        offset += 10;
+        let data = message
+            .get(offset..offset + len)
+            .ok_or_else(|| invalid("truncated DNS response"))?;
+        // Anything else, like the CNAMEs leading to the address, is skipped.
+        if record == RecordType::A as u16 && len == 4 {
+            let octets: [u8; 4] = data.try_into().unwrap();
+            addresses.push(IpAddr::V4(Ipv4Addr::from(octets)));
+        } else if record == RecordType::Aaaa as u16 && len == 16 {
+            let octets: [u8; 16] = data.try_into().unwrap();
+            addresses.push(IpAddr::V6(Ipv6Addr::from(octets)));
+        }
+        offset += len;
+    }
+    Ok(addresses)
+}
+
+/// Query a DNS server over UDP.
+pub(crate) fn query_server(
+    server: SocketAddr,
+    domain: &str,
+    record: RecordType,
+) -> io::Result<Vec<IpAddr>> {
+    let bind: SocketAddr = if server.is_ipv6() {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(bind)?;
+    socket.set_read_timeout(Some(TIMEOUT))?;
+    socket.connect(server)?;
+
+    let id = query_id();
+    socket.send(&build_query(id, domain, record)?)?;
+    let mut buffer = [0; 1232];
+    let len = socket.recv(&mut buffer)?;
+    parse_response(&buffer[..len], id)
+}
+
+/// Query a DNS-over-HTTPS endpoint, such as `https://dns.quad9.net/dns-query`, using the
+/// RFC 8484 wire format.
+#[cfg(feature = "doh")]
+pub(crate) fn query_https(url: &str, domain: &str, record: RecordType) -> io::Result<Vec<IpAddr>> {
+    use std::io::Read;
+
+    // The ID should be zero for DoH, to make responses cacheable.
+    let response = ureq::post(url)
+        .timeout(TIMEOUT)
+        .set("Content-Type", "application/dns-message")
+        .set("Accept", "application/dns-message")
+        .send_bytes(&build_query(0, domain, record)?)
+        .map_err(io::Error::other)?;
+    let mut message = vec![];
+    response.into_reader().read_to_end(&mut message)?;
+    parse_response(&message, 0)
+}
+
+/// Whether a name is in the `.local` domain, which is resolved with multicast DNS (RFC 6762).
+#[cfg(feature = "mdns")]
+pub(crate) fn is_mdns_name(domain: &str) -> bool {
+    domain
+        .trim_end_matches('.')
+        .to_ascii_lowercase()
+        .ends_with(".local")
+}
+
+/// Ask the hosts on the local network for the address of a `.local` name. This sends a one-shot
+/// query from an ephemeral port, which responders answer directly rather than to the group.
+#[cfg(feature = "mdns")]
+pub(crate) fn query_mdns(domain: &str, record: RecordType) -> io::Result<Vec<IpAddr>> {
+    use std::time::Instant;
+
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    let id = query_id();
+    socket.send_to(&build_query(id, domain, record)?, MDNS_GROUP)?;
+
+    let deadline = Instant::now() + MDNS_TIMEOUT;
+    let mut buffer = [0; 9000];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(vec![]);
+        }
+        socket.set_read_timeout(Some(remaining))?;
+        let len = match socket.recv(&mut buffer) {
+            Ok(len) => len,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Ok(vec![])
+            }
+            Err(e) => return Err(e),
+        };
+        // Every host that knows the name may answer, so skip any that don't have an address.
+        match parse_response(&buffer[..len], id) {
+            Ok(addresses) if !addresses.is_empty() => return Ok(addresses),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_response() {
+        let query = build_query(0x1234, "example.com", RecordType::A).unwrap();
+        let mut response = query.clone();
+        // Mark it as a response with two answers: a CNAME and then the address it points at.
+        response[2..4].copy_from_slice(&[0x81, 0x80]);
+        response[6..8].copy_from_slice(&[0x00, 0x02]);
+        response.extend([0xC0, 0x0C, 0x00, 0x05, 0x00, 0x01, 0, 0, 0, 60, 0x00, 0x02]);
+        response.extend([0xC0, 0x0C]);
+        response.extend([0xC0, 0x0C, 0x00, 0x01, 0x00, 0x01, 0, 0, 0, 60, 0x00, 0x04]);
+        response.extend([93, 184, 215, 14]);
+
+        let addresses = parse_response(&response, 0x1234).unwrap();
+        assert_eq!(addresses, vec![IpAddr::from([93, 184, 215, 14])]);
+        assert!(parse_response(&response, 0x4321).is_err());
+        assert!(build_query(0, "bad..name", RecordType::A).is_err());
+    }
+
+    #[cfg(feature = "mdns")]
+    #[test]
+    fn test_is_mdns_name() {
+        assert!(is_mdns_name("printer.local"));
+        assert!(is_mdns_name("Printer.LOCAL."));
+        assert!(!is_mdns_name("local"));
+        assert!(!is_mdns_name("printer.localdomain"));
+    }
+}
@@ -0,0 +1,46 @@
+//! Serde support for `std::process::ExitStatus`, which has no portable constructor.
+//! Statuses are stored as their raw platform value alongside the exit code.
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::process::ExitStatus;
+
+#[derive(Serialize, Deserialize)]
+struct ExitStatusDef {
+    code: Option<i32>,
+    raw: i64,
+}
+
+pub fn serialize<S: Serializer>(status: &ExitStatus, serializer: S) -> Result<S::Ok, S::Error> {
+    ExitStatusDef {
+        code: status.code(),
+        raw: into_raw(status),
+    }
+    .serialize(serializer)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ExitStatus, D::Error> {
+    let def = ExitStatusDef::deserialize(deserializer)?;
+    Ok(from_raw(def.raw))
+}
+
+#[cfg(unix)]
+fn into_raw(status: &ExitStatus) -> i64 {
+    use std::os::unix::process::ExitStatusExt;
+    status.into_raw() as i64
+}
+
+#[cfg(unix)]
+fn from_raw(raw: i64) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    ExitStatus::from_raw(raw as i32)
+}
+
+#[cfg(windows)]
+fn into_raw(status: &ExitStatus) -> i64 {
+    status.code().unwrap_or_default() as u32 as i64
+}
+
+#[cfg(windows)]
+fn from_raw(raw: i64) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(raw as u32)
+}
@@ -1,43 +1,139 @@
-use crate::{PingCreationError, PingOptions, PingResult, Pinger};
+use crate::{PingControl, PingCreationError, PingOptions, PingResult, PingStream, Pinger};
 use rand::prelude::*;
-use std::sync::mpsc;
-use std::sync::mpsc::Receiver;
+use std::str::FromStr;
+use std::sync::{mpsc, Arc};
 use std::thread;
 use std::time::Duration;
 
+/// How fake round trip times are spread around the base latency.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FakeDistribution {
+    /// Evenly spread between `base - jitter` and `base + jitter`.
+    Uniform,
+    /// Normally distributed around `base`, with `jitter` as the standard deviation.
+    Normal,
+}
+
+/// Controls the results produced by [`FakePinger`]. The defaults match the original behaviour of
+/// uniform 50-150ms replies with no loss.
+#[derive(Debug, Clone)]
+pub struct FakeOptions {
+    pub base: Duration,
+    pub jitter: Duration,
+    pub distribution: FakeDistribution,
+    /// Percentage of probes that time out, between 0 and 100.
+    pub loss: f64,
+    /// Percentage of replies that are delayed by `spike`, between 0 and 100.
+    pub spike_chance: f64,
+    pub spike: Duration,
+    /// Seed the random number generator, to produce the same results on every run.
+    pub seed: Option<u64>,
+}
+
+impl Default for FakeOptions {
+    fn default() -> Self {
+        Self {
+            base: Duration::from_millis(100),
+            jitter: Duration::from_millis(50),
+            distribution: FakeDistribution::Uniform,
+            loss: 0.0,
+            spike_chance: 0.0,
+            spike: Duration::from_millis(500),
+            seed: None,
+        }
+    }
+}
+
+fn env_var<T: FromStr>(name: &str) -> Option<T> {
+    std::env::var(name).ok()?.parse().ok()
+}
+
+impl FakeOptions {
+    /// Read options from `PINGER_FAKE_*` environment variables, using the defaults for any that
+    /// are unset or invalid: `BASE_MS`, `JITTER_MS`, `DISTRIBUTION` (`uniform` or `normal`),
+    /// `LOSS`, `SPIKE_CHANCE`, `SPIKE_MS` and `SEED`.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let millis = |name| env_var(name).map(Duration::from_millis);
+        Self {
+            base: millis("PINGER_FAKE_BASE_MS").unwrap_or(defaults.base),
+            jitter: millis("PINGER_FAKE_JITTER_MS").unwrap_or(defaults.jitter),
+            distribution: match std::env::var("PINGER_FAKE_DISTRIBUTION").as_deref() {
+                Ok("normal") => FakeDistribution::Normal,
+                _ => defaults.distribution,
+            },
+            loss: env_var("PINGER_FAKE_LOSS").unwrap_or(defaults.loss),
+            spike_chance: env_var("PINGER_FAKE_SPIKE_CHANCE").unwrap_or(defaults.spike_chance),
+            spike: millis("PINGER_FAKE_SPIKE_MS").unwrap_or(defaults.spike),
+            seed: env_var("PINGER_FAKE_SEED"),
+        }
+    }
+
+    fn sample(&self, random: &mut impl Rng) -> PingResult {
+        if random.gen_bool((self.loss / 100.0).clamp(0.0, 1.0)) {
+            return PingResult::Timeout("Fake timeout".to_string());
+        }
+        let base = self.base.as_secs_f64();
+        let jitter = self.jitter.as_secs_f64();
+        let mut seconds = match self.distribution {
+            FakeDistribution::Uniform => base + jitter * random.gen_range(-1.0..1.0),
+            FakeDistribution::Normal => {
+                // Box-Muller transform
+                let (u1, u2): (f64, f64) = (1.0 - random.gen::<f64>(), random.gen());
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+                base + jitter * z
+            }
+        };
+        if random.gen_bool((self.spike_chance / 100.0).clamp(0.0, 1.0)) {
+            seconds += self.spike.as_secs_f64();
+        }
+        let duration = Duration::from_secs_f64(seconds.max(0.0));
+        PingResult::Pong(
+            duration,
+            format!("Fake ping line: {} ms", duration.as_millis()),
+            None,
+        )
+    }
+}
+
 pub struct FakePinger {
     options: PingOptions,
+    fake: FakeOptions,
+}
+
+impl FakePinger {
+    pub fn new(options: PingOptions, fake: FakeOptions) -> Self {
+        Self { options, fake }
+    }
 }
 
 impl Pinger for FakePinger {
+    /// Uses [`FakeOptions::from_env`]. Use [`FakePinger::new`] to configure it directly.
     fn from_options(options: PingOptions) -> Result<Self, PingCreationError>
     where
         Self: Sized,
     {
-        Ok(Self { options })
-    }
-
-    fn parse_fn(&self) -> fn(String) -> Option<PingResult> {
-        unimplemented!("parse for FakeParser not implemented")
-    }
-
-    fn ping_args(&self) -> (&str, Vec<String>) {
-        unimplemented!("ping_args not implemented for FakePinger")
+        Ok(Self::new(options, FakeOptions::from_env()))
     }
 
-    fn start(&self) -> Result<Receiver<PingResult>, PingCreationError> {
+    fn start(&self) -> Result<PingStream, PingCreationError> {
         let (tx, rx) = mpsc::channel();
         let sleep_time = self.options.interval;
+        let fake = self.fake.clone();
+        let control = Arc::new(PingControl::default());
+        let thread_control = control.clone();
 
         thread::spawn(move || {
-            let mut random = thread_rng();
-            loop {
-                let fake_seconds = random.gen_range(50..150);
-                let ping_result = PingResult::Pong(
-                    Duration::from_millis(fake_seconds),
-                    format!("Fake ping line: {fake_seconds} ms"),
-                );
-                if tx.send(ping_result).is_err() {
+            let mut random = match fake.seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            };
+            while !thread_control.is_stopped() {
+                if thread_control.is_paused() {
+                    thread::sleep(sleep_time);
+                    continue;
+                }
+                if tx.send(fake.sample(&mut random)).is_err() {
                     break;
                 }
 
@@ -45,6 +141,38 @@ impl Pinger for FakePinger {
             }
         });
 
-        Ok(rx)
+        Ok(PingStream::new(rx, control))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(fake: &FakeOptions) -> Vec<String> {
+        let mut random = StdRng::seed_from_u64(fake.seed.unwrap());
+        (0..20)
+            .map(|_| fake.sample(&mut random).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_seeded_samples() {
+        let fake = FakeOptions {
+            distribution: FakeDistribution::Normal,
+            loss: 25.0,
+            seed: Some(42),
+            ..Default::default()
+        };
+        let first = samples(&fake);
+        assert_eq!(first, samples(&fake));
+        assert!(first.iter().any(|result| result == "Timeout"));
+
+        let no_jitter = FakeOptions {
+            jitter: Duration::ZERO,
+            seed: Some(1),
+            ..Default::default()
+        };
+        assert!(samples(&no_jitter).iter().all(|result| result == "100ms"));
     }
 }
@@ -0,0 +1,80 @@
+use crate::PingCreationError;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+
+/// Common ping flags, translated to the right arguments for each platform. Setting a flag the
+/// platform's ping doesn't support is an error when the pinger is created.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PingFlags {
+    /// Set the Don't Fragment bit on outgoing packets.
+    pub dont_fragment: bool,
+    /// Send the next probe as soon as a reply arrives, rather than waiting for the interval.
+    pub adaptive: bool,
+    /// Ring the terminal bell on every reply.
+    pub audible: bool,
+    /// Don't try to look up names for addresses in replies.
+    pub numeric: bool,
+    /// Tag outgoing packets with a socket mark (`SO_MARK`), for policy routing.
+    pub mark: Option<u32>,
+    /// Report the time each reply arrived, rather than leaving it to when the line was read.
+    pub timestamps: bool,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PingFlag {
+    DontFragment,
+    Adaptive,
+    Audible,
+    Numeric,
+    Mark,
+    Timestamps,
+}
+
+impl Display for PingFlag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PingFlag::DontFragment => "don't fragment",
+            PingFlag::Adaptive => "adaptive",
+            PingFlag::Audible => "audible",
+            PingFlag::Numeric => "numeric",
+            PingFlag::Mark => "mark",
+            PingFlag::Timestamps => "timestamps",
+        };
+        f.write_str(name)
+    }
+}
+
+impl PingFlags {
+    /// The flags that are set.
+    pub fn enabled(&self) -> Vec<PingFlag> {
+        [
+            (self.dont_fragment, PingFlag::DontFragment),
+            (self.adaptive, PingFlag::Adaptive),
+            (self.audible, PingFlag::Audible),
+            (self.numeric, PingFlag::Numeric),
+            (self.mark.is_some(), PingFlag::Mark),
+            (self.timestamps, PingFlag::Timestamps),
+        ]
+        .iter()
+        .filter_map(|&(set, flag)| set.then_some(flag))
+        .collect()
+    }
+
+    pub(crate) fn ensure_supported(
+        &self,
+        ping: &str,
+        supported: &[PingFlag],
+    ) -> Result<(), PingCreationError> {
+        match self
+            .enabled()
+            .into_iter()
+            .find(|flag| !supported.contains(flag))
+        {
+            Some(flag) => Err(PingCreationError::InvalidOptions(format!(
+                "the {flag} flag is not supported by {ping} ping"
+            ))),
+            None => Ok(()),
+        }
+    }
+}
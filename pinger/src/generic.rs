@@ -0,0 +1,64 @@
+use crate::locale::parse_localized;
+use crate::{
+    extract_regex, start_subprocess, PingCreationError, PingOptions, PingResult, PingStream,
+    Pinger, SubprocessPinger,
+};
+use lazy_regex::*;
+use std::sync::Arc;
+
+pub static RE: Lazy<Regex> = lazy_regex!(r"(?i)time[=<](?P<ms>\d+)(?:[.,](?P<ns>\d+))?");
+
+/// A best-effort pinger for platforms whose ping could not be detected, such as Haiku.
+/// Only the target is passed, as even flags like `-i` differ between implementations,
+/// so the interval is whatever the platform defaults to.
+pub struct GenericPinger {
+    options: PingOptions,
+}
+
+pub(crate) fn parse_generic(line: String) -> Option<PingResult> {
+    if line.starts_with("PING ") {
+        return None;
+    }
+    let lowercase = line.to_lowercase();
+    if lowercase.contains("timeout") || lowercase.contains("timed out") {
+        return Some(PingResult::Timeout(line));
+    }
+    if !RE.is_match(&line) {
+        return parse_localized(line);
+    }
+    extract_regex(&RE, line)
+}
+
+impl Pinger for GenericPinger {
+    fn from_options(options: PingOptions) -> Result<Self, PingCreationError>
+    where
+        Self: Sized,
+    {
+        options.flags.ensure_supported("this platform's", &[])?;
+        Ok(Self { options })
+    }
+
+    fn start(&self) -> Result<PingStream, PingCreationError> {
+        start_subprocess(self)
+    }
+
+    // Picking a pinger again would rerun the detection this one fell back from.
+    fn with_options(&self, options: PingOptions) -> Result<Arc<dyn Pinger>, PingCreationError> {
+        Ok(Arc::new(Self::from_options(options)?))
+    }
+}
+
+impl SubprocessPinger for GenericPinger {
+    fn parse_fn(&self) -> fn(String) -> Option<PingResult> {
+        parse_generic
+    }
+
+    fn ping_args(&self) -> (&str, Vec<String>) {
+        let mut args = vec![];
+        if let Some(raw_args) = &self.options.raw_arguments {
+            args.extend(raw_args.iter().cloned());
+        }
+        args.push(self.options.target.to_string());
+        (self.options.ping_command("ping"), args)
+    }
+}
@@ -10,41 +10,111 @@ use crate::linux::LinuxPinger;
 /// let stream = ping(options).expect("Error pinging");
 /// for message in stream {
 ///     match message {
-///         PingResult::Pong(duration, line) => println!("{:?} (line: {})", duration, line),
+///         PingResult::Pong(duration, line, _) => println!("{:?} (line: {})", duration, line),
+///         PingResult::Duplicate(duration, _) => println!("Duplicate reply after {:?}", duration),
+///         PingResult::NtpReply(delay, offset, _) => println!("{:?}, offset {}s", delay, offset),
 ///         PingResult::Timeout(_) => println!("Timeout!"),
+///         PingResult::Unreachable(_) | PingResult::TtlExceeded(_) | PingResult::Filtered(_) => {
+///             println!("Error: {}", message)
+///         }
+///         PingResult::AddressChanged(ip) => println!("Now pinging {}", ip),
+///         PingResult::Restarted(stderr) => println!("Ping restarted after: {}", stderr),
 ///         PingResult::Unknown(line) => println!("Unknown line: {}", line),
-///         PingResult::PingExited(_code, _stderr) => {}
+///         PingResult::PingExited(_code, _stderr, _summary) => {}
+///         _ => {} // Kinds of result added in later versions
 ///     }
 /// }
 /// ```
-use lazy_regex::Regex;
+use lazy_regex::{lazy_regex, Lazy, Regex};
 use std::ffi::OsStr;
 use std::fmt::{Debug, Formatter};
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, ExitStatus, Stdio};
 use std::sync::{mpsc, Arc};
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::{fmt, io, thread};
-use target::Target;
+pub(crate) use stream::PingControl;
+pub use stream::PingStream;
+pub use target::{IPVersion, Resolver, Target};
 use thiserror::Error;
 
+pub use builder::PingOptionsBuilder;
+#[cfg(feature = "fake-ping")]
+pub use fake::{FakeDistribution, FakeOptions, FakePinger};
+pub use flags::{PingFlag, PingFlags};
+pub use ntp::NtpPinger;
+pub use observer::{CountingObserver, ResultObserver};
+pub use stats::{PingStats, PingStatsSnapshot};
+use summary::SummaryParser;
+pub use summary::{PingSummary, RttSummary};
+pub use sweep::sweep;
+#[cfg(feature = "websocket")]
+pub use websocket::WebSocketPinger;
+
+pub mod aix;
 pub mod linux;
 pub mod macos;
 #[cfg(windows)]
 pub mod windows;
 
 mod bsd;
+mod builder;
+mod dns;
+#[cfg(feature = "serde")]
+mod exit_status;
 #[cfg(feature = "fake-ping")]
 mod fake;
+mod flags;
+mod generic;
+mod locale;
+mod ntp;
+mod observer;
+mod ssh;
+mod stats;
+mod stream;
+mod summary;
+mod supervisor;
+mod sweep;
 mod target;
 #[cfg(test)]
 mod test;
+#[cfg(feature = "websocket")]
+mod websocket;
+
+/// The examples in the README are compiled as doctests, so they can't fall out of date.
+#[cfg(doctest)]
+#[doc = include_str!("../README.md")]
+struct ReadmeDoctests;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PingOptions {
     pub target: Target,
     pub interval: Duration,
     pub interface: Option<String>,
+    pub source: Option<IpAddr>,
+    /// How long to wait for each reply. Only used on Windows and by iputils, which rounds it up
+    /// to whole seconds.
+    pub timeout: Option<Duration>,
+    /// How often to resolve a hostname target again, to follow DNS changes during long sessions.
+    pub reresolve: Option<Duration>,
+    /// Start ping again when it exits, such as after a network error, rather than ending the stream.
+    pub restart: bool,
+    /// How hostname targets are resolved. Anything other than the system resolver resolves the
+    /// target before ping is started.
+    pub resolver: Resolver,
+    /// Run ping on this host over SSH, such as `user@host`, rather than locally.
+    pub ssh: Option<String>,
+    /// Called with every result produced by [`ping`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub observers: Vec<Arc<dyn ResultObserver>>,
+    pub flags: PingFlags,
+    /// Run this ping binary instead of looking up `ping` on the `PATH`. Not used on Windows.
+    pub ping_binary: Option<PathBuf>,
+    /// Extra arguments passed directly to the ping command. Prefer `flags` where possible, as
+    /// these are platform specific and not validated.
     pub raw_arguments: Option<Vec<String>>,
 }
 
@@ -58,14 +128,92 @@ impl PingOptions {
         );
         self
     }
+
+    pub fn with_flags(mut self, flags: PingFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Use a specific ping binary, for systems where `ping` isn't on the `PATH` or isn't the right one.
+    pub fn with_ping_binary(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ping_binary = Some(path.into());
+        self
+    }
+
+    /// The command to run, which is the configured ping binary if there is one.
+    pub(crate) fn ping_command<'a>(&'a self, default: &'a str) -> &'a str {
+        self.ping_binary
+            .as_deref()
+            .and_then(Path::to_str)
+            .unwrap_or(default)
+    }
+
+    /// Send probes from the given source address, for hosts with more than one address.
+    pub fn with_source(mut self, source: IpAddr) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Report a probe as timed out if no reply arrives within this time, regardless of the interval.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Resolve a hostname target again on this schedule, restarting ping when its address changes.
+    pub fn with_reresolve(mut self, every: Duration) -> Self {
+        self.reresolve = Some(every);
+        self
+    }
+
+    /// Start ping again when it exits, waiting longer between each attempt until it replies. A
+    /// [`PingResult::Restarted`] result is sent in place of [`PingResult::PingExited`], so the
+    /// stream only ends when it is stopped.
+    pub fn with_restart(mut self) -> Self {
+        self.restart = true;
+        self
+    }
+
+    /// Resolve hostname targets with the given resolver, such as a specific DNS server.
+    pub fn with_resolver(mut self, resolver: Resolver) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Ping from a remote host, by running ping on it over SSH. The host can be anything `ssh`
+    /// accepts, including aliases from `~/.ssh/config`, and must not prompt for a password.
+    pub fn via_ssh(mut self, host: impl ToString) -> Self {
+        self.ssh = Some(host.to_string());
+        self
+    }
+
+    /// Call `observer` with every result as it is produced.
+    pub fn with_observer(mut self, observer: Arc<dyn ResultObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
 }
 
 impl PingOptions {
+    /// Create a builder that validates the options before they are used.
+    pub fn builder() -> PingOptionsBuilder {
+        PingOptionsBuilder::default()
+    }
+
     pub fn from_target(target: Target, interval: Duration, interface: Option<String>) -> Self {
         Self {
             target,
             interval,
             interface,
+            source: None,
+            timeout: None,
+            reresolve: None,
+            restart: false,
+            resolver: Resolver::System,
+            ssh: None,
+            observers: vec![],
+            flags: PingFlags::default(),
+            ping_binary: None,
             raw_arguments: None,
         }
     }
@@ -97,6 +245,26 @@ pub fn run_ping(
         .spawn()?)
 }
 
+/// Classify the ICMP error messages that ping prints when a probe is answered with an error,
+/// such as "From 10.0.0.1 icmp_seq=1 Destination Host Unreachable" (iputils) or
+/// "92 bytes from 10.0.0.1: Time to live exceeded" (BSD).
+/// Local errors such as "ping: sendto: Network unreachable" aren't replies, so are ignored.
+pub(crate) fn parse_icmp_error(line: String) -> Option<PingResult> {
+    if line.starts_with("ping:") {
+        return None;
+    }
+    let lowercase = line.to_lowercase();
+    if lowercase.contains("packet filtered") || lowercase.contains("prohibited") {
+        Some(PingResult::Filtered(line))
+    } else if lowercase.contains("unreachable") {
+        Some(PingResult::Unreachable(line))
+    } else if lowercase.contains("time to live exceeded") || lowercase.contains("ttl expired") {
+        Some(PingResult::TtlExceeded(line))
+    } else {
+        None
+    }
+}
+
 pub(crate) fn extract_regex(regex: &Regex, line: String) -> Option<PingResult> {
     let cap = regex.captures(&line)?;
     let ms = cap
@@ -108,14 +276,30 @@ pub(crate) fn extract_regex(regex: &Regex, line: String) -> Option<PingResult> {
     let ns = match cap.name("ns") {
         None => 0,
         Some(cap) => {
-            let matched_str = cap.as_str();
+            // Digits past nanoseconds are dropped, as some pings print more than six.
+            let matched_str = &cap.as_str()[..cap.as_str().len().min(6)];
             let number_of_digits = matched_str.len() as u32;
             let fractional_ms = matched_str.parse::<u64>().ok()?;
             fractional_ms * (10u64.pow(6 - number_of_digits))
         }
     };
     let duration = Duration::from_millis(ms) + Duration::from_nanos(ns);
-    Some(PingResult::Pong(duration, line))
+    // iputils and BSD ping mark repeated replies to the same probe with "(DUP!)"
+    if line.contains("(DUP!)") {
+        return Some(PingResult::Duplicate(duration, line));
+    }
+    let timestamp = reply_timestamp(&line);
+    Some(PingResult::Pong(duration, line, timestamp))
+}
+
+/// Matches the `[1700000000.123456]` prefix iputils adds to each line when run with `-D`.
+pub(crate) static TIMESTAMP_RE: Lazy<Regex> = lazy_regex!(r"^\[(?P<secs>\d+)\.(?P<us>\d+)\] ");
+
+fn reply_timestamp(line: &str) -> Option<SystemTime> {
+    let cap = TIMESTAMP_RE.captures(line)?;
+    let secs = cap.name("secs")?.as_str().parse::<u64>().ok()?;
+    let micros = cap.name("us")?.as_str().parse::<u64>().ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(secs) + Duration::from_micros(micros))
 }
 
 pub trait Pinger: Send + Sync {
@@ -123,57 +307,151 @@ pub trait Pinger: Send + Sync {
     where
         Self: Sized;
 
+    fn start(&self) -> Result<PingStream, PingCreationError>;
+
+    /// A pinger like this one for other options, such as another target, which reuses whatever
+    /// was detected when this one was created. By default one is picked with [`get_pinger`].
+    fn with_options(
+        &self,
+        options: PingOptions,
+    ) -> std::result::Result<Arc<dyn Pinger>, PingCreationError> {
+        get_pinger(options)
+    }
+}
+
+/// A [`Pinger`] that runs a ping command and parses each line of its output.
+pub trait SubprocessPinger: Pinger {
     fn parse_fn(&self) -> fn(String) -> Option<PingResult>;
 
     fn ping_args(&self) -> (&str, Vec<String>);
+}
+
+/// Run the command of a [`SubprocessPinger`], parsing every line of its output.
+pub(crate) fn start_subprocess(
+    pinger: &impl SubprocessPinger,
+) -> Result<PingStream, PingCreationError> {
+    let (cmd, args) = pinger.ping_args();
+    let parse_fn = pinger.parse_fn();
+    spawn_ping(cmd, args, move |line| parse_fn(line).into_iter().collect())
+}
+
+/// Run a ping subprocess, passing every line of its output to `parse` on a background thread.
+/// `parse` can return any number of results for each line, which lets backends keep state
+/// between lines.
+pub(crate) fn spawn_ping(
+    cmd: impl AsRef<OsStr> + Debug,
+    args: Vec<impl AsRef<OsStr> + Debug>,
+    parse: impl FnMut(String) -> Vec<PingResult> + Send + 'static,
+) -> Result<PingStream, PingCreationError> {
+    let (tx, rx) = mpsc::channel();
+    let control = spawn_ping_into(cmd, args, parse, tx)?;
+    Ok(PingStream::new(rx, control))
+}
 
-    fn start(&self) -> Result<mpsc::Receiver<PingResult>, PingCreationError> {
-        let (tx, rx) = mpsc::channel();
-        let (cmd, args) = self.ping_args();
+/// Like [`spawn_ping`], but sending results to `tx`, so that a backend can also send results of
+/// its own from another thread until the subprocess exits.
+pub(crate) fn spawn_ping_into(
+    cmd: impl AsRef<OsStr> + Debug,
+    args: Vec<impl AsRef<OsStr> + Debug>,
+    mut parse: impl FnMut(String) -> Vec<PingResult> + Send + 'static,
+    tx: mpsc::Sender<PingResult>,
+) -> Result<Arc<PingControl>, PingCreationError> {
+    let mut child = run_ping(cmd, args)?;
+    let stdout = child.stdout.take().expect("child did not have a stdout");
+    let mut stderr = child.stderr.take().expect("child did not have a stderr");
 
-        let mut child = run_ping(cmd, args)?;
-        let stdout = child.stdout.take().expect("child did not have a stdout");
+    let control = Arc::new(PingControl::default());
+    control.set_child(child);
 
-        let parse_fn = self.parse_fn();
+    let thread_control = control.clone();
 
-        thread::spawn(move || {
-            let reader = BufReader::new(stdout).lines();
-            for line in reader {
-                match line {
-                    Ok(msg) => {
-                        if let Some(result) = parse_fn(msg) {
-                            if tx.send(result).is_err() {
-                                break;
-                            }
+    thread::spawn(move || {
+        let reader = BufReader::new(stdout).lines();
+        let mut summary = SummaryParser::default();
+        'lines: for line in reader {
+            match line {
+                Ok(msg) => {
+                    summary.feed(&msg);
+                    if thread_control.is_paused() {
+                        continue;
+                    }
+                    for result in parse(msg) {
+                        if tx.send(result).is_err() {
+                            break 'lines;
                         }
                     }
-                    Err(_) => break,
                 }
+                Err(_) => break,
             }
-            let result = child.wait_with_output().expect("Child wasn't started?");
-            let decoded_stderr = String::from_utf8(result.stderr).expect("Error decoding stderr");
-            let _ = tx.send(PingResult::PingExited(result.status, decoded_stderr));
-        });
+        }
+        let mut stderr_bytes = vec![];
+        let _ = stderr.read_to_end(&mut stderr_bytes);
+        // If the stream was stopped the child has already been killed and reaped, and
+        // the consumer isn't interested in the exit status.
+        let Some(mut child) = thread_control.take_child() else {
+            return;
+        };
+        let status = child.wait().expect("Child wasn't started?");
+        let decoded_stderr = String::from_utf8(stderr_bytes).expect("Error decoding stderr");
+        let _ = tx.send(PingResult::PingExited(
+            status,
+            decoded_stderr,
+            summary.finish(),
+        ));
+    });
 
-        Ok(rx)
-    }
+    Ok(control)
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum PingResult {
-    Pong(Duration, String),
+    /// A reply, with the time it arrived if ping reports it (see [`PingFlags::timestamps`]).
+    Pong(Duration, String, Option<SystemTime>),
+    /// A duplicate reply to a probe that was already answered, which can indicate a network problem.
+    Duplicate(Duration, String),
+    /// A reply from an NTP server, with the round trip delay and the offset of the local clock
+    /// from the server's in seconds, which is positive when the local clock is behind.
+    NtpReply(Duration, f64, String),
     Timeout(String),
+    /// The destination host, network or port was reported unreachable.
+    Unreachable(String),
+    /// The probe's time to live ran out before it reached the destination.
+    TtlExceeded(String),
+    /// The probe was dropped by a filter, such as a firewall.
+    Filtered(String),
+    /// The hostname being pinged now resolves to a different address, which is pinged from here on.
+    /// Only sent when re-resolution is enabled with [`PingOptions::with_reresolve`].
+    AddressChanged(IpAddr),
+    /// Ping exited with the given stderr and was started again. Only sent when restarting is
+    /// enabled with [`PingOptions::with_restart`].
+    Restarted(String),
     Unknown(String),
-    PingExited(ExitStatus, String),
+    /// Ping exited, with its stderr and the summary it printed, if any.
+    PingExited(
+        #[cfg_attr(feature = "serde", serde(with = "exit_status"))] ExitStatus,
+        String,
+        Option<PingSummary>,
+    ),
 }
 
 impl fmt::Display for PingResult {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match &self {
-            PingResult::Pong(duration, _) => write!(f, "{duration:?}"),
+            PingResult::Pong(duration, ..) => write!(f, "{duration:?}"),
+            PingResult::Duplicate(duration, _) => write!(f, "{duration:?} (DUP!)"),
+            PingResult::NtpReply(duration, offset, _) => {
+                write!(f, "{duration:?} (offset {offset:+.6}s)")
+            }
             PingResult::Timeout(_) => write!(f, "Timeout"),
+            PingResult::Unreachable(_) => write!(f, "Unreachable"),
+            PingResult::TtlExceeded(_) => write!(f, "TTL exceeded"),
+            PingResult::Filtered(_) => write!(f, "Filtered"),
+            PingResult::AddressChanged(ip) => write!(f, "Address changed to {ip}"),
+            PingResult::Restarted(_) => write!(f, "Restarted"),
             PingResult::Unknown(_) => write!(f, "Unknown"),
-            PingResult::PingExited(status, stderr) => write!(f, "Exited({status}, {stderr})"),
+            PingResult::PingExited(status, stderr, _) => write!(f, "Exited({status}, {stderr})"),
         }
     }
 }
@@ -193,9 +471,42 @@ pub enum PingCreationError {
 
     #[error("Invalid or unresolvable hostname {0}")]
     HostnameError(String),
+
+    #[error("Invalid ping options: {0}")]
+    InvalidOptions(String),
 }
 
 pub fn get_pinger(options: PingOptions) -> std::result::Result<Arc<dyn Pinger>, PingCreationError> {
+    if let Some(path) = &options.ping_binary {
+        if path.to_str().is_none() {
+            return Err(PingCreationError::InvalidOptions(format!(
+                "ping binary path {path:?} is not valid UTF-8"
+            )));
+        }
+    }
+    if let Target::DualStack { .. } = options.target {
+        return Err(PingCreationError::InvalidOptions(
+            "dual stack targets must be pinged with ping_dual_stack".to_string(),
+        ));
+    }
+
+    if options.ssh.is_some() {
+        return Ok(Arc::new(ssh::SshPinger::from_options(options)?));
+    }
+
+    if let Target::Hostname { domain, .. } = &options.target {
+        if ntp::is_ntp_url(domain) {
+            return Ok(Arc::new(ntp::NtpPinger::from_options(options)?));
+        }
+    }
+
+    #[cfg(feature = "websocket")]
+    if let Target::Hostname { domain, .. } = &options.target {
+        if websocket::is_websocket_url(domain) {
+            return Ok(Arc::new(websocket::WebSocketPinger::from_options(options)?));
+        }
+    }
+
     #[cfg(feature = "fake-ping")]
     if std::env::var("PINGER_FAKE_PING")
         .map(|e| e == "1")
@@ -210,7 +521,9 @@ pub fn get_pinger(options: PingOptions) -> std::result::Result<Arc<dyn Pinger>,
     }
     #[cfg(unix)]
     {
-        if cfg!(target_os = "freebsd")
+        if cfg!(target_os = "aix") {
+            Ok(Arc::new(aix::AixPinger::from_options(options)?))
+        } else if cfg!(target_os = "freebsd")
             || cfg!(target_os = "dragonfly")
             || cfg!(target_os = "openbsd")
             || cfg!(target_os = "netbsd")
@@ -219,15 +532,129 @@ pub fn get_pinger(options: PingOptions) -> std::result::Result<Arc<dyn Pinger>,
         } else if cfg!(target_os = "macos") {
             Ok(Arc::new(macos::MacOSPinger::from_options(options)?))
         } else {
-            Ok(Arc::new(LinuxPinger::from_options(options)?))
+            match LinuxPinger::from_options(options.clone()) {
+                Ok(pinger) => Ok(Arc::new(pinger)),
+                // Fall back to a permissive parser rather than refusing to run at all
+                Err(PingCreationError::UnknownPing { .. }) => {
+                    Ok(Arc::new(generic::GenericPinger::from_options(options)?))
+                }
+                Err(e) => Err(e),
+            }
         }
     }
 }
 
 /// Start pinging a an address. The address can be either a hostname or an IP address.
-pub fn ping(
-    options: PingOptions,
-) -> std::result::Result<mpsc::Receiver<PingResult>, PingCreationError> {
+pub fn ping(options: PingOptions) -> std::result::Result<PingStream, PingCreationError> {
+    let target = options.target.clone();
+    let observers = options.observers.clone();
+    let stream = start(options)?;
+    if observers.is_empty() {
+        Ok(stream)
+    } else {
+        Ok(stream.observed(target, observers))
+    }
+}
+
+fn start(options: PingOptions) -> std::result::Result<PingStream, PingCreationError> {
+    let reresolving = options.reresolve.is_some() && options.ssh.is_none();
+    if options.restart || (reresolving && matches!(options.target, Target::Hostname { .. })) {
+        return supervisor::supervise(options);
+    }
+    start_once(options)
+}
+
+/// Start a single ping, without restarting or re-resolving it.
+pub(crate) fn start_once(
+    mut options: PingOptions,
+) -> std::result::Result<PingStream, PingCreationError> {
+    // Resolve hostnames here rather than leaving it to ping, so that the destination is known and
+    // other resolvers can be used. Over SSH the remote host resolves the target itself, and
+    // URL targets are resolved by their backend.
+    let resolvable = match &options.target {
+        Target::Hostname { domain, .. } if ntp::is_ntp_url(domain) => false,
+        #[cfg(feature = "websocket")]
+        Target::Hostname { domain, .. } if websocket::is_websocket_url(domain) => false,
+        Target::Hostname { .. } => options.ssh.is_none(),
+        _ => false,
+    };
+    if resolvable {
+        match supervisor::resolve(&options) {
+            Ok(ip) => options.target = Target::IP(ip),
+            // ping may still be able to resolve names we can't, such as internationalised ones.
+            Err(_) if options.resolver == Resolver::System => {}
+            Err(e) => return Err(e),
+        }
+    }
+    let destination = match options.target {
+        Target::IP(ip) => Some(ip),
+        _ => None,
+    };
     let pinger = get_pinger(options)?;
-    pinger.start()
+    Ok(pinger.start()?.with_destination(destination))
+}
+
+/// Start pinging several targets at once. Results are tagged with the index of the options they
+/// came from and the address that was pinged, if it is known (see [`PingStream::destination`]).
+/// All targets are started before this returns, so an error from any of them is reported upfront.
+/// Pinging stops once the receiver is dropped.
+pub fn ping_many(
+    options: Vec<PingOptions>,
+) -> std::result::Result<mpsc::Receiver<(usize, Option<IpAddr>, PingResult)>, PingCreationError> {
+    let streams = options
+        .into_iter()
+        .map(ping)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (tx, rx) = mpsc::channel();
+    for (idx, stream) in streams.into_iter().enumerate() {
+        let tx = tx.clone();
+        thread::spawn(move || {
+            while let Ok(result) = stream.recv() {
+                if tx.send((idx, stream.destination(), result)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    Ok(rx)
+}
+
+/// Ping the IPv4 and IPv6 addresses of a target at the same time, tagging each result with the
+/// IP version it came from. If the target only resolves to one family, only that family is pinged.
+pub fn ping_dual_stack(
+    options: PingOptions,
+) -> std::result::Result<mpsc::Receiver<(IPVersion, PingResult)>, PingCreationError> {
+    let (ipv4, ipv6) = options
+        .target
+        .resolve_dual_stack(&options.resolver)
+        .map_err(|_| PingCreationError::HostnameError(options.target.to_string()))?;
+    let mut versions = vec![];
+    let mut all_options = vec![];
+    for (version, ip) in [
+        (IPVersion::V4, ipv4.map(IpAddr::V4)),
+        (IPVersion::V6, ipv6.map(IpAddr::V6)),
+    ] {
+        if let Some(ip) = ip {
+            versions.push(version);
+            all_options.push(PingOptions {
+                target: Target::IP(ip),
+                ..options.clone()
+            });
+        }
+    }
+    if all_options.is_empty() {
+        return Err(PingCreationError::HostnameError(options.target.to_string()));
+    }
+
+    let results = ping_many(all_options)?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for (idx, _, result) in results {
+            if tx.send((versions[idx], result)).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(rx)
 }
@@ -1,7 +1,20 @@
-use crate::{extract_regex, run_ping, PingCreationError, PingOptions, PingResult, Pinger};
+use crate::locale::parse_localized;
+use crate::{
+    extract_regex, run_ping, start_subprocess, PingCreationError, PingFlag, PingFlags, PingOptions,
+    PingResult, PingStream, Pinger, SubprocessPinger, TIMESTAMP_RE,
+};
 use lazy_regex::*;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
-pub static UBUNTU_RE: Lazy<Regex> = lazy_regex!(r"(?i-u)time=(?P<ms>\d+)(?:\.(?P<ns>\d+))? *ms");
+pub static UBUNTU_RE: Lazy<Regex> = lazy_regex!(r"(?i-u)time=(?P<ms>\d+)(?:[.,](?P<ns>\d+))? *ms");
+
+const TERMUX_PING: &str = "/data/data/com.termux/files/usr/bin/ping";
+const ANDROID_PING: &str = "/system/bin/ping";
+const ANDROID_PING6: &str = "/system/bin/ping6";
+// Android refuses intervals below 200ms for unprivileged users.
+const ANDROID_MIN_INTERVAL: Duration = Duration::from_millis(200);
 
 #[derive(Debug)]
 pub enum LinuxPinger {
@@ -9,11 +22,115 @@ pub enum LinuxPinger {
     BusyBox(PingOptions),
     // Debian, Ubuntu, etc
     IPTools(PingOptions),
+    // Android and Termux, which ship an old iputils outside of $PATH
+    Android(PingOptions),
+}
+
+/// Find the ping binary on Android, preferring one installed by Termux.
+fn android_ping_path(ipv6: bool) -> Option<&'static str> {
+    let candidates: &[&'static str] = if ipv6 {
+        &[ANDROID_PING6]
+    } else {
+        &[TERMUX_PING, ANDROID_PING]
+    };
+    candidates
+        .iter()
+        .copied()
+        .find(|path| Path::new(path).exists())
+}
+
+fn is_android() -> bool {
+    cfg!(target_os = "android")
+        || std::env::var_os("TERMUX_VERSION").is_some()
+        || Path::new("/system/bin/app_process").exists()
+}
+
+const IPUTILS_FLAGS: &[PingFlag] = &[
+    PingFlag::DontFragment,
+    PingFlag::Adaptive,
+    PingFlag::Audible,
+    PingFlag::Numeric,
+    PingFlag::Mark,
+    PingFlag::Timestamps,
+];
+// BusyBox never resolves reply addresses, so numeric is accepted but needs no argument.
+const BUSYBOX_FLAGS: &[PingFlag] = &[PingFlag::Adaptive, PingFlag::Numeric];
+
+/// iputils before 20190324 only accepts whole seconds for -W.
+fn iputils_timeout_args(timeout: Option<Duration>) -> Vec<String> {
+    match timeout {
+        Some(timeout) => {
+            let seconds = timeout.as_secs_f32().ceil().max(1.0) as u64;
+            vec!["-W".to_string(), seconds.to_string()]
+        }
+        None => vec![],
+    }
+}
+
+/// iputils takes a single -I, either an interface name or a source address. The source address
+/// is the more specific of the two, so it wins when both are given, as it does on Windows.
+fn iputils_bind_args(options: &PingOptions) -> Vec<String> {
+    match (&options.source, &options.interface) {
+        (Some(source), _) => vec!["-I".to_string(), source.to_string()],
+        (None, Some(interface)) => vec!["-I".to_string(), interface.clone()],
+        (None, None) => vec![],
+    }
+}
+
+fn iputils_flag_args(flags: &PingFlags) -> Vec<String> {
+    let mut args = vec![];
+    if flags.dont_fragment {
+        args.extend(["-M".to_string(), "do".to_string()]);
+    }
+    if flags.adaptive {
+        args.push("-A".into());
+    }
+    if flags.audible {
+        args.push("-a".into());
+    }
+    if flags.numeric {
+        args.push("-n".into());
+    }
+    if let Some(mark) = flags.mark {
+        args.extend(["-m".to_string(), mark.to_string()]);
+    }
+    if flags.timestamps {
+        args.push("-D".into());
+    }
+    args
 }
 
 impl LinuxPinger {
+    fn options(&self) -> &PingOptions {
+        match self {
+            LinuxPinger::BusyBox(options)
+            | LinuxPinger::IPTools(options)
+            | LinuxPinger::Android(options) => options,
+        }
+    }
+
+    pub(crate) fn ensure_flags_supported(&self) -> Result<(), PingCreationError> {
+        let flags = &self.options().flags;
+        match self {
+            LinuxPinger::BusyBox(_) => flags.ensure_supported("BusyBox", BUSYBOX_FLAGS),
+            LinuxPinger::IPTools(_) | LinuxPinger::Android(_) => {
+                flags.ensure_supported("iputils", IPUTILS_FLAGS)
+            }
+        }
+    }
+
     pub fn detect_platform_ping(options: PingOptions) -> Result<Self, PingCreationError> {
-        let child = run_ping("ping", vec!["-V".to_string()])?;
+        if options.ping_binary.is_none() && is_android() {
+            if let Some(path) = android_ping_path(false) {
+                let output = run_ping(path, vec!["-V".to_string()])?.wait_with_output()?;
+                // Termux can also provide BusyBox or inetutils ping, which are detected below.
+                if String::from_utf8_lossy(&output.stdout).contains("iputils") {
+                    return Ok(LinuxPinger::Android(options));
+                }
+            }
+        }
+
+        let child = run_ping(options.ping_command("ping"), vec!["-V".to_string()])?;
         let output = child.wait_with_output()?;
         let stdout = String::from_utf8(output.stdout).expect("Error decoding ping stdout");
         let stderr = String::from_utf8(output.stderr).expect("Error decoding ping stderr");
@@ -44,19 +161,41 @@ impl Pinger for LinuxPinger {
     where
         Self: Sized,
     {
-        Self::detect_platform_ping(options)
+        let pinger = Self::detect_platform_ping(options)?;
+        pinger.ensure_flags_supported()?;
+        Ok(pinger)
+    }
+
+    fn start(&self) -> Result<PingStream, PingCreationError> {
+        start_subprocess(self)
+    }
+
+    fn with_options(&self, options: PingOptions) -> Result<Arc<dyn Pinger>, PingCreationError> {
+        let pinger = match self {
+            LinuxPinger::BusyBox(_) => LinuxPinger::BusyBox(options),
+            LinuxPinger::IPTools(_) => LinuxPinger::IPTools(options),
+            LinuxPinger::Android(_) => LinuxPinger::Android(options),
+        };
+        pinger.ensure_flags_supported()?;
+        Ok(Arc::new(pinger))
     }
+}
 
+impl SubprocessPinger for LinuxPinger {
     fn parse_fn(&self) -> fn(String) -> Option<PingResult> {
         |line| {
             #[cfg(test)]
             eprintln!("Got line {line}");
-            if line.starts_with("64 bytes from") {
+            // Skip the timestamp added by -D, which extract_regex parses from the full line.
+            let body = TIMESTAMP_RE
+                .find(&line)
+                .map_or(line.as_str(), |prefix| &line[prefix.end()..]);
+            if body.starts_with("64 bytes from") {
                 return extract_regex(&UBUNTU_RE, line);
-            } else if line.starts_with("no answer yet") {
+            } else if body.starts_with("no answer yet") {
                 return Some(PingResult::Timeout(line));
             }
-            None
+            parse_localized(line)
         }
     }
 
@@ -64,16 +203,23 @@ impl Pinger for LinuxPinger {
         match self {
             // Alpine doesn't support timeout notifications, so we don't add the -O flag here.
             LinuxPinger::BusyBox(options) => {
-                let cmd = if options.target.is_ipv6() {
+                let cmd = options.ping_command(if options.target.is_ipv6() {
                     "ping6"
                 } else {
                     "ping"
-                };
+                });
 
                 let mut args = vec![
                     options.target.to_string(),
                     format!("-i{:.1}", options.interval.as_millis() as f32 / 1_000_f32),
                 ];
+                if let Some(source) = &options.source {
+                    args.push("-I".into());
+                    args.push(source.to_string());
+                }
+                if options.flags.adaptive {
+                    args.push("-A".into());
+                }
 
                 if let Some(raw_args) = &options.raw_arguments {
                     args.extend(raw_args.iter().cloned());
@@ -82,11 +228,11 @@ impl Pinger for LinuxPinger {
                 (cmd, args)
             }
             LinuxPinger::IPTools(options) => {
-                let cmd = if options.target.is_ipv6() {
+                let cmd = options.ping_command(if options.target.is_ipv6() {
                     "ping6"
                 } else {
                     "ping"
-                };
+                });
 
                 // The -O flag ensures we "no answer yet" messages from ping
                 // See https://superuser.com/questions/270083/linux-ping-show-time-out
@@ -94,10 +240,32 @@ impl Pinger for LinuxPinger {
                     "-O".to_string(),
                     format!("-i{:.1}", options.interval.as_millis() as f32 / 1_000_f32),
                 ];
-                if let Some(interface) = &options.interface {
-                    args.push("-I".into());
-                    args.push(interface.clone());
+                args.extend(iputils_bind_args(options));
+                args.extend(iputils_timeout_args(options.timeout));
+                args.extend(iputils_flag_args(&options.flags));
+                if let Some(raw_args) = &options.raw_arguments {
+                    args.extend(raw_args.iter().cloned());
                 }
+
+                args.push(options.target.to_string());
+                (cmd, args)
+            }
+            // Android's ping is iputils, but it doesn't live on $PATH under Termux and
+            // doesn't allow short intervals or -O without root.
+            LinuxPinger::Android(options) => {
+                let default = if options.target.is_ipv6() {
+                    "ping6"
+                } else {
+                    "ping"
+                };
+                let cmd = options
+                    .ping_command(android_ping_path(options.target.is_ipv6()).unwrap_or(default));
+                let interval = options.interval.max(ANDROID_MIN_INTERVAL);
+
+                let mut args = vec![format!("-i{:.1}", interval.as_millis() as f32 / 1_000_f32)];
+                args.extend(iputils_bind_args(options));
+                args.extend(iputils_timeout_args(options.timeout));
+                args.extend(iputils_flag_args(&options.flags));
                 if let Some(raw_args) = &options.raw_arguments {
                     args.extend(raw_args.iter().cloned());
                 }
@@ -134,4 +302,18 @@ mod tests {
             _ => {}
         }
     }
+
+    #[test]
+    fn test_with_options() {
+        use super::*;
+
+        let options = PingOptions::new("10.0.0.1".to_string(), Duration::from_secs(1), None);
+        let mut audible = options.clone();
+        audible.flags.audible = true;
+        // The detected ping is kept, so BusyBox still rejects flags that only iputils supports.
+        assert!(LinuxPinger::IPTools(options.clone())
+            .with_options(audible.clone())
+            .is_ok());
+        assert!(LinuxPinger::BusyBox(options).with_options(audible).is_err());
+    }
 }
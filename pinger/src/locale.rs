@@ -0,0 +1,38 @@
+use crate::{extract_regex, parse_icmp_error, PingResult};
+use lazy_regex::*;
+
+/// Matches the round trip time in localized output, such as "Zeit=12,3 ms" from a German iputils
+/// or "temps=12 ms" from a French Windows. The decimal separator is taken from the line itself
+/// rather than the locale, as not every ping honours `LC_ALL=C`.
+pub(crate) static TIME_RE: Lazy<Regex> = lazy_regex!(
+    r"(?i)\b(?:time|zeit|temps|tiempo|tempo|tijd|czas|tid|aika|čas|süre|время|時間|时间)\s*[=<]\s*(?P<ms>\d+)(?:[.,](?P<ns>\d+))?\s*(?:ms|мс)"
+);
+
+/// Translations of the "Request timed out" message printed by Windows and some Linux distributions.
+const TIMEOUT_PHRASES: &[&str] = &[
+    "zeitüberschreitung",
+    "délai d'attente",
+    "tiempo de espera agotado",
+    "esgotado o tempo limite",
+    "richiesta scaduta",
+    "time-out",
+    "upłynął limit czasu",
+    "превышен интервал ожидания",
+    "要求がタイムアウト",
+    "请求超时",
+];
+
+/// Fallback for lines that a platform's parser doesn't recognise, which may be translated.
+pub(crate) fn parse_localized(line: String) -> Option<PingResult> {
+    if TIME_RE.is_match(&line) {
+        return extract_regex(&TIME_RE, line);
+    }
+    let lowercase = line.to_lowercase();
+    if TIMEOUT_PHRASES
+        .iter()
+        .any(|phrase| lowercase.contains(phrase))
+    {
+        return Some(PingResult::Timeout(line));
+    }
+    parse_icmp_error(line)
+}
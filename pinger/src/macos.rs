@@ -1,5 +1,8 @@
-use crate::bsd::parse_bsd;
-use crate::{PingCreationError, PingOptions, PingResult, Pinger};
+use crate::bsd::{bsd_flag_args, parse_bsd, BSD_FLAGS};
+use crate::{
+    start_subprocess, PingCreationError, PingOptions, PingResult, PingStream, Pinger,
+    SubprocessPinger,
+};
 use lazy_regex::*;
 
 pub static RE: Lazy<Regex> = lazy_regex!(r"time=(?:(?P<ms>[0-9]+).(?P<ns>[0-9]+)\s+ms)");
@@ -13,19 +16,26 @@ impl Pinger for MacOSPinger {
     where
         Self: Sized,
     {
+        options.flags.ensure_supported("macOS", BSD_FLAGS)?;
         Ok(Self { options })
     }
 
+    fn start(&self) -> Result<PingStream, PingCreationError> {
+        start_subprocess(self)
+    }
+}
+
+impl SubprocessPinger for MacOSPinger {
     fn parse_fn(&self) -> fn(String) -> Option<PingResult> {
         parse_bsd
     }
 
     fn ping_args(&self) -> (&str, Vec<String>) {
-        let cmd = if self.options.target.is_ipv6() {
+        let cmd = self.options.ping_command(if self.options.target.is_ipv6() {
             "ping6"
         } else {
             "ping"
-        };
+        });
         let mut args = vec![
             format!(
                 "-i{:.1}",
@@ -37,6 +47,11 @@ impl Pinger for MacOSPinger {
             args.push("-b".into());
             args.push(interface.clone());
         }
+        if let Some(source) = &self.options.source {
+            args.push("-S".into());
+            args.push(source.to_string());
+        }
+        args.extend(bsd_flag_args(&self.options.flags));
 
         if let Some(raw_args) = &self.options.raw_arguments {
             args.extend(raw_args.iter().cloned());
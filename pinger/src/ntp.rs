@@ -0,0 +1,263 @@
+//! An SNTP client (RFC 4330), for measuring the delay to a time server and the offset of the local
+//! clock from it.
+use crate::target::{IPVersion, Target};
+use crate::{PingControl, PingCreationError, PingOptions, PingResult, PingStream, Pinger};
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const NTP_PORT: u16 = 123;
+
+// Seconds between the NTP epoch (1900) and the Unix epoch (1970).
+const NTP_EPOCH_OFFSET: f64 = 2_208_988_800.0;
+
+/// Whether a target is an NTP URL, such as `ntp://pool.ntp.org`, which is probed with [`NtpPinger`].
+pub(crate) fn is_ntp_url(target: &str) -> bool {
+    target.to_ascii_lowercase().starts_with("ntp://")
+}
+
+/// Split an NTP URL into its host and port, which defaults to 123.
+fn host_and_port(url: &str) -> (&str, u16) {
+    let authority = url[6..].trim_end_matches('/');
+    if let Some((host, port)) = authority.rsplit_once(':') {
+        // An unbracketed IPv6 address has colons but no port.
+        if let (false, Ok(port)) = (host.contains(':') && !host.ends_with(']'), port.parse()) {
+            return (host.trim_start_matches('[').trim_end_matches(']'), port);
+        }
+    }
+    (
+        authority.trim_start_matches('[').trim_end_matches(']'),
+        NTP_PORT,
+    )
+}
+
+/// The current time as seconds since the NTP epoch.
+fn now() -> f64 {
+    let since_unix = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    since_unix.as_secs_f64() + NTP_EPOCH_OFFSET
+}
+
+fn to_timestamp(seconds: f64) -> [u8; 8] {
+    let secs = seconds.trunc() as u64 as u32;
+    let fraction = (seconds.fract() * 4_294_967_296.0) as u32;
+    let mut timestamp = [0; 8];
+    timestamp[..4].copy_from_slice(&secs.to_be_bytes());
+    timestamp[4..].copy_from_slice(&fraction.to_be_bytes());
+    timestamp
+}
+
+fn from_timestamp(bytes: &[u8]) -> f64 {
+    let secs = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let fraction = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+    secs as f64 + fraction as f64 / 4_294_967_296.0
+}
+
+/// Build a client request sent at `sent`: version 4, client mode.
+pub(crate) fn build_request(sent: f64) -> [u8; 48] {
+    let mut request = [0; 48];
+    request[0] = 0x23;
+    request[40..].copy_from_slice(&to_timestamp(sent));
+    request
+}
+
+/// Whether `reply` is a server reply to the request sent at `sent`, going by the originate
+/// timestamp that it echoes.
+fn is_reply_to(reply: &[u8], sent: f64) -> bool {
+    reply.len() >= 48 && reply[0] & 0x07 == 4 && reply[24..32] == to_timestamp(sent)
+}
+
+/// Parse the server's reply to a request sent at `sent` and received at `received`, returning the
+/// round trip delay and the clock offset in seconds, as `(delay, offset, stratum)`.
+pub(crate) fn parse_reply(reply: &[u8], sent: f64, received: f64) -> io::Result<(f64, f64, u8)> {
+    let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidData, reason.to_string());
+    if reply.len() < 48 || reply[0] & 0x07 != 4 {
+        return Err(invalid("not an NTP server reply"));
+    }
+    // The originate timestamp echoes the request, which rejects stray and spoofed replies.
+    if reply[24..32] != to_timestamp(sent) {
+        return Err(invalid("NTP reply does not match the request"));
+    }
+    let stratum = reply[1];
+    if stratum == 0 {
+        let code = String::from_utf8_lossy(&reply[12..16]).into_owned();
+        return Err(invalid(&format!("NTP server refused the request ({code})")));
+    }
+    let server_received = from_timestamp(&reply[32..40]);
+    let server_sent = from_timestamp(&reply[40..48]);
+    let delay = (received - sent) - (server_sent - server_received);
+    let offset = ((server_received - sent) + (server_sent - received)) / 2.0;
+    Ok((delay.max(0.0), offset, stratum))
+}
+
+/// Queries an NTP server every interval, for `ntp://host[:port]` targets. Each reply is reported as
+/// a [`PingResult::NtpReply`], with the round trip delay and the offset of the local clock.
+pub struct NtpPinger {
+    host: String,
+    port: u16,
+    options: PingOptions,
+}
+
+impl NtpPinger {
+    fn server(&self) -> Result<SocketAddr, PingCreationError> {
+        let target = match self.options.target {
+            Target::Hostname { version, .. } => match version {
+                IPVersion::V4 => Target::new_ipv4(&self.host),
+                IPVersion::V6 => Target::new_ipv6(&self.host),
+                IPVersion::Any => Target::new_any(&self.host),
+            },
+            _ => Target::new_any(&self.host),
+        };
+        let ip = target
+            .resolve(&self.options.resolver)
+            .ok()
+            .flatten()
+            .ok_or_else(|| PingCreationError::HostnameError(self.host.clone()))?;
+        Ok(SocketAddr::new(ip, self.port))
+    }
+
+    fn probe(socket: &UdpSocket, server: SocketAddr, timeout: Duration) -> PingResult {
+        let sent = now();
+        let deadline = Instant::now() + timeout;
+        let mut buffer = [0; 48];
+        let reply = socket.send(&build_request(sent)).and_then(|_| loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break Err(io::ErrorKind::TimedOut.into());
+            }
+            socket.set_read_timeout(Some(remaining))?;
+            let len = socket.recv(&mut buffer)?;
+            // A late reply to an earlier request doesn't answer this one, so keep waiting.
+            if is_reply_to(&buffer[..len], sent) {
+                break parse_reply(&buffer[..len], sent, now());
+            }
+        });
+        match reply {
+            Ok((delay, offset, stratum)) => {
+                let delay = Duration::from_secs_f64(delay);
+                let line = format!(
+                    "reply from {server}: stratum={stratum} delay={delay:?} offset={offset:+.6}s"
+                );
+                PingResult::NtpReply(delay, offset, line)
+            }
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                PingResult::Timeout(format!("no reply from {server}"))
+            }
+            Err(e) => PingResult::Unknown(format!("{server}: {e}")),
+        }
+    }
+}
+
+impl Pinger for NtpPinger {
+    fn from_options(options: PingOptions) -> Result<Self, PingCreationError>
+    where
+        Self: Sized,
+    {
+        let (host, port) = match &options.target {
+            Target::Hostname { domain, .. } if is_ntp_url(domain) => host_and_port(domain),
+            target => {
+                return Err(PingCreationError::InvalidOptions(format!(
+                    "{target} is not an ntp:// URL"
+                )))
+            }
+        };
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            options,
+        })
+    }
+
+    fn start(&self) -> Result<PingStream, PingCreationError> {
+        let server = self.server()?;
+        let bind: SocketAddr = match (self.options.source, server) {
+            (Some(source), _) => (source, 0).into(),
+            (None, SocketAddr::V4(_)) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            (None, SocketAddr::V6(_)) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = UdpSocket::bind(bind)?;
+        socket.connect(server)?;
+        let interval = self.options.interval;
+        let timeout = self.options.timeout.unwrap_or(interval);
+
+        let (tx, rx) = mpsc::channel();
+        let control = Arc::new(PingControl::default());
+        let thread_control = control.clone();
+
+        thread::spawn(move || {
+            while !thread_control.is_stopped() {
+                let started = Instant::now();
+                if !thread_control.is_paused()
+                    && tx.send(Self::probe(&socket, server, timeout)).is_err()
+                {
+                    break;
+                }
+                thread::sleep(interval.saturating_sub(started.elapsed()));
+            }
+        });
+
+        Ok(PingStream::new(rx, control).with_destination(Some(server.ip())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reply() {
+        let sent = 3_900_000_000.25;
+        let request = build_request(sent);
+        let mut reply = request;
+        reply[0] = 0x24;
+        reply[1] = 2;
+        reply[24..32].copy_from_slice(&request[40..48]);
+        // The server clock is 1.5s ahead, and it took 10ms to answer. The network took 40ms.
+        reply[32..40].copy_from_slice(&to_timestamp(sent + 1.52));
+        reply[40..48].copy_from_slice(&to_timestamp(sent + 1.53));
+        let (delay, offset, stratum) = parse_reply(&reply, sent, sent + 0.05).unwrap();
+        assert!((delay - 0.04).abs() < 1e-5, "delay was {}", delay);
+        assert!((offset - 1.5).abs() < 1e-5, "offset was {}", offset);
+        assert_eq!(stratum, 2);
+
+        assert!(parse_reply(&reply, sent + 1.0, sent + 0.05).is_err());
+        assert!(parse_reply(&request, sent, sent + 0.05).is_err());
+    }
+
+    #[test]
+    fn test_probe_skips_late_replies() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client.connect(server.local_addr().unwrap()).unwrap();
+        thread::spawn(move || {
+            let mut request = [0; 48];
+            let (_, client) = server.recv_from(&mut request).unwrap();
+            let mut reply = [0; 48];
+            reply[0] = 0x24;
+            reply[1] = 2;
+            // A reply to an earlier request arrives first.
+            reply[24..32].copy_from_slice(&to_timestamp(from_timestamp(&request[40..48]) - 1.0));
+            server.send_to(&reply, client).unwrap();
+            reply[24..32].copy_from_slice(&request[40..48]);
+            server.send_to(&reply, client).unwrap();
+        });
+        let result = NtpPinger::probe(&client, client.peer_addr().unwrap(), Duration::from_secs(5));
+        assert!(matches!(result, PingResult::NtpReply(..)), "{:?}", result);
+    }
+
+    #[test]
+    fn test_host_and_port() {
+        assert_eq!(host_and_port("ntp://pool.ntp.org"), ("pool.ntp.org", 123));
+        assert_eq!(host_and_port("ntp://10.0.0.1:1123/"), ("10.0.0.1", 1123));
+        assert_eq!(host_and_port("ntp://[::1]:1123"), ("::1", 1123));
+        assert_eq!(host_and_port("ntp://::1"), ("::1", 123));
+    }
+}
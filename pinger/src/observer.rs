@@ -0,0 +1,70 @@
+use crate::target::Target;
+use crate::{PingResult, PingSummary};
+use std::fmt::Debug;
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Called with every result as it is produced, for building exporters on top of the library
+/// without consuming the stream. Register observers with [`PingOptions::with_observer`].
+///
+/// Observers are called from a background thread, so should return quickly.
+///
+/// [`PingOptions::with_observer`]: crate::PingOptions::with_observer
+pub trait ResultObserver: Debug + Send + Sync {
+    /// Called with every result. By default this calls the more specific methods below.
+    fn on_result(&self, target: &Target, result: &PingResult) {
+        match result {
+            PingResult::Pong(duration, ..) | PingResult::NtpReply(duration, ..) => {
+                self.on_pong(target, *duration)
+            }
+            PingResult::Timeout(_) => self.on_timeout(target),
+            PingResult::PingExited(status, _, summary) => {
+                self.on_exit(target, status, summary.as_ref())
+            }
+            _ => {}
+        }
+    }
+
+    fn on_pong(&self, _target: &Target, _duration: Duration) {}
+
+    fn on_timeout(&self, _target: &Target) {}
+
+    fn on_exit(&self, _target: &Target, _status: &ExitStatus, _summary: Option<&PingSummary>) {}
+}
+
+/// Counts the results it observes. Share it between targets to get totals across all of them.
+#[derive(Debug, Default)]
+pub struct CountingObserver {
+    pongs: AtomicU64,
+    timeouts: AtomicU64,
+    exits: AtomicU64,
+}
+
+impl CountingObserver {
+    pub fn pongs(&self) -> u64 {
+        self.pongs.load(Ordering::Relaxed)
+    }
+
+    pub fn timeouts(&self) -> u64 {
+        self.timeouts.load(Ordering::Relaxed)
+    }
+
+    pub fn exits(&self) -> u64 {
+        self.exits.load(Ordering::Relaxed)
+    }
+}
+
+impl ResultObserver for CountingObserver {
+    fn on_pong(&self, _target: &Target, _duration: Duration) {
+        self.pongs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_timeout(&self, _target: &Target) {
+        self.timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_exit(&self, _target: &Target, _status: &ExitStatus, _summary: Option<&PingSummary>) {
+        self.exits.fetch_add(1, Ordering::Relaxed);
+    }
+}
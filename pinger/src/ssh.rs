@@ -0,0 +1,111 @@
+use crate::aix::AixPinger;
+use crate::bsd::BSDPinger;
+use crate::generic::GenericPinger;
+use crate::linux::LinuxPinger;
+use crate::macos::MacOSPinger;
+use crate::{
+    run_ping, start_subprocess, PingCreationError, PingOptions, PingResult, PingStream, Pinger,
+    SubprocessPinger,
+};
+
+/// Runs ping on a remote host over SSH, so latency is measured from that host. The remote ping is
+/// detected when the pinger is created, and its output is parsed by the matching local backend.
+///
+/// SSH runs in batch mode, so the host must be reachable without a password prompt.
+pub struct SshPinger {
+    pub(crate) host: String,
+    pub(crate) remote: Box<dyn SubprocessPinger>,
+}
+
+// Arguments that prevent ssh from prompting or allocating a terminal, which would corrupt the output.
+const SSH_ARGS: &[&str] = &["-T", "-o", "BatchMode=yes"];
+
+/// Quote a word for the remote shell, which ssh passes the command to.
+fn shell_quote(word: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c);
+    if !word.is_empty() && word.chars().all(safe) {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+fn detect_remote(
+    host: &str,
+    options: PingOptions,
+) -> Result<Box<dyn SubprocessPinger>, PingCreationError> {
+    let probe = format!(
+        "uname -s; {} -V 2>&1",
+        shell_quote(options.ping_command("ping"))
+    );
+    let mut args: Vec<&str> = SSH_ARGS.to_vec();
+    args.extend([host, "--", &probe]);
+    let output = run_ping("ssh", args)?.wait_with_output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    let Some(os) = lines.next().map(str::trim) else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(PingCreationError::UnknownPing {
+            stderr: stderr.lines().take(2).map(str::to_string).collect(),
+            stdout: vec![],
+        });
+    };
+    let version = lines.collect::<Vec<_>>().join("\n");
+
+    Ok(match os {
+        "Linux" => {
+            let pinger = if version.contains("BusyBox") {
+                LinuxPinger::BusyBox(options)
+            } else {
+                LinuxPinger::IPTools(options)
+            };
+            pinger.ensure_flags_supported()?;
+            Box::new(pinger)
+        }
+        "Darwin" => Box::new(MacOSPinger::from_options(options)?),
+        "FreeBSD" | "DragonFly" | "OpenBSD" | "NetBSD" => {
+            Box::new(BSDPinger::from_options(options)?)
+        }
+        "AIX" => Box::new(AixPinger::from_options(options)?),
+        _ => Box::new(GenericPinger::from_options(options)?),
+    })
+}
+
+impl Pinger for SshPinger {
+    fn from_options(options: PingOptions) -> Result<Self, PingCreationError>
+    where
+        Self: Sized,
+    {
+        let host = options.ssh.clone().ok_or_else(|| {
+            PingCreationError::InvalidOptions("no SSH host was given".to_string())
+        })?;
+        let remote = detect_remote(&host, options)?;
+        Ok(Self { host, remote })
+    }
+
+    fn start(&self) -> Result<PingStream, PingCreationError> {
+        start_subprocess(self)
+    }
+}
+
+impl SubprocessPinger for SshPinger {
+    fn parse_fn(&self) -> fn(String) -> Option<PingResult> {
+        self.remote.parse_fn()
+    }
+
+    fn ping_args(&self) -> (&str, Vec<String>) {
+        let (cmd, args) = self.remote.ping_args();
+        // Force the remote ping to print untranslated output, as run_ping does locally.
+        let command = ["env", "LANG=C", "LC_ALL=C", cmd]
+            .iter()
+            .copied()
+            .chain(args.iter().map(String::as_str))
+            .map(shell_quote)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut ssh_args: Vec<String> = SSH_ARGS.iter().map(|arg| arg.to_string()).collect();
+        ssh_args.extend([self.host.clone(), "--".to_string(), command]);
+        ("ssh", ssh_args)
+    }
+}
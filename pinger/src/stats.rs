@@ -0,0 +1,333 @@
+use crate::PingResult;
+use std::time::Duration;
+
+/// Running statistics over a stream of ping results.
+///
+/// Every update is O(1): the median and 95th percentile are estimated using the P² algorithm
+/// rather than by keeping every sample around.
+#[derive(Debug, Clone)]
+pub struct PingStats {
+    received: u64,
+    timeouts: u64,
+    duplicates: u64,
+    errors: u64,
+    last: Option<f64>,
+    min: f64,
+    max: f64,
+    sum: f64,
+    jitter_sum: f64,
+    median: P2Quantile,
+    p95: P2Quantile,
+}
+
+/// A point-in-time copy of the values held by a [`PingStats`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PingStatsSnapshot {
+    pub sent: u64,
+    pub received: u64,
+    pub timeouts: u64,
+    pub duplicates: u64,
+    pub errors: u64,
+    /// Fraction of probes that timed out or were answered with an ICMP error, between 0 and 1.
+    pub loss: f64,
+    pub last: Option<Duration>,
+    pub min: Option<Duration>,
+    pub max: Option<Duration>,
+    pub avg: Option<Duration>,
+    pub median: Option<Duration>,
+    pub p95: Option<Duration>,
+    pub jitter: Option<Duration>,
+}
+
+impl Default for PingStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PingStats {
+    pub fn new() -> Self {
+        Self {
+            received: 0,
+            timeouts: 0,
+            duplicates: 0,
+            errors: 0,
+            last: None,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            sum: 0.0,
+            jitter_sum: 0.0,
+            median: P2Quantile::new(0.5),
+            p95: P2Quantile::new(0.95),
+        }
+    }
+
+    /// Record a result. Unknown lines and exits are ignored.
+    pub fn update(&mut self, result: &PingResult) {
+        match result {
+            PingResult::Pong(duration, ..) | PingResult::NtpReply(duration, ..) => {
+                self.record_pong(*duration)
+            }
+            PingResult::Timeout(_) => self.record_timeout(),
+            PingResult::Duplicate(..) => self.record_duplicate(),
+            PingResult::Unreachable(_) | PingResult::TtlExceeded(_) | PingResult::Filtered(_) => {
+                self.record_error()
+            }
+            _ => {}
+        }
+    }
+
+    pub fn record_pong(&mut self, duration: Duration) {
+        let value = duration.as_secs_f64();
+        if let Some(last) = self.last {
+            self.jitter_sum += (value - last).abs();
+        }
+        self.received += 1;
+        self.last = Some(value);
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+        self.median.add(value);
+        self.p95.add(value);
+    }
+
+    pub fn record_timeout(&mut self) {
+        self.timeouts += 1;
+    }
+
+    /// Duplicate replies aren't counted as received, as they answer a probe that was already answered.
+    pub fn record_duplicate(&mut self) {
+        self.duplicates += 1;
+    }
+
+    /// A probe answered with an ICMP error, such as the destination being unreachable.
+    pub fn record_error(&mut self) {
+        self.errors += 1;
+    }
+
+    pub fn sent(&self) -> u64 {
+        self.received + self.timeouts + self.errors
+    }
+
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+
+    pub fn timeouts(&self) -> u64 {
+        self.timeouts
+    }
+
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+
+    /// Fraction of probes that timed out or were answered with an ICMP error, between 0 and 1.
+    pub fn loss(&self) -> f64 {
+        match self.sent() {
+            0 => 0.0,
+            sent => (self.timeouts + self.errors) as f64 / sent as f64,
+        }
+    }
+
+    pub fn last(&self) -> Option<Duration> {
+        self.last.map(Duration::from_secs_f64)
+    }
+
+    pub fn min(&self) -> Option<Duration> {
+        self.if_received(self.min)
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.if_received(self.max)
+    }
+
+    pub fn avg(&self) -> Option<Duration> {
+        self.if_received(self.sum / self.received as f64)
+    }
+
+    pub fn median(&self) -> Option<Duration> {
+        self.median.value().map(Duration::from_secs_f64)
+    }
+
+    pub fn p95(&self) -> Option<Duration> {
+        self.p95.value().map(Duration::from_secs_f64)
+    }
+
+    /// The mean difference between consecutive round trip times.
+    pub fn jitter(&self) -> Option<Duration> {
+        if self.received < 2 {
+            return None;
+        }
+        Some(Duration::from_secs_f64(
+            self.jitter_sum / (self.received - 1) as f64,
+        ))
+    }
+
+    pub fn snapshot(&self) -> PingStatsSnapshot {
+        PingStatsSnapshot {
+            sent: self.sent(),
+            received: self.received,
+            timeouts: self.timeouts,
+            duplicates: self.duplicates,
+            errors: self.errors,
+            loss: self.loss(),
+            last: self.last(),
+            min: self.min(),
+            max: self.max(),
+            avg: self.avg(),
+            median: self.median(),
+            p95: self.p95(),
+            jitter: self.jitter(),
+        }
+    }
+
+    fn if_received(&self, value: f64) -> Option<Duration> {
+        if self.received == 0 {
+            None
+        } else {
+            Some(Duration::from_secs_f64(value))
+        }
+    }
+}
+
+/// Streaming quantile estimation using the P² algorithm (Jain & Chlamtac, 1985).
+/// Five markers are kept and adjusted on each observation, so memory and time are constant.
+#[derive(Debug, Clone)]
+struct P2Quantile {
+    count: usize,
+    heights: [f64; 5],
+    positions: [f64; 5],
+    desired: [f64; 5],
+    increments: [f64; 5],
+}
+
+impl P2Quantile {
+    fn new(p: f64) -> Self {
+        Self {
+            count: 0,
+            heights: [0.0; 5],
+            positions: [1.0, 2.0, 3.0, 4.0, 5.0],
+            desired: [1.0, 1.0 + 2.0 * p, 1.0 + 4.0 * p, 3.0 + 2.0 * p, 5.0],
+            increments: [0.0, p / 2.0, p, (1.0 + p) / 2.0, 1.0],
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        if self.count < 5 {
+            self.heights[self.count] = value;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(f64::total_cmp);
+            }
+            return;
+        }
+        self.count += 1;
+
+        let cell = if value < self.heights[0] {
+            self.heights[0] = value;
+            0
+        } else if value >= self.heights[4] {
+            self.heights[4] = value;
+            3
+        } else {
+            (1..5)
+                .find(|&i| value < self.heights[i])
+                .map(|i| i - 1)
+                .unwrap_or(3)
+        };
+
+        for position in &mut self.positions[cell + 1..] {
+            *position += 1.0;
+        }
+        for (desired, increment) in self.desired.iter_mut().zip(self.increments) {
+            *desired += increment;
+        }
+
+        for i in 1..4 {
+            let delta = self.desired[i] - self.positions[i];
+            if (delta >= 1.0 && self.positions[i + 1] - self.positions[i] > 1.0)
+                || (delta <= -1.0 && self.positions[i - 1] - self.positions[i] < -1.0)
+            {
+                let delta = delta.signum();
+                let parabolic = self.parabolic(i, delta);
+                self.heights[i] =
+                    if self.heights[i - 1] < parabolic && parabolic < self.heights[i + 1] {
+                        parabolic
+                    } else {
+                        self.linear(i, delta)
+                    };
+                self.positions[i] += delta;
+            }
+        }
+    }
+
+    fn parabolic(&self, i: usize, d: f64) -> f64 {
+        let (q, n) = (&self.heights, &self.positions);
+        q[i] + d / (n[i + 1] - n[i - 1])
+            * ((n[i] - n[i - 1] + d) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                + (n[i + 1] - n[i] - d) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]))
+    }
+
+    fn linear(&self, i: usize, d: f64) -> f64 {
+        let j = if d > 0.0 { i + 1 } else { i - 1 };
+        let (q, n) = (&self.heights, &self.positions);
+        q[i] + d * (q[j] - q[i]) / (n[j] - n[i])
+    }
+
+    fn value(&self) -> Option<f64> {
+        match self.count {
+            0 => None,
+            // Until the markers are initialised, compute the exact value from the samples seen.
+            count if count <= 5 => {
+                let mut seen = self.heights[..count].to_vec();
+                seen.sort_by(f64::total_cmp);
+                let p = self.increments[2];
+                Some(seen[((count - 1) as f64 * p).round() as usize])
+            }
+            _ => Some(self.heights[2]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_stats() {
+        let stats = PingStats::new();
+        assert_eq!(stats.min(), None);
+        assert_eq!(stats.p95(), None);
+        assert_eq!(stats.jitter(), None);
+        assert_eq!(stats.loss(), 0.0);
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut stats = PingStats::new();
+        for ms in 1..=100 {
+            stats.record_pong(Duration::from_millis(ms));
+        }
+        stats.record_timeout();
+        stats.update(&PingResult::Unreachable("unreachable".to_string()));
+
+        assert_eq!(stats.sent(), 102);
+        assert_eq!(stats.min(), Some(Duration::from_millis(1)));
+        assert_eq!(stats.max(), Some(Duration::from_millis(100)));
+        assert_eq!(stats.last(), Some(Duration::from_millis(100)));
+        assert_eq!(stats.avg().unwrap().as_micros(), 50_500);
+        assert_eq!(stats.jitter().unwrap().as_millis(), 1);
+        assert_eq!(stats.errors(), 1);
+        assert!((stats.loss() - 2.0 / 102.0).abs() < f64::EPSILON);
+
+        let median = stats.median().unwrap().as_secs_f64() * 1000.0;
+        let p95 = stats.p95().unwrap().as_secs_f64() * 1000.0;
+        assert!((median - 50.0).abs() < 2.0, "median was {}", median);
+        assert!((p95 - 95.0).abs() < 2.0, "p95 was {}", p95);
+    }
+}
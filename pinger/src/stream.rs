@@ -0,0 +1,191 @@
+use crate::target::Target;
+use crate::{PingResult, ResultObserver};
+use std::cell::Cell;
+use std::net::IpAddr;
+use std::process::Child;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvError, RecvTimeoutError, TryRecvError};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Shared state between a `PingStream` and the thread producing its results.
+#[derive(Debug, Default)]
+pub(crate) struct PingControl {
+    stopped: AtomicBool,
+    paused: AtomicBool,
+    child: Mutex<Option<Child>>,
+}
+
+impl PingControl {
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn set_child(&self, child: Child) {
+        *self.child.lock().unwrap() = Some(child);
+    }
+
+    pub(crate) fn take_child(&self) -> Option<Child> {
+        self.child.lock().unwrap().take()
+    }
+
+    /// Whether the subprocess is still running, rather than exited or stopped.
+    pub(crate) fn has_child(&self) -> bool {
+        self.child.lock().unwrap().is_some()
+    }
+
+    fn child_id(&self) -> Option<u32> {
+        self.child.lock().unwrap().as_ref().map(Child::id)
+    }
+
+    fn stop(&self) {
+        self.stopped.store(true, Ordering::Release);
+        // Kill and reap the child here rather than leaving it to the reader thread, so that
+        // no process outlives the stream even if the reader is blocked.
+        if let Some(mut child) = self.take_child() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Release);
+        #[cfg(unix)]
+        if let Some(pid) = self.child_id() {
+            let signal = if paused { libc::SIGSTOP } else { libc::SIGCONT };
+            // SAFETY: `pid` belongs to a child we spawned and have not yet reaped.
+            unsafe {
+                libc::kill(pid as libc::pid_t, signal);
+            }
+        }
+    }
+}
+
+/// A running ping, returned from [`ping`](crate::ping).
+///
+/// Iterating over the stream yields results until the underlying ping exits or is stopped.
+/// The stream can also be used to pause, resume or stop the thread or subprocess producing them.
+#[derive(Debug)]
+pub struct PingStream {
+    receiver: Receiver<PingResult>,
+    control: Arc<PingControl>,
+    destination: Cell<Option<IpAddr>>,
+}
+
+impl PingStream {
+    pub(crate) fn new(receiver: Receiver<PingResult>, control: Arc<PingControl>) -> Self {
+        Self {
+            receiver,
+            control,
+            destination: Cell::new(None),
+        }
+    }
+
+    pub(crate) fn with_destination(self, destination: Option<IpAddr>) -> Self {
+        self.destination.set(destination);
+        self
+    }
+
+    /// The address the most recently received result came from, if the target was resolved before
+    /// ping was started. When re-resolving, this changes once [`PingResult::AddressChanged`] is
+    /// received, so it can be read after each result to tell which address that result was for.
+    pub fn destination(&self) -> Option<IpAddr> {
+        self.destination.get()
+    }
+
+    /// Follow the address being pinged as results are received.
+    fn track(&self, result: PingResult) -> PingResult {
+        if let PingResult::AddressChanged(ip) = result {
+            self.destination.set(Some(ip));
+        }
+        result
+    }
+
+    /// Pass every result to `observers` before it reaches the returned stream, which controls the
+    /// same ping as this one.
+    pub(crate) fn observed(
+        self,
+        target: Target,
+        observers: Vec<Arc<dyn ResultObserver>>,
+    ) -> PingStream {
+        let (tx, rx) = mpsc::channel();
+        let control = self.control.clone();
+        let destination = self.destination();
+        thread::spawn(move || {
+            for result in self {
+                for observer in &observers {
+                    observer.on_result(&target, &result);
+                }
+                if tx.send(result).is_err() {
+                    break;
+                }
+            }
+        });
+        PingStream::new(rx, control).with_destination(destination)
+    }
+
+    /// Block until the next result is available.
+    pub fn recv(&self) -> Result<PingResult, RecvError> {
+        self.receiver.recv().map(|result| self.track(result))
+    }
+
+    /// Return the next result if one is available, without blocking.
+    pub fn try_recv(&self) -> Result<PingResult, TryRecvError> {
+        self.receiver.try_recv().map(|result| self.track(result))
+    }
+
+    /// Wait up to `timeout` for the next result.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<PingResult, RecvTimeoutError> {
+        self.receiver
+            .recv_timeout(timeout)
+            .map(|result| self.track(result))
+    }
+
+    /// The process ID of the ping subprocess, if this backend uses one and it is still running.
+    pub fn child_id(&self) -> Option<u32> {
+        self.control.child_id()
+    }
+
+    /// Stop pinging. Any subprocess is killed and reaped, and the stream ends once buffered results
+    /// are consumed. This is called automatically when the stream is dropped.
+    pub fn stop(&self) {
+        self.control.stop();
+    }
+
+    /// Temporarily stop sending probes. Results are not produced until `resume()` is called.
+    pub fn pause(&self) {
+        self.control.set_paused(true);
+    }
+
+    /// Resume a stream previously paused with `pause()`.
+    pub fn resume(&self) {
+        self.control.set_paused(false);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.control.is_paused()
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.control.is_stopped()
+    }
+}
+
+impl Drop for PingStream {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl Iterator for PingStream {
+    type Item = PingResult;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().ok()
+    }
+}
@@ -0,0 +1,76 @@
+use lazy_regex::*;
+use std::time::Duration;
+
+// iputils: "6 packets transmitted, 6 received, 0% packet loss, time 5018ms"
+// BSD, macOS, BusyBox and AIX: "4 packets transmitted, 3 packets received, 25.0% packet loss"
+static PACKETS_RE: Lazy<Regex> = lazy_regex!(
+    r"^(?P<transmitted>\d+) packets transmitted, (?P<received>\d+) (?:packets )?received,.*?(?P<loss>[\d.]+)% packet loss"
+);
+
+// iputils: "rtt min/avg/max/mdev = 106.252/163.821/246.851/58.823 ms"
+// BSD and macOS: "round-trip min/avg/max/stddev = 0.512/6.952/12.345/4.825 ms"
+// BusyBox and AIX: "round-trip min/avg/max = 17.944/18.833/19.319 ms"
+static RTT_RE: Lazy<Regex> = lazy_regex!(
+    r"^(?:rtt|round-trip) min/avg/max(?:/\w+)? = (?P<min>[\d.]+)/(?P<avg>[\d.]+)/(?P<max>[\d.]+)(?:/(?P<deviation>[\d.]+))? ms"
+);
+
+/// The statistics printed by ping when it exits.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PingSummary {
+    pub transmitted: u64,
+    pub received: u64,
+    /// Packet loss as a percentage, between 0 and 100.
+    pub loss: f64,
+    pub rtt: Option<RttSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RttSummary {
+    pub min: Duration,
+    pub avg: Duration,
+    pub max: Duration,
+    /// The mean or standard deviation, depending on the platform. Not reported by all platforms.
+    pub deviation: Option<Duration>,
+}
+
+/// Collects the summary lines from ping output. Lines that aren't part of the summary are ignored.
+#[derive(Debug, Default)]
+pub(crate) struct SummaryParser {
+    summary: Option<PingSummary>,
+}
+
+fn parse_ms(value: &str) -> Option<Duration> {
+    let ms = value.parse::<f64>().ok()?;
+    Some(Duration::from_secs_f64(ms / 1_000f64))
+}
+
+impl SummaryParser {
+    pub(crate) fn feed(&mut self, line: &str) {
+        if let Some(cap) = PACKETS_RE.captures(line) {
+            self.summary = Some(PingSummary {
+                transmitted: cap["transmitted"].parse().unwrap_or_default(),
+                received: cap["received"].parse().unwrap_or_default(),
+                loss: cap["loss"].parse().unwrap_or_default(),
+                rtt: None,
+            });
+        } else if let Some(cap) = RTT_RE.captures(line) {
+            let rtt = (|| {
+                Some(RttSummary {
+                    min: parse_ms(&cap["min"])?,
+                    avg: parse_ms(&cap["avg"])?,
+                    max: parse_ms(&cap["max"])?,
+                    deviation: cap.name("deviation").and_then(|d| parse_ms(d.as_str())),
+                })
+            })();
+            if let Some(summary) = &mut self.summary {
+                summary.rtt = rtt;
+            }
+        }
+    }
+
+    pub(crate) fn finish(self) -> Option<PingSummary> {
+        self.summary
+    }
+}
@@ -0,0 +1,147 @@
+use crate::target::Target;
+use crate::{start_once, PingControl, PingCreationError, PingOptions, PingResult, PingStream};
+use std::net::IpAddr;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How often the supervising thread checks for stop and pause requests when no results arrive.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Restarts are delayed by this much, doubling after each restart that doesn't produce a reply.
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+pub(crate) fn resolve(options: &PingOptions) -> Result<IpAddr, PingCreationError> {
+    options
+        .target
+        .resolve(&options.resolver)
+        .ok()
+        .flatten()
+        .ok_or_else(|| PingCreationError::HostnameError(options.target.to_string()))
+}
+
+/// Start a ping that isn't supervised, to `ip` if it is given.
+fn start_inner(options: &PingOptions, ip: Option<IpAddr>) -> Result<PingStream, PingCreationError> {
+    let mut options = options.clone();
+    if let Some(ip) = ip {
+        options.target = Target::IP(ip);
+    }
+    options.reresolve = None;
+    options.restart = false;
+    start_once(options)
+}
+
+/// Wait out the backoff and start ping again, retrying until it starts. Returns `None` if the
+/// stream is stopped first.
+fn restart(
+    options: &PingOptions,
+    ip: Option<IpAddr>,
+    control: &PingControl,
+    backoff: &mut Duration,
+) -> Option<PingStream> {
+    loop {
+        let restart_at = Instant::now() + *backoff;
+        *backoff = (*backoff * 2).min(MAX_BACKOFF);
+        while Instant::now() < restart_at {
+            if control.is_stopped() {
+                return None;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        if let Ok(stream) = start_inner(options, ip) {
+            return Some(stream);
+        }
+    }
+}
+
+/// Run ping on a background thread that can replace it while it runs:
+///
+/// * With [`PingOptions::with_reresolve`], a hostname target is resolved again on that schedule.
+///   When its address changes the running ping is replaced with one to the new address, and
+///   [`PingResult::AddressChanged`] is sent before its results.
+/// * With [`PingOptions::with_restart`], ping is started again with a backoff when it exits, and
+///   [`PingResult::Restarted`] is sent in place of [`PingResult::PingExited`].
+pub(crate) fn supervise(options: PingOptions) -> Result<PingStream, PingCreationError> {
+    let every = match options.target {
+        Target::Hostname { .. } if options.ssh.is_none() => options.reresolve,
+        _ => None,
+    };
+    let mut current = match every {
+        Some(_) => Some(resolve(&options)?),
+        None => None,
+    };
+    let mut stream = start_inner(&options, current)?;
+    let destination = stream.destination();
+
+    let (tx, rx) = mpsc::channel();
+    let control = Arc::new(PingControl::default());
+    let thread_control = control.clone();
+
+    thread::spawn(move || {
+        let mut next_resolve = every.map(|every| Instant::now() + every);
+        let mut backoff = MIN_BACKOFF;
+        while !thread_control.is_stopped() {
+            if thread_control.is_paused() != stream.is_paused() {
+                if thread_control.is_paused() {
+                    stream.pause();
+                } else {
+                    stream.resume();
+                }
+            }
+
+            let exited = match stream.recv_timeout(POLL_INTERVAL) {
+                Ok(PingResult::PingExited(_, stderr, _)) if options.restart => Some(stderr),
+                Ok(result) => {
+                    if let PingResult::Pong(..) | PingResult::NtpReply(..) = result {
+                        backoff = MIN_BACKOFF;
+                    }
+                    if tx.send(result).is_err() {
+                        break;
+                    }
+                    None
+                }
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) if options.restart => Some(String::new()),
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            if let Some(stderr) = exited {
+                match restart(&options, current, &thread_control, &mut backoff) {
+                    Some(new_stream) => stream = new_stream,
+                    None => break,
+                }
+                if tx.send(PingResult::Restarted(stderr)).is_err() {
+                    break;
+                }
+                continue;
+            }
+
+            let Some(every) = every else {
+                continue;
+            };
+            if next_resolve.is_some_and(|at| Instant::now() < at) {
+                continue;
+            }
+            next_resolve = Some(Instant::now() + every);
+            // Keep pinging the old address if resolution fails, as DNS may be briefly unavailable.
+            let ip = match resolve(&options) {
+                Ok(ip) if Some(ip) != current => ip,
+                _ => continue,
+            };
+            if let Ok(new_stream) = start_inner(&options, Some(ip)) {
+                if thread_control.is_paused() {
+                    new_stream.pause();
+                }
+                // Dropping the old stream stops it, so it doesn't report exiting.
+                stream = new_stream;
+                current = Some(ip);
+                if tx.send(PingResult::AddressChanged(ip)).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(PingStream::new(rx, control).with_destination(destination))
+}
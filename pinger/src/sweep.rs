@@ -0,0 +1,140 @@
+use crate::target::Target;
+use crate::{get_pinger, PingCreationError, PingOptions, PingResult, Pinger};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How many addresses are pinged at once.
+const CONCURRENCY: usize = 32;
+
+// The largest network that can be swept, which is a /16 for IPv4 or a /112 for IPv6.
+const MAX_HOST_BITS: u32 = 16;
+
+// How long to wait for a reply when no timeout is set, on top of the interval.
+const DEFAULT_WAIT: Duration = Duration::from_secs(2);
+
+/// The host addresses in a network written in CIDR notation, such as `192.168.1.0/24`. The
+/// network and broadcast addresses of IPv4 networks are skipped.
+pub(crate) fn addresses(cidr: &str) -> Result<Vec<IpAddr>, PingCreationError> {
+    let invalid = |reason: &str| PingCreationError::InvalidOptions(format!("{cidr:?} {reason}"));
+    let (address, prefix) = cidr
+        .split_once('/')
+        .ok_or_else(|| invalid("is not in CIDR notation"))?;
+    let address: IpAddr = address
+        .parse()
+        .map_err(|_| invalid("does not contain a valid IP address"))?;
+    let bits = if address.is_ipv4() { 32 } else { 128 };
+    let prefix: u32 = prefix
+        .parse()
+        .ok()
+        .filter(|&prefix| prefix <= bits)
+        .ok_or_else(|| invalid("does not have a valid prefix length"))?;
+    let host_bits = bits - prefix;
+    if host_bits > MAX_HOST_BITS {
+        return Err(invalid("is too large to sweep"));
+    }
+
+    let (network, mut hosts) = match address {
+        IpAddr::V4(ip) => (
+            u32::from(ip) as u128 & !((1 << host_bits) - 1),
+            0..1 << host_bits,
+        ),
+        IpAddr::V6(ip) => (u128::from(ip) & !((1 << host_bits) - 1), 0..1 << host_bits),
+    };
+    if address.is_ipv4() && host_bits >= 2 {
+        hosts = hosts.start + 1..hosts.end - 1;
+    }
+    Ok(hosts
+        .map(|host| match address {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from((network + host) as u32)),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(network + host)),
+        })
+        .collect())
+}
+
+/// Ping a single address with a pinger like `pinger` until the first reply or error, which is
+/// reported as a timeout if it doesn't arrive in time.
+fn probe(pinger: &dyn Pinger, options: &PingOptions, ip: IpAddr) -> PingResult {
+    let mut options = options.clone();
+    options.target = Target::IP(ip);
+    let deadline = Instant::now() + options.interval + options.timeout.unwrap_or(DEFAULT_WAIT);
+    let stream = match pinger
+        .with_options(options)
+        .and_then(|pinger| pinger.start())
+    {
+        Ok(stream) => stream,
+        Err(e) => return PingResult::Unknown(e.to_string()),
+    };
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match stream.recv_timeout(remaining) {
+            Ok(PingResult::Unknown(_)) => continue,
+            Ok(result) => return result,
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => {
+                return PingResult::Timeout(format!("No reply from {ip}"))
+            }
+        }
+    }
+}
+
+/// Ping every host address in a network, such as `192.168.1.0/24`, a few at a time. Each address
+/// is pinged until it replies or times out, and the first result is sent with the address it came
+/// from, in the order they arrive. Sweeping stops once the receiver is dropped.
+///
+/// The target in `options` is ignored, as are restarting, re-resolution and observers. The
+/// platform's ping is detected once, so an error creating the pinger is reported upfront.
+pub fn sweep(
+    cidr: &str,
+    options: PingOptions,
+) -> Result<mpsc::Receiver<(IpAddr, PingResult)>, PingCreationError> {
+    let addresses = addresses(cidr)?;
+    let mut detect = options.clone();
+    detect.target = Target::IP(addresses[0]);
+    let pinger = get_pinger(detect)?;
+    let workers = CONCURRENCY.min(addresses.len());
+    let queue = Arc::new(Mutex::new(addresses.into_iter()));
+
+    let (tx, rx) = mpsc::channel();
+    for _ in 0..workers {
+        let queue = queue.clone();
+        let pinger = pinger.clone();
+        let options = options.clone();
+        let tx = tx.clone();
+        thread::spawn(move || loop {
+            let Some(ip) = queue.lock().unwrap().next() else {
+                break;
+            };
+            if tx.send((ip, probe(&*pinger, &options, ip))).is_err() {
+                break;
+            }
+        });
+    }
+    Ok(rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_addresses() {
+        let hosts = addresses("192.168.1.77/24").unwrap();
+        assert_eq!(hosts.len(), 254);
+        assert_eq!(hosts[0], IpAddr::from([192, 168, 1, 1]));
+        assert_eq!(hosts[253], IpAddr::from([192, 168, 1, 254]));
+
+        assert_eq!(
+            addresses("10.0.0.1/32").unwrap(),
+            vec![IpAddr::from([10, 0, 0, 1])]
+        );
+        assert_eq!(addresses("10.0.0.0/31").unwrap().len(), 2);
+        assert_eq!(addresses("fe80::1/126").unwrap().len(), 4);
+
+        assert!(addresses("10.0.0.0").is_err());
+        assert!(addresses("10.0.0.0/33").is_err());
+        assert!(addresses("10.0.0.0/8").is_err());
+        assert!(addresses("example.com/24").is_err());
+    }
+}
@@ -1,18 +1,85 @@
+use crate::dns::{self, RecordType};
 use std::fmt;
 use std::fmt::{Display, Formatter};
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IPVersion {
     V4,
     V6,
     Any,
 }
 
+/// How hostnames are resolved to addresses.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Resolver {
+    /// The operating system's resolver, which is what ping itself uses. With the `mdns` feature,
+    /// `.local` names are resolved with multicast DNS instead, as many systems can't resolve them.
+    #[default]
+    System,
+    /// Query a DNS server directly over UDP, such as `9.9.9.9:53`.
+    Server(SocketAddr),
+    /// Query a DNS-over-HTTPS endpoint, such as `https://dns.quad9.net/dns-query`.
+    #[cfg(feature = "doh")]
+    Https(String),
+}
+
+impl Resolver {
+    /// Look up the addresses of a domain, optionally only those of one IP version.
+    pub fn lookup(&self, domain: &str, version: IPVersion) -> io::Result<Vec<IpAddr>> {
+        let records: &[RecordType] = match version {
+            IPVersion::V4 => &[RecordType::A],
+            IPVersion::V6 => &[RecordType::Aaaa],
+            IPVersion::Any => &[RecordType::A, RecordType::Aaaa],
+        };
+        let mut addresses = vec![];
+        match self {
+            #[cfg(feature = "mdns")]
+            Resolver::System if dns::is_mdns_name(domain) => {
+                for &record in records {
+                    addresses.extend(dns::query_mdns(domain, record)?);
+                }
+            }
+            Resolver::System => {
+                addresses.extend((domain, 0).to_socket_addrs()?.map(|addr| addr.ip()).filter(
+                    |ip| match version {
+                        IPVersion::V4 => ip.is_ipv4(),
+                        IPVersion::V6 => ip.is_ipv6(),
+                        IPVersion::Any => true,
+                    },
+                ))
+            }
+            Resolver::Server(server) => {
+                for &record in records {
+                    addresses.extend(dns::query_server(*server, domain, record)?);
+                }
+            }
+            #[cfg(feature = "doh")]
+            Resolver::Https(url) => {
+                for &record in records {
+                    addresses.extend(dns::query_https(url, domain, record)?);
+                }
+            }
+        }
+        Ok(addresses)
+    }
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Target {
     IP(IpAddr),
-    Hostname { domain: String, version: IPVersion },
+    Hostname {
+        domain: String,
+        version: IPVersion,
+    },
+    /// Ping both the IPv4 and IPv6 address of a hostname. Only usable with `ping_dual_stack`.
+    DualStack {
+        domain: String,
+    },
 }
 
 impl Target {
@@ -20,7 +87,45 @@ impl Target {
         match self {
             Target::IP(ip) => ip.is_ipv6(),
             Target::Hostname { version, .. } => *version == IPVersion::V6,
+            Target::DualStack { .. } => false,
+        }
+    }
+
+    pub fn new_dual_stack(value: impl ToString) -> Self {
+        Self::DualStack {
+            domain: value.to_string(),
+        }
+    }
+
+    /// Resolve the first IPv4 and first IPv6 address of the target.
+    pub fn resolve_dual_stack(
+        &self,
+        resolver: &Resolver,
+    ) -> io::Result<(Option<Ipv4Addr>, Option<Ipv6Addr>)> {
+        let domain = match self {
+            Target::IP(IpAddr::V4(ip)) => return Ok((Some(*ip), None)),
+            Target::IP(IpAddr::V6(ip)) => return Ok((None, Some(*ip))),
+            Target::Hostname { domain, .. } | Target::DualStack { domain } => domain,
+        };
+        let mut ipv4 = None;
+        let mut ipv6 = None;
+        for ip in resolver.lookup(domain, IPVersion::Any)? {
+            match ip {
+                IpAddr::V4(ip) => ipv4 = ipv4.or(Some(ip)),
+                IpAddr::V6(ip) => ipv6 = ipv6.or(Some(ip)),
+            }
         }
+        Ok((ipv4, ipv6))
+    }
+
+    /// Resolve the target to a single address of the requested IP version, if it has one.
+    pub fn resolve(&self, resolver: &Resolver) -> io::Result<Option<IpAddr>> {
+        let (domain, version) = match self {
+            Target::IP(ip) => return Ok(Some(*ip)),
+            Target::Hostname { domain, version } => (domain, *version),
+            Target::DualStack { domain } => (domain, IPVersion::Any),
+        };
+        Ok(resolver.lookup(domain, version)?.into_iter().next())
     }
 
     pub fn new_any(value: impl ToString) -> Self {
@@ -61,7 +166,9 @@ impl Display for Target {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Target::IP(v) => Display::fmt(&v, f),
-            Target::Hostname { domain, .. } => Display::fmt(&domain, f),
+            Target::Hostname { domain, .. } | Target::DualStack { domain } => {
+                Display::fmt(&domain, f)
+            }
         }
     }
 }
@@ -1,14 +1,17 @@
 #[cfg(test)]
 mod tests {
+    use crate::aix::{AixPinger, SequenceTracker};
     use crate::bsd::BSDPinger;
+    use crate::generic::GenericPinger;
     use crate::linux::LinuxPinger;
     use crate::macos::MacOSPinger;
+    use crate::summary::SummaryParser;
     #[cfg(windows)]
-    use crate::windows::WindowsPinger;
-    use crate::{PingOptions, PingResult, Pinger};
+    use crate::windows::parse_windows;
+    use crate::{PingOptions, PingResult, Pinger, SubprocessPinger};
     use anyhow::bail;
     use ntest::timeout;
-    use std::time::Duration;
+    use std::time::{Duration, Instant};
 
     const IS_GHA: bool = option_env!("GITHUB_ACTIONS").is_some();
 
@@ -54,15 +57,23 @@ mod tests {
 
         for message in stream.into_iter().take(3) {
             match message {
-                PingResult::Pong(_, m) | PingResult::Timeout(m) => {
+                PingResult::Pong(_, m, _)
+                | PingResult::Duplicate(_, m)
+                | PingResult::NtpReply(_, _, m)
+                | PingResult::Timeout(m) => {
                     eprintln!("Message: {}", m);
                     success += 1;
                 }
-                PingResult::Unknown(line) => {
-                    eprintln!("Unknown line: {}", line);
+                PingResult::AddressChanged(ip) => eprintln!("Address changed: {}", ip),
+                PingResult::Restarted(stderr) => eprintln!("Restarted: {}", stderr),
+                PingResult::Unreachable(line)
+                | PingResult::TtlExceeded(line)
+                | PingResult::Filtered(line)
+                | PingResult::Unknown(line) => {
+                    eprintln!("Unexpected line: {}", line);
                     errors += 1;
                 }
-                PingResult::PingExited(code, stderr) => {
+                PingResult::PingExited(code, stderr, _) => {
                     bail!("Ping exited with code: {}, stderr: {}", code, stderr);
                 }
             }
@@ -76,13 +87,12 @@ mod tests {
         PingOptions::new("foo".to_string(), Duration::from_secs(1), None)
     }
 
-    fn test_parser<T: Pinger>(contents: &str) {
+    fn test_parser<T: SubprocessPinger>(contents: &str) {
         let pinger = T::from_options(opts()).unwrap();
-        run_parser_test(contents, &pinger);
+        run_parser_test(contents, pinger.parse_fn());
     }
 
-    fn run_parser_test(contents: &str, pinger: &impl Pinger) {
-        let parser = pinger.parse_fn();
+    fn run_parser_test(contents: &str, parser: fn(String) -> Option<PingResult>) {
         let test_file: Vec<&str> = contents.split("-----").collect();
         let input = test_file[0].trim().split('\n');
         let expected: Vec<&str> = test_file[1].trim().split('\n').collect();
@@ -109,11 +119,326 @@ mod tests {
         }
     }
 
+    #[cfg(unix)]
+    struct LoopingPinger;
+
+    #[cfg(unix)]
+    impl Pinger for LoopingPinger {
+        fn from_options(_options: PingOptions) -> Result<Self, crate::PingCreationError> {
+            Ok(Self)
+        }
+
+        fn start(&self) -> Result<crate::PingStream, crate::PingCreationError> {
+            crate::start_subprocess(self)
+        }
+    }
+
+    #[cfg(unix)]
+    impl SubprocessPinger for LoopingPinger {
+        fn parse_fn(&self) -> fn(String) -> Option<PingResult> {
+            |line| Some(PingResult::Unknown(line))
+        }
+
+        fn ping_args(&self) -> (&str, Vec<String>) {
+            (
+                "sh",
+                vec![
+                    "-c".to_string(),
+                    "while true; do echo pong; sleep 0.1; done".to_string(),
+                ],
+            )
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[timeout(10_000)]
+    fn test_drop_kills_child() {
+        let stream = LoopingPinger.start().unwrap();
+        assert!(stream.recv().is_ok());
+        let pid = stream.child_id().expect("No child process") as libc::pid_t;
+        drop(stream);
+        // Signal 0 only checks whether the process exists. A reaped child no longer does.
+        let result = unsafe { libc::kill(pid, 0) };
+        assert_eq!(result, -1, "Child {pid} is still running after drop");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[timeout(10_000)]
+    fn test_observers() {
+        use crate::{CountingObserver, ResultObserver, Target};
+        use std::sync::Arc;
+
+        let target = Target::new_any("foo");
+        let counter = Arc::new(CountingObserver::default());
+        counter.on_result(
+            &target,
+            &PingResult::Pong(Duration::from_millis(1), String::new(), None),
+        );
+        counter.on_result(&target, &PingResult::Timeout(String::new()));
+        counter.on_result(&target, &PingResult::Unknown(String::new()));
+        assert_eq!((counter.pongs(), counter.timeouts()), (1, 1));
+
+        // Observing a stream doesn't stop its results from being consumed or controlled.
+        let stream = LoopingPinger
+            .start()
+            .unwrap()
+            .observed(target, vec![counter]);
+        assert!(matches!(stream.recv(), Ok(PingResult::Unknown(_))));
+        assert!(stream.child_id().is_some());
+        stream.stop();
+        assert!(stream.child_id().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[timeout(10_000)]
+    fn test_restart() {
+        // `true` exits straight away, so every run ends in a restart.
+        let options = PingOptions::new("127.0.0.1", Duration::from_millis(100), None)
+            .with_ping_binary("/bin/true")
+            .with_restart();
+        let stream = crate::ping(options).unwrap();
+        assert!(matches!(stream.recv(), Ok(PingResult::Restarted(_))));
+        stream.stop();
+        assert!(stream.recv().is_err());
+    }
+
+    #[test]
+    fn test_destination() {
+        use crate::stream::PingControl;
+        use crate::PingStream;
+        use std::net::IpAddr;
+        use std::sync::{mpsc, Arc};
+
+        let (old, new) = (IpAddr::from([10, 0, 0, 1]), IpAddr::from([10, 0, 0, 2]));
+        let (tx, rx) = mpsc::channel();
+        let stream =
+            PingStream::new(rx, Arc::new(PingControl::default())).with_destination(Some(old));
+        tx.send(PingResult::Timeout(String::new())).unwrap();
+        tx.send(PingResult::AddressChanged(new)).unwrap();
+        tx.send(PingResult::Timeout(String::new())).unwrap();
+
+        assert!(stream.recv().is_ok());
+        assert_eq!(stream.destination(), Some(old));
+        // Results after an address change came from the new address.
+        assert!(stream.recv().is_ok());
+        assert!(stream.recv().is_ok());
+        assert_eq!(stream.destination(), Some(new));
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        assert!(PingOptions::builder().build().is_err());
+        assert!(PingOptions::builder()
+            .target("foo.com")
+            .interval(Duration::ZERO)
+            .build()
+            .is_err());
+        assert!(PingOptions::builder()
+            .target("foo.com")
+            .ipv4()
+            .ipv6()
+            .build()
+            .is_err());
+        assert!(PingOptions::builder()
+            .target("foo.com")
+            .interface("eth 0")
+            .build()
+            .is_err());
+        assert!(PingOptions::builder()
+            .target("foo.com")
+            .timeout(Duration::ZERO)
+            .build()
+            .is_err());
+
+        let options = PingOptions::builder()
+            .target("foo.com")
+            .ipv6()
+            .interface("eth0")
+            .build()
+            .unwrap();
+        assert!(options.target.is_ipv6());
+        assert_eq!(options.interval, Duration::from_secs(1));
+        assert_eq!(options.interface.as_deref(), Some("eth0"));
+    }
+
+    #[cfg(all(unix, feature = "serde"))]
+    #[test]
+    fn test_serde_roundtrip() {
+        use std::os::unix::process::ExitStatusExt;
+        use std::process::ExitStatus;
+
+        let results = vec![
+            PingResult::Pong(Duration::from_millis(12), "line".to_string(), None),
+            PingResult::Timeout("timeout".to_string()),
+            PingResult::PingExited(
+                ExitStatus::from_raw(256),
+                "stderr".to_string(),
+                Some(summary_of(include_str!("tests/termux.txt"))),
+            ),
+        ];
+        let json = serde_json::to_string(&results).unwrap();
+        let decoded: Vec<PingResult> = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{results:?}"), format!("{decoded:?}"));
+
+        let options: PingOptions =
+            serde_json::from_str(&serde_json::to_string(&opts()).unwrap()).unwrap();
+        assert_eq!(options.target.to_string(), "foo");
+    }
+
+    #[test]
+    fn test_resolve() {
+        use crate::Resolver;
+        let localhost = crate::Target::new_ipv4("localhost")
+            .resolve(&Resolver::System)
+            .unwrap();
+        assert_eq!(localhost, Some("127.0.0.1".parse().unwrap()));
+        let ip = crate::Target::new_any("::1")
+            .resolve(&Resolver::System)
+            .unwrap();
+        assert_eq!(ip, Some("::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_flags() {
+        let flags = crate::PingFlags {
+            dont_fragment: true,
+            numeric: true,
+            mark: Some(7),
+            ..Default::default()
+        };
+        let options = opts().with_flags(flags.clone());
+        let (_, args) = LinuxPinger::IPTools(options.clone()).ping_args();
+        assert_eq!(
+            args,
+            vec!["-O", "-i1.0", "-M", "do", "-n", "-m", "7", "foo"]
+        );
+        assert!(LinuxPinger::BusyBox(options.clone())
+            .ensure_flags_supported()
+            .is_err());
+        assert!(BSDPinger::from_options(options).is_err());
+
+        let options = opts().with_flags(crate::PingFlags {
+            mark: None,
+            ..flags
+        });
+        let (_, args) = BSDPinger::from_options(options).unwrap().ping_args();
+        assert_eq!(args, vec!["-i1.0", "-D", "-n", "foo"]);
+    }
+
+    #[test]
+    fn test_source_and_interface() {
+        let mut options = opts();
+        options.interface = Some("eth0".to_string());
+        let (_, args) = LinuxPinger::IPTools(options.clone()).ping_args();
+        assert_eq!(args, vec!["-O", "-i1.0", "-I", "eth0", "foo"]);
+
+        options.source = Some("10.0.0.2".parse().unwrap());
+        let (_, args) = LinuxPinger::Android(options).ping_args();
+        assert_eq!(args, vec!["-i1.0", "-I", "10.0.0.2", "foo"]);
+    }
+
+    fn summary_of(contents: &str) -> crate::PingSummary {
+        let mut parser = SummaryParser::default();
+        for line in contents.lines() {
+            parser.feed(line);
+        }
+        parser.finish().expect("No summary parsed")
+    }
+
+    #[test]
+    fn test_summary() {
+        let iputils = summary_of(include_str!("tests/termux.txt"));
+        assert_eq!((iputils.transmitted, iputils.received), (5, 4));
+        assert_eq!(iputils.loss, 20.0);
+        let rtt = iputils.rtt.unwrap();
+        assert_eq!(rtt.min, Duration::from_micros(28_923));
+        assert_eq!(rtt.max, Duration::from_micros(1_024_123));
+        assert_eq!(rtt.deviation, Some(Duration::from_micros(430_845)));
+
+        let bsd = summary_of(include_str!("tests/generic.txt"));
+        assert_eq!((bsd.transmitted, bsd.received), (4, 3));
+        assert_eq!(bsd.loss, 25.0);
+        assert_eq!(bsd.rtt.unwrap().avg, Duration::from_micros(6_952));
+
+        let aix = summary_of(include_str!("tests/aix.txt"));
+        assert_eq!(aix.loss, 40.0);
+        let rtt = aix.rtt.unwrap();
+        assert_eq!(rtt.max, Duration::from_millis(12));
+        assert_eq!(rtt.deviation, None);
+
+        let busybox = summary_of(
+            "3 packets transmitted, 3 packets received, 0% packet loss\n\
+             round-trip min/avg/max = 17.944/18.833/19.319 ms",
+        );
+        assert_eq!(busybox.received, 3);
+        assert_eq!(busybox.rtt.unwrap().min, Duration::from_micros(17_944));
+    }
+
     #[test]
     fn macos() {
         test_parser::<MacOSPinger>(include_str!("tests/macos.txt"));
     }
 
+    #[test]
+    fn aix() {
+        test_parser::<AixPinger>(include_str!("tests/aix.txt"));
+    }
+
+    #[test]
+    fn aix_timeouts() {
+        let now = Instant::now();
+        let mut tracker = SequenceTracker::new(Duration::from_secs(1), Duration::from_secs(2), now);
+        let results: Vec<String> = include_str!("tests/aix.txt")
+            .lines()
+            .flat_map(|line| tracker.process(line.to_string(), now))
+            .map(|result| result.to_string())
+            .collect();
+        assert_eq!(results, vec!["0ns", "1ms", "Timeout", "Timeout", "12ms"]);
+    }
+
+    #[test]
+    fn aix_overdue() {
+        let start = Instant::now();
+        let at = |secs: f64| start + Duration::from_secs_f64(secs);
+        let mut tracker =
+            SequenceTracker::new(Duration::from_secs(1), Duration::from_secs(2), start);
+        assert!(tracker.expire(at(2.9)).is_empty());
+        assert_eq!(tracker.expire(at(3.0)).len(), 1);
+        assert_eq!(tracker.expire(at(5.5)).len(), 2);
+
+        // A late reply to a sequence that has timed out doesn't time it out again
+        let late = "64 bytes from 10.0.0.1: icmp_seq=1 ttl=255 time=2500 ms".to_string();
+        let results = tracker.process(late, at(5.5));
+        assert!(matches!(results[..], [PingResult::Pong(..)]));
+        let reply = "64 bytes from 10.0.0.1: icmp_seq=3 ttl=255 time=1 ms".to_string();
+        assert_eq!(tracker.process(reply, at(6.0)).len(), 1);
+        assert!(tracker.expire(at(8.9)).is_empty());
+        let results = tracker.expire(at(9.0));
+        assert!(matches!(&results[..], [PingResult::Timeout(line)] if line == "icmp_seq=4"));
+    }
+
+    #[test]
+    fn generic() {
+        test_parser::<GenericPinger>(include_str!("tests/generic.txt"));
+    }
+
+    #[test]
+    fn generic_comma_decimals() {
+        let line = "64 bytes from 1.1.1.1: icmp_seq=0 ttl=57 time=12,3 ms".to_string();
+        let result = crate::generic::parse_generic(line).unwrap();
+        assert_eq!(result.to_string(), "12.3ms");
+    }
+
+    #[test]
+    fn long_decimals() {
+        let line = "64 bytes from 1.1.1.1: icmp_seq=0 ttl=57 time=12.3456789 ms".to_string();
+        let result = crate::generic::parse_generic(line).unwrap();
+        assert_eq!(result.to_string(), "12.345678ms");
+    }
+
     #[test]
     fn freebsd() {
         test_parser::<BSDPinger>(include_str!("tests/bsd.txt"));
@@ -138,7 +463,7 @@ mod tests {
     fn ubuntu() {
         run_parser_test(
             include_str!("tests/ubuntu.txt"),
-            &LinuxPinger::IPTools(opts()),
+            LinuxPinger::IPTools(opts()).parse_fn(),
         );
     }
 
@@ -146,21 +471,99 @@ mod tests {
     fn debian() {
         run_parser_test(
             include_str!("tests/debian.txt"),
-            &LinuxPinger::IPTools(opts()),
+            LinuxPinger::IPTools(opts()).parse_fn(),
         );
     }
 
+    #[test]
+    fn ubuntu_localized() {
+        run_parser_test(
+            include_str!("tests/ubuntu_de.txt"),
+            LinuxPinger::IPTools(opts()).parse_fn(),
+        );
+    }
+
+    #[test]
+    fn ubuntu_timestamps() {
+        let parser = LinuxPinger::IPTools(opts()).parse_fn();
+        let line = "[1700000000.250000] 64 bytes from 1.1.1.1: icmp_seq=1 ttl=57 time=12.3 ms";
+        match parser(line.to_string()) {
+            Some(PingResult::Pong(duration, _, Some(arrived))) => {
+                assert_eq!(duration, Duration::from_micros(12_300));
+                assert_eq!(
+                    arrived.duration_since(std::time::UNIX_EPOCH).unwrap(),
+                    Duration::from_millis(1_700_000_000_250)
+                );
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+        let timeout = parser("[1700000001.250000] no answer yet for icmp_seq=2".to_string());
+        assert!(matches!(timeout, Some(PingResult::Timeout(_))));
+    }
+
     #[cfg(windows)]
     #[test]
     fn windows() {
-        test_parser::<WindowsPinger>(include_str!("tests/windows.txt"));
+        run_parser_test(include_str!("tests/windows.txt"), parse_windows);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn windows_localized() {
+        run_parser_test(include_str!("tests/windows_de.txt"), parse_windows);
+        run_parser_test(include_str!("tests/windows_fr.txt"), parse_windows);
+    }
+
+    #[test]
+    fn generic_localized() {
+        test_parser::<GenericPinger>(include_str!("tests/windows_de.txt"));
+        test_parser::<GenericPinger>(include_str!("tests/windows_fr.txt"));
     }
 
     #[test]
     fn android() {
         run_parser_test(
             include_str!("tests/android.txt"),
-            &LinuxPinger::BusyBox(opts()),
+            LinuxPinger::Android(opts()).parse_fn(),
+        );
+    }
+
+    #[test]
+    fn termux() {
+        run_parser_test(
+            include_str!("tests/termux.txt"),
+            LinuxPinger::Android(opts()).parse_fn(),
+        );
+    }
+
+    #[test]
+    fn android_args() {
+        let options = PingOptions::new("foo", Duration::from_millis(100), None);
+        let pinger = LinuxPinger::Android(options);
+        let (_, args) = pinger.ping_args();
+        assert_eq!(args, vec!["-i0.2", "foo"]);
+    }
+
+    #[test]
+    fn ssh_args() {
+        let pinger = crate::ssh::SshPinger {
+            host: "user@jump".to_string(),
+            remote: Box::new(LinuxPinger::IPTools(
+                opts().with_raw_arguments(vec!["-p", "it's"]),
+            )),
+        };
+        let (cmd, args) = pinger.ping_args();
+        assert_eq!(cmd, "ssh");
+        assert_eq!(
+            args,
+            vec![
+                "-T",
+                "-o",
+                "BatchMode=yes",
+                "user@jump",
+                "--",
+                r"env LANG=C LC_ALL=C ping -O -i1.0 -p 'it'\''s' foo"
+            ]
         );
     }
 
@@ -168,7 +571,7 @@ mod tests {
     fn alpine() {
         run_parser_test(
             include_str!("tests/alpine.txt"),
-            &LinuxPinger::BusyBox(opts()),
+            LinuxPinger::BusyBox(opts()).parse_fn(),
         );
     }
 }
@@ -0,0 +1,211 @@
+use crate::target::Target;
+use crate::{PingControl, PingCreationError, PingOptions, PingResult, PingStream, Pinger};
+use std::io;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Instant;
+use tungstenite::client::IntoClientRequest;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{HandshakeError, Message, WebSocket};
+
+type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+/// Measures the round trip of WebSocket ping and pong frames to a `ws://` or `wss://` URL. This
+/// follows the same path as application traffic, through any proxies and load balancers that
+/// ICMP bypasses.
+///
+/// One ping frame is sent every interval over a single connection. If the connection fails, the
+/// probe is reported as a timeout and a new connection is made on the next interval.
+pub struct WebSocketPinger {
+    url: String,
+    options: PingOptions,
+}
+
+impl WebSocketPinger {
+    /// Connect to the server, giving up on each step after the timeout rather than waiting for the
+    /// operating system to.
+    fn connect(&self) -> io::Result<Socket> {
+        let timeout = self.options.timeout.unwrap_or(self.options.interval);
+        let request = self
+            .url
+            .as_str()
+            .into_client_request()
+            .map_err(io::Error::other)?;
+        let uri = request.uri();
+        let host = uri.host().unwrap_or_default();
+        let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+            Some("wss") => 443,
+            _ => 80,
+        });
+        let mut last_error =
+            io::Error::new(io::ErrorKind::NotFound, format!("{host} has no addresses"));
+        for addr in (host.trim_start_matches('[').trim_end_matches(']'), port).to_socket_addrs()? {
+            let stream = match TcpStream::connect_timeout(&addr, timeout) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    last_error = e;
+                    continue;
+                }
+            };
+            // Set before the handshake, so that a server that accepts but never answers times out.
+            stream.set_read_timeout(Some(timeout))?;
+            stream.set_write_timeout(Some(timeout))?;
+            return match tungstenite::client_tls(request, stream) {
+                Ok((socket, _)) => Ok(socket),
+                Err(HandshakeError::Failure(e)) => Err(io::Error::other(e)),
+                Err(HandshakeError::Interrupted(_)) => Err(io::ErrorKind::TimedOut.into()),
+            };
+        }
+        Err(last_error)
+    }
+
+    /// Send a ping frame and wait for the matching pong, skipping any other messages.
+    fn probe(&self, socket: &mut Socket, seq: u64) -> io::Result<PingResult> {
+        let payload = seq.to_be_bytes().to_vec();
+        let sent = Instant::now();
+        socket
+            .send(Message::Ping(payload.clone()))
+            .map_err(io::Error::other)?;
+        loop {
+            match socket.read() {
+                Ok(Message::Pong(data)) if data == payload => {
+                    let duration = sent.elapsed();
+                    return Ok(PingResult::Pong(
+                        duration,
+                        format!("pong from {}: seq={seq} time={duration:?}", self.url),
+                        None,
+                    ));
+                }
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(e))
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(PingResult::Timeout(format!(
+                        "no pong from {}: seq={seq}",
+                        self.url
+                    )));
+                }
+                Err(e) => return Err(io::Error::other(e)),
+            }
+        }
+    }
+}
+
+impl Pinger for WebSocketPinger {
+    fn from_options(options: PingOptions) -> Result<Self, PingCreationError>
+    where
+        Self: Sized,
+    {
+        let url = match &options.target {
+            Target::Hostname { domain, .. } if is_websocket_url(domain) => domain.clone(),
+            target => {
+                return Err(PingCreationError::InvalidOptions(format!(
+                    "{target} is not a ws:// or wss:// URL"
+                )))
+            }
+        };
+        Ok(Self { url, options })
+    }
+
+    fn start(&self) -> Result<PingStream, PingCreationError> {
+        // Connect once up front, so that an invalid URL or unreachable server is reported here.
+        let mut socket = Some(self.connect()?);
+        let pinger = Self {
+            url: self.url.clone(),
+            options: self.options.clone(),
+        };
+        let (tx, rx) = mpsc::channel();
+        let control = Arc::new(PingControl::default());
+        let thread_control = control.clone();
+
+        thread::spawn(move || {
+            let interval = pinger.options.interval;
+            let mut seq = 0;
+            while !thread_control.is_stopped() {
+                let started = Instant::now();
+                if !thread_control.is_paused() {
+                    seq += 1;
+                    let result = match socket.take().map_or_else(|| pinger.connect(), Ok) {
+                        Ok(mut connected) => match pinger.probe(&mut connected, seq) {
+                            Ok(result) => {
+                                socket = Some(connected);
+                                result
+                            }
+                            // Dropping the connection makes the next probe reconnect.
+                            Err(e) => PingResult::Timeout(format!(
+                                "connection to {} failed: {e}",
+                                pinger.url
+                            )),
+                        },
+                        Err(e) => {
+                            PingResult::Timeout(format!("could not connect to {}: {e}", pinger.url))
+                        }
+                    };
+                    if tx.send(result).is_err() {
+                        break;
+                    }
+                }
+                thread::sleep(interval.saturating_sub(started.elapsed()));
+            }
+            if let Some(mut socket) = socket {
+                let _ = socket.close(None);
+            }
+        });
+
+        Ok(PingStream::new(rx, control))
+    }
+}
+
+/// Whether a target is a WebSocket URL, which is pinged with [`WebSocketPinger`].
+pub(crate) fn is_websocket_url(target: &str) -> bool {
+    let lowercase = target.to_ascii_lowercase();
+    lowercase.starts_with("ws://") || lowercase.starts_with("wss://")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    #[test]
+    fn test_websocket_pong() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut socket = tungstenite::accept(stream).unwrap();
+            // Reading replies to ping frames automatically.
+            while socket.read().is_ok() {}
+        });
+
+        let options = PingOptions::new(&url, Duration::from_millis(50), None);
+        let stream = crate::ping(options).unwrap();
+        assert!(matches!(stream.recv(), Ok(PingResult::Pong(..))));
+        assert!(crate::ping(PingOptions::new(
+            "ws://127.0.0.1:1/",
+            Duration::from_secs(1),
+            None
+        ))
+        .is_err());
+    }
+
+    #[test]
+    #[ntest::timeout(5000)]
+    fn test_websocket_handshake_timeout() {
+        // A server that accepts connections but never answers the handshake.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let _connections = listener.incoming().collect::<Vec<_>>();
+        });
+
+        let options = PingOptions::new(&url, Duration::from_secs(60), None)
+            .with_timeout(Duration::from_millis(100));
+        assert!(crate::ping(options).is_err());
+    }
+}
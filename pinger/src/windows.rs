@@ -1,12 +1,28 @@
-use crate::target::{IPVersion, Target};
-use crate::PingCreationError;
-use crate::{extract_regex, PingOptions, PingResult, Pinger};
+use crate::locale::parse_localized;
+use crate::target::Target;
+use crate::{extract_regex, PingControl, PingOptions, PingResult, PingStream, Pinger};
+use crate::{PingCreationError, PingFlag};
 use lazy_regex::*;
-use std::net::{IpAddr, ToSocketAddrs};
-use std::sync::mpsc;
-use std::thread;
+use std::net::{IpAddr, Ipv6Addr};
+use std::sync::{mpsc, Arc};
 use std::time::Duration;
-use winping::{Buffer, Pinger as WinPinger};
+use std::{io, mem, ptr, thread};
+use windows_sys::Win32::Foundation::{
+    CloseHandle, GetLastError, ERROR_HOST_UNREACHABLE, ERROR_IO_PENDING, ERROR_NETWORK_UNREACHABLE,
+    ERROR_PROTOCOL_UNREACHABLE, HANDLE, INVALID_HANDLE_VALUE,
+};
+use windows_sys::Win32::NetworkManagement::IpHelper::{
+    Icmp6CreateFile, Icmp6ParseReplies, Icmp6SendEcho2, IcmpCloseHandle, IcmpCreateFile,
+    IcmpParseReplies, IcmpSendEcho2Ex, ICMPV6_ECHO_REPLY_LH, ICMP_ECHO_REPLY,
+    IP_DEST_HOST_UNREACHABLE, IP_DEST_NET_UNREACHABLE, IP_DEST_PORT_UNREACHABLE,
+    IP_DEST_PROT_UNREACHABLE, IP_DEST_UNREACHABLE, IP_FLAG_DF, IP_OPTION_INFORMATION,
+    IP_PACKET_TOO_BIG, IP_REQ_TIMED_OUT, IP_SUCCESS, IP_TTL_EXPIRED_REASSEM,
+    IP_TTL_EXPIRED_TRANSIT,
+};
+use windows_sys::Win32::Networking::WinSock::{AF_INET6, SOCKADDR_IN6};
+use windows_sys::Win32::System::Threading::{
+    CreateEventW, SetEvent, WaitForSingleObject, INFINITE,
+};
 
 pub static RE: Lazy<Regex> = lazy_regex!(r"(?ix-u)time=(?P<ms>\d+)(?:\.(?P<ns>\d+))?");
 
@@ -14,79 +30,431 @@ pub struct WindowsPinger {
     options: PingOptions,
 }
 
-impl Pinger for WindowsPinger {
-    fn from_options(options: PingOptions) -> Result<Self, PingCreationError> {
-        Ok(Self { options })
+/// Parse a line printed by `ping.exe`. Probes are sent with the ICMP API rather than ping.exe, so
+/// this is only needed for its output captured elsewhere.
+pub fn parse_windows(line: String) -> Option<PingResult> {
+    if line.contains("timed out") || line.contains("failure") {
+        return Some(PingResult::Timeout(line));
     }
-
-    fn parse_fn(&self) -> fn(String) -> Option<PingResult> {
-        |line| {
-            if line.contains("timed out") || line.contains("failure") {
-                return Some(PingResult::Timeout(line));
-            }
-            extract_regex(&RE, line)
-        }
+    if !RE.is_match(&line) {
+        return parse_localized(line);
     }
+    extract_regex(&RE, line)
+}
 
-    fn ping_args(&self) -> (&str, Vec<String>) {
-        unimplemented!("ping_args for WindowsPinger is not implemented")
+impl Pinger for WindowsPinger {
+    fn from_options(options: PingOptions) -> Result<Self, PingCreationError> {
+        options
+            .flags
+            .ensure_supported("Windows", &[PingFlag::DontFragment])?;
+        Ok(Self { options })
     }
 
-    fn start(&self) -> Result<mpsc::Receiver<PingResult>, PingCreationError> {
+    fn start(&self) -> Result<PingStream, PingCreationError> {
         let interval = self.options.interval;
         let parsed_ip = match &self.options.target {
-            Target::IP(ip) => ip.clone(),
-            Target::Hostname { domain, version } => {
-                let ips = (domain.as_str(), 0).to_socket_addrs()?;
-                let selected_ips: Vec<_> = if *version == IPVersion::Any {
-                    ips.collect()
-                } else {
-                    ips.into_iter()
-                        .filter(|addr| {
-                            if *version == IPVersion::V6 {
-                                matches!(addr.ip(), IpAddr::V6(_))
-                            } else {
-                                matches!(addr.ip(), IpAddr::V4(_))
-                            }
-                        })
-                        .collect()
-                };
-                if selected_ips.is_empty() {
-                    return Err(PingCreationError::HostnameError(domain.clone()).into());
-                }
-                selected_ips[0].ip()
+            Target::DualStack { domain } => {
+                return Err(PingCreationError::HostnameError(domain.clone()))
             }
+            target => target
+                .resolve(&self.options.resolver)?
+                .ok_or_else(|| PingCreationError::HostnameError(target.to_string()))?,
+        };
+        // The ICMP API can only bind to an address, so an interface is resolved to one of its own.
+        let source = match (self.options.source, &self.options.interface) {
+            (Some(source), _) => Some(source),
+            (None, Some(interface)) => Some(interface_address(interface, parsed_ip.is_ipv6())?),
+            (None, None) => None,
+        };
+        let source = source
+            .map(|source| check_source(source, parsed_ip))
+            .transpose()?;
+
+        // The handle is shared by every probe. If it couldn't be opened, each probe fails and is
+        // reported as a result rather than as a panic.
+        let handle = Arc::new(IcmpHandle::open(parsed_ip.is_ipv6()));
+        let request = Request {
+            source,
+            destination: parsed_ip,
+            timeout: self
+                .options
+                .timeout
+                .map_or(DEFAULT_TIMEOUT, |timeout| timeout.as_millis() as u32),
+            dont_fragment: self.options.flags.dont_fragment,
         };
 
         let (tx, rx) = mpsc::channel();
+        let (probe_tx, probe_rx) = mpsc::channel();
+        let control = Arc::new(PingControl::default());
+        let thread_control = control.clone();
+        let reply_control = control.clone();
 
+        // Probes are sent without waiting for their replies, so a slow reply doesn't delay the
+        // next probe and the timeout is independent of the interval.
         thread::spawn(move || {
-            let pinger = WinPinger::new().expect("Failed to create a WinPinger instance");
-            let mut buffer = Buffer::new();
-            loop {
-                match pinger.send(parsed_ip.clone(), &mut buffer) {
-                    Ok(rtt) => {
-                        if tx
-                            .send(PingResult::Pong(
-                                Duration::from_millis(rtt as u64),
-                                "".to_string(),
-                            ))
-                            .is_err()
-                        {
-                            break;
-                        }
-                    }
-                    Err(_) => {
-                        // Fuck it. All errors are timeouts. Why not.
-                        if tx.send(PingResult::Timeout("".to_string())).is_err() {
-                            break;
-                        }
-                    }
+            while !thread_control.is_stopped() {
+                if thread_control.is_paused() {
+                    thread::sleep(interval);
+                    continue;
+                }
+                if probe_tx.send(Probe::send(&handle, &request)).is_err() {
+                    break;
                 }
                 thread::sleep(interval);
             }
         });
+        // Every probe has the same timeout, so they finish in the order they were sent.
+        thread::spawn(move || {
+            for probe in probe_rx {
+                let reply = probe.and_then(|mut probe| probe.wait());
+                if reply_control.is_stopped() || tx.send(to_ping_result(parsed_ip, reply)).is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        Ok(PingStream::new(rx, control))
+    }
+}
+
+/// How long to wait for a reply, in milliseconds, if no timeout is given.
+const DEFAULT_TIMEOUT: u32 = 2000;
+
+/// The data sent with each echo request, which is what ping.exe sends.
+const REQUEST_DATA: &[u8; 32] = b"abcdefghijklmnopqrstuvwabcdefghi";
+
+/// Room for a reply, the data it echoes, an ICMP error and an `IO_STATUS_BLOCK`.
+const REPLY_SIZE: usize = mem::size_of::<ICMP_ECHO_REPLY>() + REQUEST_DATA.len() + 8 + 16;
+
+/// The TTL of each echo request, which is what ping.exe uses.
+const REQUEST_TTL: u8 = 128;
+
+/// An ICMP handle for one IP version, closed once the last probe sent with it is answered.
+struct IcmpHandle(HANDLE);
+
+// The handle is only used by the ICMP API, which can be called from any thread.
+unsafe impl Send for IcmpHandle {}
+unsafe impl Sync for IcmpHandle {}
+
+impl IcmpHandle {
+    fn open(ipv6: bool) -> Self {
+        Self(unsafe {
+            match ipv6 {
+                true => Icmp6CreateFile(),
+                false => IcmpCreateFile(),
+            }
+        })
+    }
+}
+
+impl Drop for IcmpHandle {
+    fn drop(&mut self) {
+        if self.0 != INVALID_HANDLE_VALUE {
+            unsafe { IcmpCloseHandle(self.0) };
+        }
+    }
+}
+
+/// Where each probe is sent from and to, and how.
+struct Request {
+    source: Option<IpAddr>,
+    destination: IpAddr,
+    timeout: u32,
+    dont_fragment: bool,
+}
+
+/// `ICMP_ECHO_REPLY32`, which is what an asynchronous request is answered with, on 64-bit
+/// Windows too. Its pointers are 32 bits wide, unlike those of the windows-sys definition.
+#[repr(C)]
+struct EchoReply32 {
+    address: u32,
+    status: u32,
+    round_trip_time: u32,
+    data_size: u16,
+    reserved: u16,
+    data: u32,
+    ttl: u8,
+    tos: u8,
+    flags: u8,
+    options_size: u8,
+    options_data: u32,
+}
+
+/// An echo reply, with its TTL. ICMPv6 replies don't carry their hop limit.
+struct Reply {
+    rtt: u32,
+    ttl: Option<u8>,
+}
+
+/// An echo request that is waiting for its reply, which is written to `reply`.
+struct Probe {
+    _handle: Arc<IcmpHandle>,
+    event: HANDLE,
+    ipv6: bool,
+    reply: Vec<u64>,
+}
 
-        Ok(rx)
+// The event and the reply buffer belong to the probe until it's dropped.
+unsafe impl Send for Probe {}
+
+impl Probe {
+    /// Send an echo request, or return the error code if it couldn't be sent.
+    fn send(handle: &Arc<IcmpHandle>, request: &Request) -> Result<Self, u32> {
+        let mut reply = vec![0u64; REPLY_SIZE / 8 + 1];
+        let reply_size = (reply.len() * 8) as u32;
+        let options = IP_OPTION_INFORMATION {
+            Ttl: REQUEST_TTL,
+            Tos: 0,
+            Flags: match request.dont_fragment {
+                true => IP_FLAG_DF as u8,
+                false => 0,
+            },
+            OptionsSize: 0,
+            OptionsData: ptr::null_mut(),
+        };
+        // A manual reset event stays signaled, so the probe can be waited for more than once.
+        let event = unsafe { CreateEventW(ptr::null(), 1, 0, ptr::null()) };
+        if event.is_null() {
+            return Err(unsafe { GetLastError() });
+        }
+        let sent = unsafe {
+            match (request.source, request.destination) {
+                (source, IpAddr::V6(destination)) => {
+                    let source = match source {
+                        Some(IpAddr::V6(source)) => source,
+                        _ => Ipv6Addr::UNSPECIFIED,
+                    };
+                    Icmp6SendEcho2(
+                        handle.0,
+                        event,
+                        None,
+                        ptr::null(),
+                        &to_sockaddr(source),
+                        &to_sockaddr(destination),
+                        REQUEST_DATA.as_ptr().cast(),
+                        REQUEST_DATA.len() as u16,
+                        &options,
+                        reply.as_mut_ptr().cast(),
+                        reply_size,
+                        request.timeout,
+                    )
+                }
+                (source, IpAddr::V4(destination)) => {
+                    // An unspecified source lets Windows choose one.
+                    let source = match source {
+                        Some(IpAddr::V4(source)) => u32::from_ne_bytes(source.octets()),
+                        _ => 0,
+                    };
+                    IcmpSendEcho2Ex(
+                        handle.0,
+                        event,
+                        None,
+                        ptr::null(),
+                        source,
+                        u32::from_ne_bytes(destination.octets()),
+                        REQUEST_DATA.as_ptr().cast(),
+                        REQUEST_DATA.len() as u16,
+                        &options,
+                        reply.as_mut_ptr().cast(),
+                        reply_size,
+                        request.timeout,
+                    )
+                }
+            }
+        };
+        if sent == 0 {
+            let error = unsafe { GetLastError() };
+            if error != ERROR_IO_PENDING {
+                unsafe { CloseHandle(event) };
+                return Err(error);
+            }
+        } else {
+            // The reply is already in the buffer.
+            unsafe { SetEvent(event) };
+        }
+        Ok(Self {
+            _handle: handle.clone(),
+            event,
+            ipv6: request.destination.is_ipv6(),
+            reply,
+        })
+    }
+
+    /// Block the current thread until the probe has its reply or times out.
+    fn wait(&mut self) -> Result<Reply, u32> {
+        let buffer = self.reply.as_mut_ptr().cast();
+        let size = (self.reply.len() * 8) as u32;
+        unsafe {
+            WaitForSingleObject(self.event, INFINITE);
+            if self.ipv6 {
+                if Icmp6ParseReplies(buffer, size) == 0 {
+                    return Err(GetLastError());
+                }
+                let reply = &*(buffer as *const ICMPV6_ECHO_REPLY_LH);
+                match reply.Status {
+                    IP_SUCCESS => Ok(Reply {
+                        rtt: reply.RoundTripTime,
+                        ttl: None,
+                    }),
+                    status => Err(status),
+                }
+            } else {
+                if IcmpParseReplies(buffer, size) == 0 {
+                    return Err(GetLastError());
+                }
+                let reply = &*(buffer as *const EchoReply32);
+                match reply.status {
+                    IP_SUCCESS => Ok(Reply {
+                        rtt: reply.round_trip_time,
+                        ttl: Some(reply.ttl),
+                    }),
+                    status => Err(status),
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Probe {
+    fn drop(&mut self) {
+        // The reply buffer can't be freed while the request is still pending.
+        unsafe {
+            WaitForSingleObject(self.event, INFINITE);
+            CloseHandle(self.event);
+        }
+    }
+}
+
+fn to_sockaddr(ip: Ipv6Addr) -> SOCKADDR_IN6 {
+    let mut sockaddr: SOCKADDR_IN6 = unsafe { mem::zeroed() };
+    sockaddr.sin6_family = AF_INET6;
+    sockaddr.sin6_addr.u.Byte = ip.octets();
+    sockaddr
+}
+
+/// Check that a probe's source is the same IP version as its destination.
+fn check_source(src: IpAddr, dst: IpAddr) -> Result<IpAddr, PingCreationError> {
+    match src.is_ipv6() == dst.is_ipv6() {
+        true => Ok(src),
+        false => Err(PingCreationError::InvalidOptions(format!(
+            "source address {src} is not the same IP version as the target {dst}"
+        ))),
+    }
+}
+
+/// Find an address of the given family on an interface, matched by its friendly name
+/// (such as "Ethernet") or its adapter name.
+fn interface_address(interface: &str, ipv6: bool) -> Result<IpAddr, PingCreationError> {
+    let adapters = ipconfig::get_adapters().map_err(|e| {
+        PingCreationError::InvalidOptions(format!("could not list network interfaces: {e}"))
+    })?;
+    let adapter = adapters
+        .iter()
+        .find(|adapter| {
+            adapter.friendly_name().eq_ignore_ascii_case(interface)
+                || adapter.adapter_name().eq_ignore_ascii_case(interface)
+        })
+        .ok_or_else(|| {
+            PingCreationError::InvalidOptions(format!("interface {interface:?} does not exist"))
+        })?;
+    adapter
+        .ip_addresses()
+        .iter()
+        .copied()
+        .find(|ip| ip.is_ipv6() == ipv6)
+        .ok_or_else(|| {
+            let family = if ipv6 { "IPv6" } else { "IPv4" };
+            PingCreationError::InvalidOptions(format!(
+                "interface {interface:?} has no {family} address"
+            ))
+        })
+}
+
+/// What ping.exe prints for an IP status or a Windows error code.
+fn describe(code: u32) -> String {
+    match code {
+        IP_REQ_TIMED_OUT => "Request timed out.",
+        IP_DEST_NET_UNREACHABLE | ERROR_NETWORK_UNREACHABLE => "Destination net unreachable.",
+        IP_DEST_HOST_UNREACHABLE | ERROR_HOST_UNREACHABLE => "Destination host unreachable.",
+        IP_DEST_PROT_UNREACHABLE | ERROR_PROTOCOL_UNREACHABLE => {
+            "Destination protocol unreachable."
+        }
+        IP_DEST_PORT_UNREACHABLE => "Destination port unreachable.",
+        IP_DEST_UNREACHABLE => "Destination unreachable.",
+        IP_PACKET_TOO_BIG => "Packet needs to be fragmented but DF set.",
+        IP_TTL_EXPIRED_TRANSIT => "TTL expired in transit.",
+        IP_TTL_EXPIRED_REASSEM => "TTL expired during reassembly.",
+        code => return io::Error::from_raw_os_error(code as i32).to_string(),
+    }
+    .to_string()
+}
+
+/// Convert the result of a single echo request into a `PingResult`. Lines are formatted like
+/// those printed by `ping.exe`, so that they carry the same information as on other platforms.
+fn to_ping_result(ip: IpAddr, reply: Result<Reply, u32>) -> PingResult {
+    match reply {
+        Ok(Reply { rtt, ttl }) => {
+            let ttl = ttl.map(|ttl| format!(" TTL={ttl}")).unwrap_or_default();
+            PingResult::Pong(
+                Duration::from_millis(rtt as u64),
+                format!(
+                    "Reply from {ip}: bytes={} time={rtt}ms{ttl}",
+                    REQUEST_DATA.len()
+                ),
+                None,
+            )
+        }
+        Err(IP_REQ_TIMED_OUT) => PingResult::Timeout(describe(IP_REQ_TIMED_OUT)),
+        Err(
+            code @ (IP_DEST_NET_UNREACHABLE
+            | IP_DEST_HOST_UNREACHABLE
+            | IP_DEST_PROT_UNREACHABLE
+            | IP_DEST_PORT_UNREACHABLE
+            | IP_DEST_UNREACHABLE
+            | IP_PACKET_TOO_BIG
+            | ERROR_NETWORK_UNREACHABLE
+            | ERROR_HOST_UNREACHABLE
+            | ERROR_PROTOCOL_UNREACHABLE),
+        ) => PingResult::Unreachable(format!("Reply from {ip}: {}", describe(code))),
+        Err(code @ (IP_TTL_EXPIRED_TRANSIT | IP_TTL_EXPIRED_REASSEM)) => {
+            PingResult::TtlExceeded(format!("Reply from {ip}: {}", describe(code)))
+        }
+        // Anything else is a failure of the ICMP API itself rather than an answer to the probe.
+        Err(code) => PingResult::Unknown(format!("PING: transmit failed. {}", describe(code))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_ping_result() {
+        let ip = IpAddr::from([192, 0, 2, 1]);
+        let reply = Reply {
+            rtt: 12,
+            ttl: Some(57),
+        };
+        match to_ping_result(ip, Ok(reply)) {
+            PingResult::Pong(rtt, line, _) => {
+                assert_eq!(rtt, Duration::from_millis(12));
+                assert_eq!(line, "Reply from 192.0.2.1: bytes=32 time=12ms TTL=57");
+                assert!(matches!(parse_windows(line), Some(PingResult::Pong(..))));
+            }
+            result => panic!("{:?}", result),
+        }
+        assert!(matches!(
+            to_ping_result(ip, Err(IP_REQ_TIMED_OUT)),
+            PingResult::Timeout(_)
+        ));
+        assert!(matches!(
+            to_ping_result(ip, Err(IP_DEST_HOST_UNREACHABLE)),
+            PingResult::Unreachable(_)
+        ));
+        assert!(matches!(
+            to_ping_result(ip, Err(IP_TTL_EXPIRED_TRANSIT)),
+            PingResult::TtlExceeded(_)
+        ));
     }
 }